@@ -0,0 +1,41 @@
+/*
+ * C API of near-old-stations. Build with `cargo build --release --features ffi`.
+ *
+ * Config file and dump caches are the same as the command line tool.
+ * Strings returned from this API must be freed by nos_string_free.
+ */
+#ifndef NEAR_OLD_STATIONS_H
+#define NEAR_OLD_STATIONS_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct NosSearcher NosSearcher;
+
+/* Load config file and stations dump. Returns NULL on error. */
+NosSearcher *nos_searcher_load(void);
+
+/* Free searcher returned by nos_searcher_load. */
+void nos_searcher_free(NosSearcher *searcher);
+
+/*
+ * Search around coordinates and return results as JSON:
+ *   {"origin": "x,y,z", "total": N, "records": [{"system", "station", "distance", "days", "category"}, ...]}
+ * limit 0 means max_entries in config. Returns NULL on error.
+ */
+char *nos_search_at(const NosSearcher *searcher, double x, double y, double z, size_t limit);
+
+/* Message of the last error in this thread, or NULL if no error. */
+char *nos_last_error(void);
+
+/* Free string returned by this API. */
+void nos_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
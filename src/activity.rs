@@ -0,0 +1,130 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "schemars")]
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::Serialize;
+use serde_json::to_string;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::coords::Coords;
+use crate::journal::Location;
+use crate::searcher::Record;
+use crate::stations::Station;
+
+/// JSON Schema of each line in activity log.
+#[cfg(feature = "schemars")]
+pub fn schema() -> RootSchema {
+    schema_for!(Entry<'static>)
+}
+
+/// Append-only NDJSON log of searches and docks.
+#[derive(Debug, Default, Clone)]
+pub struct ActivityLog {
+    path: Option<PathBuf>,
+}
+
+impl ActivityLog {
+    pub fn new<P: AsRef<Path>>(path: P) -> ActivityLog {
+        ActivityLog {
+            path: Some(path.as_ref().to_owned()),
+        }
+    }
+
+    /// Log which records nothing.
+    pub fn disabled() -> ActivityLog {
+        ActivityLog { path: None }
+    }
+
+    pub fn search(&self, location: &Location, top: Option<&Record>) -> Result<(), Fail> {
+        self.append(&Entry::Search {
+            time: Utc::now().to_rfc3339(),
+            origin: &location.star_system,
+            coords: location.star_pos,
+            top: top.map(|r| Top {
+                system: &r.station.system_name,
+                station: &r.station.name,
+                distance: r.distance,
+                days: r.outdated().map(|o| o.days),
+            }),
+        })
+    }
+
+    pub fn roll(&self, location: &Location, record: &Record) -> Result<(), Fail> {
+        self.append(&Entry::Roll {
+            time: Utc::now().to_rfc3339(),
+            origin: &location.star_system,
+            pick: Top {
+                system: &record.station.system_name,
+                station: &record.station.name,
+                distance: record.distance,
+                days: record.outdated().map(|o| o.days),
+            },
+        })
+    }
+
+    pub fn dock(
+        &self,
+        market_id: u64,
+        time: DateTime<Utc>,
+        station: Option<&Station>,
+    ) -> Result<(), Fail> {
+        self.append(&Entry::Dock {
+            time: time.to_rfc3339(),
+            market_id,
+            system: station.map(|st| st.system_name.as_str()),
+            station: station.map(|st| st.name.as_str()),
+        })
+    }
+
+    fn append(&self, entry: &Entry) -> Result<(), Fail> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        let line = to_string(entry).err_msg("can't encode activity log")?;
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .err_msg(format!("can't open file: {:?}", path))?;
+        writeln!(f, "{}", line).err_msg("can't write activity log")?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Entry<'a> {
+    Search {
+        time: String,
+        origin: &'a str,
+        coords: Coords,
+        top: Option<Top<'a>>,
+    },
+    Roll {
+        time: String,
+        origin: &'a str,
+        pick: Top<'a>,
+    },
+    Dock {
+        time: String,
+        market_id: u64,
+        system: Option<&'a str>,
+        station: Option<&'a str>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+struct Top<'a> {
+    system: &'a str,
+    station: &'a str,
+    distance: f64,
+    days: Option<i64>,
+}
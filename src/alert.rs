@@ -0,0 +1,69 @@
+use std::cell::RefCell;
+use std::io::{stdout, Write};
+use std::process::Command;
+use std::thread;
+
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::journal::Location;
+use crate::searcher::Record;
+
+/// Sound played when a top target is within a single jump, right after arriving in another system.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    jump_range: f64,
+    top: usize,
+    /// Program and arguments to play sound. Terminal bell if empty.
+    command: Vec<String>,
+    last_system: RefCell<Option<String>>,
+}
+
+impl Alert {
+    pub fn new(jump_range: f64, top: usize, command: Vec<String>) -> Alert {
+        Alert {
+            jump_range,
+            top,
+            command,
+            last_system: RefCell::new(None),
+        }
+    }
+
+    /// Check records searched at `location`. The first search only records the system.
+    pub fn searched(&self, location: &Location, records: &[Record]) -> Result<(), Fail> {
+        let jumped = {
+            let mut last = self.last_system.borrow_mut();
+            let jumped = last
+                .as_ref()
+                .is_some_and(|system| *system != location.star_system);
+            *last = Some(location.star_system.clone());
+            jumped
+        };
+
+        let reachable = records
+            .iter()
+            .take(self.top)
+            .any(|r| r.distance <= self.jump_range);
+        if jumped && reachable {
+            self.play()?;
+        }
+        Ok(())
+    }
+
+    fn play(&self) -> Result<(), Fail> {
+        match self.command.split_first() {
+            Some((program, args)) => {
+                let mut child = Command::new(program)
+                    .args(args)
+                    .spawn()
+                    .err_msg(format!("can't run alert command: {}", program))?;
+                // Don't block search while sound is playing, but reap the process.
+                thread::spawn(move || child.wait());
+            }
+            None => {
+                print!("\x07");
+                stdout().flush()?;
+            }
+        }
+        Ok(())
+    }
+}
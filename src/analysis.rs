@@ -0,0 +1,193 @@
+use std::fmt;
+
+use crate::coords::Coords;
+use crate::i18n::{tr, trf, Msg};
+use crate::searcher::Record;
+
+/// Horizontal direction in galactic plane, relative to origin.
+///
+/// Galactic core is toward +z, and the galaxy rotates toward -x around Sol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Coreward,
+    Rimward,
+    Spinward,
+    Antispinward,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Coreward,
+        Direction::Rimward,
+        Direction::Spinward,
+        Direction::Antispinward,
+    ];
+
+    /// Direction from `origin` to `target`, by dominant horizontal axis.
+    pub fn between(origin: Coords, target: Coords) -> Direction {
+        let d = target.relative_to(origin);
+        let (dx, dz) = (d.x(), d.z());
+        if dz.abs() >= dx.abs() {
+            if dz >= 0.0 {
+                Direction::Coreward
+            } else {
+                Direction::Rimward
+            }
+        } else if dx < 0.0 {
+            Direction::Spinward
+        } else {
+            Direction::Antispinward
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Direction::Coreward => 0,
+            Direction::Rimward => 1,
+            Direction::Spinward => 2,
+            Direction::Antispinward => 3,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Direction::Coreward => tr(Msg::Coreward),
+            Direction::Rimward => tr(Msg::Rimward),
+            Direction::Spinward => tr(Msg::Spinward),
+            Direction::Antispinward => tr(Msg::Antispinward),
+        }
+    }
+}
+
+/// Counts of stations binned by direction and distance band.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectionSummary {
+    band_width: f64,
+    /// `bands[band][direction]` is (count, sum of outdated days).
+    bands: Vec<[(usize, i64); 4]>,
+}
+
+impl DirectionSummary {
+    /// `band_width` must be positive. Records with non-finite distance are ignored.
+    pub fn new(records: &[Record], origin: Coords, band_width: f64) -> DirectionSummary {
+        assert!(band_width > 0.0, "band width must be positive");
+
+        let mut bands: Vec<[(usize, i64); 4]> = Vec::new();
+        for r in records {
+            if !r.distance.is_finite() {
+                continue;
+            }
+            let band = (r.distance.max(0.0) / band_width) as usize;
+            if bands.len() <= band {
+                bands.resize(band + 1, [(0, 0); 4]);
+            }
+            let bin = &mut bands[band][Direction::between(origin, r.station.coords).index()];
+            bin.0 += 1;
+            bin.1 += r.outdated().map_or(0, |o| o.days);
+        }
+
+        DirectionSummary { band_width, bands }
+    }
+
+    pub fn count(&self, band: usize, direction: Direction) -> usize {
+        self.bands
+            .get(band)
+            .map_or(0, |bins| bins[direction.index()].0)
+    }
+
+    /// Band and direction with most stations. Ties are broken by sum of outdated days.
+    pub fn most_neglected(&self) -> Option<(usize, Direction)> {
+        let mut best: Option<(usize, Direction, (usize, i64))> = None;
+        for (band, bins) in self.bands.iter().enumerate() {
+            for &dir in &Direction::ALL {
+                let bin = bins[dir.index()];
+                if bin.0 > 0 && best.is_none_or(|(_, _, b)| bin > b) {
+                    best = Some((band, dir, bin));
+                }
+            }
+        }
+        best.map(|(band, dir, _)| (band, dir))
+    }
+}
+
+impl fmt::Display for DirectionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>15}", "Ly")?;
+        for dir in &Direction::ALL {
+            write!(f, " {:>13}", dir.name())?;
+        }
+        writeln!(f)?;
+
+        for (band, bins) in self.bands.iter().enumerate() {
+            let from = band as f64 * self.band_width;
+            write!(f, "{:>6.0} - {:>6.0}", from, from + self.band_width)?;
+            for bin in bins {
+                write!(f, " {:>13}", bin.0)?;
+            }
+            writeln!(f)?;
+        }
+
+        if let Some((band, dir)) = self.most_neglected() {
+            let center = (band as f64 + 0.5) * self.band_width;
+            writeln!(
+                f,
+                "{}",
+                trf(
+                    Msg::MostNeglected,
+                    &[
+                        &self.count(band, dir),
+                        &format!("{:.0}", center),
+                        &dir.name()
+                    ]
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::searcher::{new_record, Coverage};
+    use crate::stations::Station;
+    use crate::visited::Visited;
+
+    fn summary(distances: &[f64]) -> DirectionSummary {
+        let st = Station::for_test(0, None);
+        let records: Vec<Record> = distances
+            .iter()
+            .map(|&d| {
+                let mut r = new_record(
+                    &st,
+                    Coords::zero(),
+                    &Visited::new(),
+                    Coverage::default(),
+                    Utc::now(),
+                );
+                r.distance = d;
+                r
+            })
+            .collect();
+        DirectionSummary::new(&records, Coords::zero(), 10.0)
+    }
+
+    #[test]
+    fn records_at_first_band_edge() {
+        let s = summary(&[0.0, -0.0, 9.9, 10.0]);
+        assert_eq!(s.bands.len(), 2);
+        assert_eq!(s.count(0, Direction::Coreward), 3);
+        assert_eq!(s.count(1, Direction::Coreward), 1);
+    }
+
+    #[test]
+    fn records_below_first_band_edge() {
+        let s = summary(&[-5.0, f64::NAN, f64::INFINITY]);
+        assert_eq!(s.bands.len(), 1);
+        assert_eq!(s.count(0, Direction::Coreward), 1);
+        assert_eq!(s.most_neglected(), Some((0, Direction::Coreward)));
+    }
+}
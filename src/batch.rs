@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{stdin, BufRead, BufReader, Write};
+
+#[cfg(feature = "schemars")]
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::Serialize;
+use serde_json::to_writer_pretty;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::export::csv_field;
+use crate::searcher::Record;
+
+/// Read origin names, one per line, from `source` file or stdin if `"-"`.
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn read_origins(source: &str) -> Result<Vec<String>, Fail> {
+    let mut origins = Vec::new();
+    let mut push_lines = |r: &mut dyn BufRead| -> Result<(), Fail> {
+        for line in r.lines() {
+            let line = line.err_msg("failed to read origins")?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            origins.push(line.to_owned());
+        }
+        Ok(())
+    };
+
+    if source == "-" {
+        let stdin = stdin();
+        push_lines(&mut stdin.lock())?;
+    } else {
+        let f = File::open(source).err_msg(format!("can't open file: {:?}", source))?;
+        push_lines(&mut BufReader::new(f))?;
+    }
+    Ok(origins)
+}
+
+/// Result of one origin in batch mode.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct BatchResult<'a> {
+    pub origin: &'a str,
+    pub total: usize,
+    pub records: Vec<BatchRecord<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct BatchRecord<'a> {
+    pub system: &'a str,
+    pub station: &'a str,
+    pub distance: f64,
+    pub days: Option<i64>,
+    pub category: Option<char>,
+}
+
+impl<'a> BatchResult<'a> {
+    pub fn new(origin: &'a str, records: &'a [Record], limit: usize) -> BatchResult<'a> {
+        BatchResult {
+            origin,
+            total: records.len(),
+            records: records
+                .iter()
+                .take(limit)
+                .map(|r| {
+                    let outdated = r.outdated();
+                    BatchRecord {
+                        system: &r.station.system_name,
+                        station: &r.station.name,
+                        distance: r.distance,
+                        days: outdated.map(|o| o.days),
+                        category: outdated.map(|o| o.category.letter()),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// JSON Schema of `--origins-from` output with `--format json`.
+#[cfg(feature = "schemars")]
+pub fn schema() -> RootSchema {
+    schema_for!(Vec<BatchResult<'static>>)
+}
+
+pub fn write_json(w: impl Write, results: &[BatchResult]) -> Result<(), Fail> {
+    to_writer_pretty(w, results).err_msg("can't encode batch results")?;
+    Ok(())
+}
+
+/// Write results as CSV, one record per row with its origin.
+pub fn write_csv(mut w: impl Write, results: &[BatchResult]) -> Result<(), Fail> {
+    writeln!(w, "Origin,System,Station,Distance,Days,Category")?;
+    for result in results {
+        for r in &result.records {
+            writeln!(
+                w,
+                "{},{},{},{:.2},{},{}",
+                csv_field(result.origin),
+                csv_field(r.system),
+                csv_field(r.station),
+                r.distance,
+                r.days.map_or(String::new(), |d| d.to_string()),
+                r.category.map_or(String::new(), |c| c.to_string()),
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+#[cfg(feature = "schemars")]
+fn main() {
+    use std::fs::{create_dir_all, File};
+    use std::path::Path;
+
+    use serde_json::to_writer_pretty;
+
+    use near_old_stations::schema::schemas;
+
+    let dir = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "schema".to_owned());
+    let dir = Path::new(&dir);
+    if let Err(e) = create_dir_all(dir) {
+        eprintln!("Error: can't create directory {:?}: {}", dir, e);
+        std::process::exit(1);
+    }
+
+    for (name, schema) in schemas() {
+        let path = dir.join(format!("{}.schema.json", name));
+        let result = File::create(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|f| to_writer_pretty(f, &schema).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Error: can't write {:?}: {}", path, e);
+            std::process::exit(1);
+        }
+        println!("{}", path.display());
+    }
+}
+
+#[cfg(not(feature = "schemars"))]
+fn main() {
+    eprintln!("Error: build with `--features schemars` to generate JSON Schemas.");
+    std::process::exit(1);
+}
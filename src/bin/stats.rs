@@ -1,13 +1,20 @@
 use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 
 use chrono::{DateTime, Utc};
+use clap::{App, Arg};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::to_writer_pretty;
 use tiny_fail::{ErrorMessageExt, Fail};
 
 use near_old_stations::config::Config;
 use near_old_stations::stations::{load_stations, Station};
 
+const CATEGORIES: [&str; 4] = ["information", "market", "shipyard", "outfitting"];
+
 fn main() {
     if let Err(e) = w_main() {
         eprintln!("Error: {}", e);
@@ -16,13 +23,69 @@ fn main() {
 }
 
 fn w_main() -> Result<(), Fail> {
-    let cfg = Config::load().err_msg("failed load config")?;
+    let matches = App::new("stats")
+        .arg(
+            Arg::with_name("group_by")
+                .long("group-by")
+                .alias("by")
+                .takes_value(true)
+                .possible_values(&["type", "economy", "system"])
+                .help("Summarize days per group instead of writing day histograms"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["tsv", "csv", "json"])
+                .default_value("tsv")
+                .help("Output format"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("Output file of --group-by (default: stdout)"),
+        )
+        .arg(
+            Arg::with_name("histogram")
+                .long("histogram")
+                .help("Print ASCII histogram of each category"),
+        )
+        .arg(
+            Arg::with_name("svg")
+                .long("svg")
+                .help("Write SVG chart of each category"),
+        )
+        .arg(
+            Arg::with_name("bin_width")
+                .long("bin-width")
+                .takes_value(true)
+                .default_value("30")
+                .help("Width in days of histogram bins"),
+        )
+        .get_matches();
+    let format = match matches.value_of("format").unwrap() {
+        "csv" => Format::Csv,
+        "json" => Format::Json,
+        _ => Format::Tsv,
+    };
+    let bin_width = matches
+        .value_of("bin_width")
+        .unwrap()
+        .parse::<i64>()
+        .err_msg("invalid bin width")?;
+    if bin_width <= 0 {
+        return Err(Fail::new("bin width must be positive"));
+    }
+
+    let cfg = Config::load_file().err_msg("failed load config")?;
 
     let exclude_names = cfg.filter_config().exclude_names()?;
     let exclude_systems = cfg.filter_config().exclude_systems()?;
 
     let mut sts = Vec::new();
-    for st in load_stations()
+    for st in load_stations(&cfg.load_options())
         .err_msg("failed load dump file")?
         .into_list()
     {
@@ -36,39 +99,294 @@ fn w_main() -> Result<(), Fail> {
         sts.push(st);
     }
 
-    count(&sts, "days_information.txt", |st| {
-        Some(st.update_time().information())
-    })?;
-    count(&sts, "days_market.txt", |st| st.update_time().market())?;
-    count(&sts, "days_shipyard.txt", |st| st.update_time().shipyard())?;
-    count(&sts, "days_outfitting.txt", |st| {
-        st.update_time().outfitting()
-    })?;
+    let now = Utc::now();
+    let days = map_stations(&sts, |st| {
+        let t = st.update_time();
+        let d = |t: Option<DateTime<Utc>>| t.map(|t| now.signed_duration_since(t).num_days());
+        [
+            d(Some(t.information())),
+            d(t.market()),
+            d(t.shipyard()),
+            d(t.outfitting()),
+        ]
+    });
+
+    if let Some(group_by) = matches.value_of("group_by") {
+        let rows = group(&sts, &days, |st| match group_by {
+            "type" => st.st_type.to_string(),
+            "economy" => st
+                .economy
+                .map_or_else(|| "None".to_owned(), |e| format!("{:?}", e)),
+            _ => st.system_name.clone(),
+        });
+        match matches.value_of("output") {
+            Some(path) => {
+                let f = File::create(path).err_msg(format!("can't create file: {:?}", path))?;
+                write_groups(BufWriter::new(f), &rows, format)?;
+            }
+            None => write_groups(io::stdout(), &rows, format)?,
+        }
+        return Ok(());
+    }
+
+    for (i, category) in CATEGORIES.iter().enumerate() {
+        let rows = count(days.iter().filter_map(|d| d[i]));
+        write_counts(
+            &rows,
+            &format!("days_{}.{}", category, format.ext()),
+            format,
+        )?;
+
+        let bins = bins(&rows, bin_width);
+        if matches.is_present("histogram") {
+            println!("== {} ==", category);
+            print!("{}", ascii_histogram(&bins, bin_width));
+            println!();
+        }
+        if matches.is_present("svg") {
+            let file_name = format!("days_{}.svg", category);
+            let mut f =
+                File::create(&file_name).err_msg(format!("can't create file: {:?}", file_name))?;
+            f.write_all(svg_histogram(category, &bins, bin_width).as_bytes())?;
+        }
+    }
 
     Ok(())
 }
 
-fn count(
-    sts: &[Station],
-    file_name: &str,
-    get_val: impl Fn(&Station) -> Option<DateTime<Utc>>,
-) -> Result<(), Fail> {
-    let mut cnt = BTreeMap::<i64, usize>::new();
+#[cfg(feature = "rayon")]
+fn map_stations<T: Send>(sts: &[Station], f: impl Fn(&Station) -> T + Sync + Send) -> Vec<T> {
+    sts.par_iter().map(f).collect()
+}
 
-    let now = Utc::now();
-    for st in sts {
-        if let Some(t) = get_val(st) {
-            let d = now.signed_duration_since(t).num_days();
-            cnt.entry(d).and_modify(|c| *c += 1).or_insert(1);
+#[cfg(not(feature = "rayon"))]
+fn map_stations<T: Send>(sts: &[Station], f: impl Fn(&Station) -> T + Sync + Send) -> Vec<T> {
+    sts.iter().map(f).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Tsv,
+    Csv,
+    Json,
+}
+
+impl Format {
+    fn ext(self) -> &'static str {
+        match self {
+            Format::Tsv => "txt",
+            Format::Csv => "csv",
+            Format::Json => "json",
         }
     }
 
-    let mut w = BufWriter::new(File::create(file_name)?);
-    writeln!(w, "Day\tCount\tAcc")?;
+    fn sep(self) -> &'static str {
+        match self {
+            Format::Csv => ",",
+            _ => "\t",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CountRow {
+    day: i64,
+    count: usize,
+    acc: usize,
+}
+
+fn count(days: impl Iterator<Item = i64>) -> Vec<CountRow> {
+    let mut cnt = BTreeMap::<i64, usize>::new();
+    for d in days {
+        cnt.entry(d).and_modify(|c| *c += 1).or_insert(1);
+    }
+
     let mut acc = 0usize;
-    for (&d, &c) in cnt.iter() {
-        acc += c;
-        writeln!(w, "{}\t{}\t{}", d, c, acc)?;
+    cnt.into_iter()
+        .map(|(day, count)| {
+            acc += count;
+            CountRow { day, count, acc }
+        })
+        .collect()
+}
+
+fn write_counts(rows: &[CountRow], file_name: &str, format: Format) -> Result<(), Fail> {
+    let mut w = BufWriter::new(File::create(file_name)?);
+    if format == Format::Json {
+        to_writer_pretty(&mut w, rows).err_msg("can't encode stats")?;
+        return Ok(());
+    }
+
+    let sep = format.sep();
+    writeln!(w, "Day{}Count{}Acc", sep, sep)?;
+    for r in rows {
+        writeln!(w, "{}{}{}{}{}", r.day, sep, r.count, sep, r.acc)?;
+    }
+
+    Ok(())
+}
+
+const ASCII_BAR_WIDTH: usize = 50;
+const SVG_WIDTH: f64 = 800.0;
+const SVG_HEIGHT: f64 = 400.0;
+const SVG_MARGIN: f64 = 40.0;
+
+/// Station counts per `bin_width` days, starting from day 0.
+fn bins(rows: &[CountRow], bin_width: i64) -> Vec<usize> {
+    let mut bins = Vec::new();
+    for r in rows {
+        let i = (r.day.max(0) / bin_width) as usize;
+        if bins.len() <= i {
+            bins.resize(i + 1, 0);
+        }
+        bins[i] += r.count;
+    }
+    bins
+}
+
+fn ascii_histogram(bins: &[usize], bin_width: i64) -> String {
+    let max = bins.iter().cloned().max().unwrap_or(0).max(1);
+
+    let mut s = String::new();
+    for (i, &count) in bins.iter().enumerate() {
+        let from = i as i64 * bin_width;
+        let bar = "#".repeat((count * ASCII_BAR_WIDTH).div_ceil(max));
+        s.push_str(&format!(
+            "{:>5}-{:<5} {:>6} {}\n",
+            from,
+            from + bin_width - 1,
+            count,
+            bar
+        ));
+    }
+    s
+}
+
+fn svg_histogram(category: &str, bins: &[usize], bin_width: i64) -> String {
+    let max = bins.iter().cloned().max().unwrap_or(0).max(1) as f64;
+    let plot_w = SVG_WIDTH - 2.0 * SVG_MARGIN;
+    let plot_h = SVG_HEIGHT - 2.0 * SVG_MARGIN;
+    let bar_w = plot_w / bins.len().max(1) as f64;
+
+    let mut s = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        SVG_WIDTH, SVG_HEIGHT
+    );
+    s.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"16\">{} (max {})</text>\n",
+        SVG_MARGIN,
+        SVG_MARGIN / 2.0,
+        category,
+        max
+    ));
+    for (i, &count) in bins.iter().enumerate() {
+        let h = count as f64 / max * plot_h;
+        s.push_str(&format!(
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"steelblue\"><title>{}-{}: {}</title></rect>\n",
+            SVG_MARGIN + i as f64 * bar_w,
+            SVG_HEIGHT - SVG_MARGIN - h,
+            bar_w,
+            h,
+            i as i64 * bin_width,
+            (i as i64 + 1) * bin_width - 1,
+            count
+        ));
+    }
+    s.push_str(&format!(
+        "<line x1=\"{m}\" y1=\"{y}\" x2=\"{x}\" y2=\"{y}\" stroke=\"black\"/>\n",
+        m = SVG_MARGIN,
+        x = SVG_WIDTH - SVG_MARGIN,
+        y = SVG_HEIGHT - SVG_MARGIN
+    ));
+    s.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"12\">0</text>\n",
+        SVG_MARGIN,
+        SVG_HEIGHT - SVG_MARGIN / 2.0
+    ));
+    s.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"end\">{} days</text>\n",
+        SVG_WIDTH - SVG_MARGIN,
+        SVG_HEIGHT - SVG_MARGIN / 2.0,
+        bins.len() as i64 * bin_width
+    ));
+    s.push_str("</svg>\n");
+    s
+}
+
+#[derive(Debug, Serialize)]
+struct GroupRow {
+    group: String,
+    stations: usize,
+    /// Median days for each category. `None` if no station in the group has the category.
+    median_days: BTreeMap<&'static str, Option<i64>>,
+    mean_days: BTreeMap<&'static str, Option<f64>>,
+}
+
+fn group(
+    sts: &[Station],
+    days: &[[Option<i64>; 4]],
+    key: impl Fn(&Station) -> String,
+) -> Vec<GroupRow> {
+    let mut groups = BTreeMap::<String, Vec<&[Option<i64>; 4]>>::new();
+    for (st, d) in sts.iter().zip(days) {
+        groups.entry(key(st)).or_default().push(d);
+    }
+
+    groups
+        .into_iter()
+        .map(|(group, list)| {
+            let mut median_days = BTreeMap::new();
+            let mut mean_days = BTreeMap::new();
+            for (i, category) in CATEGORIES.iter().enumerate() {
+                let mut values: Vec<i64> = list.iter().filter_map(|d| d[i]).collect();
+                values.sort_unstable();
+                median_days.insert(*category, values.get(values.len() / 2).cloned());
+                mean_days.insert(
+                    *category,
+                    if values.is_empty() {
+                        None
+                    } else {
+                        Some(values.iter().sum::<i64>() as f64 / values.len() as f64)
+                    },
+                );
+            }
+            GroupRow {
+                group,
+                stations: list.len(),
+                median_days,
+                mean_days,
+            }
+        })
+        .collect()
+}
+
+fn write_groups(mut w: impl Write, rows: &[GroupRow], format: Format) -> Result<(), Fail> {
+    if format == Format::Json {
+        to_writer_pretty(&mut w, rows).err_msg("can't encode stats")?;
+        writeln!(w)?;
+        return Ok(());
+    }
+
+    let sep = format.sep();
+    write!(w, "Group{}Stations", sep)?;
+    for category in &CATEGORIES {
+        write!(w, "{}Median {}{}Mean {}", sep, category, sep, category)?;
+    }
+    writeln!(w)?;
+
+    for r in rows {
+        if format == Format::Csv && (r.group.contains(',') || r.group.contains('"')) {
+            write!(w, "\"{}\"", r.group.replace('"', "\"\""))?;
+        } else {
+            write!(w, "{}", r.group)?;
+        }
+        write!(w, "{}{}", sep, r.stations)?;
+        for category in &CATEGORIES {
+            let median = r.median_days[category].map_or(String::new(), |d| d.to_string());
+            let mean = r.mean_days[category].map_or(String::new(), |d| format!("{:.1}", d));
+            write!(w, "{}{}{}{}", sep, median, sep, mean)?;
+        }
+        writeln!(w)?;
     }
 
     Ok(())
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tiny_fail::Fail;
+
+use crate::state::StateFile;
+use crate::stations::{Economy, Station, StationType};
+
+/// Detected changes kept, older ones are dropped.
+const MAX_CHANGES: usize = 1000;
+/// Format version of station snapshot file.
+const VERSION: u32 = 1;
+
+/// Economies and type of each station at the last load, keyed by market ID, with detected changes.
+///
+/// Profiles are saved as arrays to keep the file small, because all stations with market ID are kept.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    stations: BTreeMap<u64, Profile>,
+    changes: Vec<Change>,
+}
+
+/// Economy, secondary economy and type of a station.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile(Option<Economy>, Option<Economy>, StationType);
+
+impl Profile {
+    fn new(st: &Station) -> Profile {
+        Profile(st.economy, st.second_economy, st.st_type.clone())
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.2)?;
+        match (self.0, self.1) {
+            (Some(first), Some(second)) => write!(f, " {:?}/{:?}", first, second),
+            (Some(first), None) => write!(f, " {:?}", first),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub market_id: u64,
+    pub system: String,
+    pub station: String,
+    /// Unix time of the load which found the change.
+    detected: i64,
+    pub before: Profile,
+    pub after: Profile,
+}
+
+impl Change {
+    pub fn detected(&self) -> DateTime<Utc> {
+        // Saved by `timestamp()`, so always in range.
+        Utc.timestamp_opt(self.detected, 0).unwrap()
+    }
+}
+
+impl Snapshot {
+    pub fn new() -> Snapshot {
+        Snapshot::default()
+    }
+
+    /// Load snapshot saved by `save`. Returns empty snapshot if `path` doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Snapshot, Fail> {
+        Ok(StateFile::new(path, "station snapshot", VERSION)
+            .load()?
+            .unwrap_or_default())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Fail> {
+        StateFile::new(path, "station snapshot", VERSION).save(self)
+    }
+
+    /// Compare stations with the snapshot and record changes. Returns `true` if the snapshot is modified.
+    ///
+    /// Stations first seen are only recorded, because there is nothing to compare.
+    pub fn update(&mut self, list: &[Station], now: DateTime<Utc>) -> bool {
+        let mut modified = false;
+        for st in list {
+            let id = match st.market_id {
+                Some(id) => id,
+                None => continue,
+            };
+            let profile = Profile::new(st);
+            match self.stations.insert(id, profile.clone()) {
+                Some(before) if before != profile => {
+                    self.changes.push(Change {
+                        market_id: id,
+                        system: st.system_name.clone(),
+                        station: st.name.clone(),
+                        detected: now.timestamp(),
+                        before,
+                        after: profile,
+                    });
+                    modified = true;
+                }
+                Some(_) => {}
+                None => modified = true,
+            }
+        }
+
+        if self.changes.len() > MAX_CHANGES {
+            let excess = self.changes.len() - MAX_CHANGES;
+            self.changes.drain(..excess);
+        }
+        modified
+    }
+
+    /// Recorded changes, in order of detection.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+}
@@ -0,0 +1,98 @@
+use crate::coords::Coords;
+use crate::searcher::Record;
+
+/// Group of stations close to each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    /// Indices of member records, in order of records.
+    pub members: Vec<usize>,
+    /// Index of the member nearest to the centroid.
+    pub center: usize,
+    /// Distance from center member to the farthest member.
+    pub radius: f64,
+}
+
+impl Cluster {
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+/// Find clusters of records by DBSCAN.
+///
+/// A station with at least `min_size - 1` other stations within `eps` Ly forms a cluster core,
+/// and clusters grow through cores. Stations not in any cluster are ignored.
+/// Clusters are ordered by size, then by distance from origin.
+pub fn find_clusters(records: &[Record], eps: f64, min_size: usize) -> Vec<Cluster> {
+    let neighbors = |i: usize| -> Vec<usize> {
+        let c = records[i].station.coords;
+        (0..records.len())
+            .filter(|&j| c.dist_to(records[j].station.coords) <= eps)
+            .collect()
+    };
+
+    let mut assigned = vec![false; records.len()];
+    let mut clusters = Vec::new();
+    for i in 0..records.len() {
+        if assigned[i] {
+            continue;
+        }
+        let seeds = neighbors(i);
+        if seeds.len() < min_size {
+            continue;
+        }
+
+        let mut members = Vec::new();
+        let mut queue = seeds;
+        while let Some(j) = queue.pop() {
+            if assigned[j] {
+                continue;
+            }
+            assigned[j] = true;
+            members.push(j);
+
+            let next = neighbors(j);
+            if next.len() >= min_size {
+                queue.extend(next.into_iter().filter(|&k| !assigned[k]));
+            }
+        }
+        members.sort_unstable();
+        clusters.push(new_cluster(records, members));
+    }
+
+    clusters.sort_by(|l, r| {
+        r.len().cmp(&l.len()).then_with(|| {
+            let ld = records[l.center].distance;
+            let rd = records[r.center].distance;
+            ld.partial_cmp(&rd).unwrap()
+        })
+    });
+    clusters
+}
+
+fn new_cluster(records: &[Record], members: Vec<usize>) -> Cluster {
+    let centroid = Coords::centroid(members.iter().map(|&i| records[i].station.coords))
+        .expect("cluster has members");
+
+    let dist = |i: usize, c: Coords| records[i].station.coords.dist_to(c);
+    let center = members
+        .iter()
+        .cloned()
+        .min_by(|&l, &r| dist(l, centroid).partial_cmp(&dist(r, centroid)).unwrap())
+        .unwrap();
+    let center_coords = records[center].station.coords;
+    let radius = members
+        .iter()
+        .map(|&i| dist(i, center_coords))
+        .fold(0.0, f64::max);
+
+    Cluster {
+        members,
+        center,
+        radius,
+    }
+}
@@ -1,43 +1,147 @@
-use std::collections::HashSet;
-use std::fs::File;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
 use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-use clap::{crate_version, App, Arg};
+use clap::{crate_version, App, AppSettings, Arg, SubCommand};
 use regex::RegexSet;
-use serde::Deserialize;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
 use tiny_fail::{ErrorMessageExt, Fail};
 use toml::from_slice;
 
-use crate::filter::{Days, Filter, Filters};
-use crate::journal::{load_current_location, sol_origin, GetLocFunc};
+use crate::activity::ActivityLog;
+use crate::alert::Alert;
+use crate::coords::Coords;
+use crate::daemon::StatusFile;
+use crate::filter::{Days, DistanceScale, Filter, FilterFile, Filters};
+use crate::hooks::Hooks;
+use crate::i18n::Lang;
+use crate::journal::{
+    coords_origin, load_current_location, load_current_location_or, load_exported_visits,
+    simulated, sol_origin, GetLocFunc, Location,
+};
+use crate::marks::{Marks, MARKS_FILE};
 use crate::mode;
-use crate::stations::Economy;
+use crate::notification::Notifier;
+use crate::patches::{find_patch, Patch, PATCHES};
+use crate::paths::{set_paths, state_file, Paths};
+use crate::printer::TextPrinter;
+use crate::result_cache::StableHasher;
+use crate::schedule::{Report, Schedule};
+use crate::searcher::{Category, DaysTransform, EconomyBoost};
+use crate::stations::download::{IpVersion, NetworkOptions};
+use crate::stations::{find_system, load_name_list, Economy, LoadOptions, StationType};
+use crate::travel::{
+    Jumps, Neutron, StraightLine, Supercruise, TravelCost, DEFAULT_ARRIVAL_WEIGHT,
+    DEFAULT_JUMP_RANGE, DEFAULT_SECS_PER_JUMP, DEFAULT_SECS_PER_STATION,
+};
+use crate::units::{deserialize_ls, deserialize_ly, parse_distance, Unit};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
+    activity_log: Option<String>,
+    alert: Option<AlertConfig>,
+    daemon: Option<DaemonConfig>,
     days: OutdatedDays,
+    #[serde(default)]
+    display: DisplayConfig,
+    /// Origin system or `"x,y,z"` used if journal is not found, instead of Sol.
+    fallback_origin: Option<String>,
+    #[serde(default)]
+    dumps: DumpsConfig,
+    /// Journal entries exported from EDDiscovery, read as visits in addition to journal files.
+    eddiscovery_export: Option<String>,
     filter: FilterConfig,
+    lang: Option<Lang>,
     max_entries: usize,
     #[serde(default)]
     mode: Mode,
+    max_age: Option<i64>,
+    #[serde(deserialize_with = "deserialize_ly")]
     max_dist: f64,
+    /// Desktop notification of new candidates after jumps.
+    notify: Option<NotifyConfig>,
+    #[serde(default = "default_poll_interval")]
+    poll_interval: u64,
+    /// Minutes without journal update to pause update mode.
+    pause_after: Option<u64>,
+    /// Minutes to reload stations dumps in update mode.
+    reload_dumps: Option<u64>,
+    #[serde(default)]
+    network: NetworkConfig,
+    #[serde(default)]
+    paths: PathsConfig,
     #[serde(default)]
     pos_origin: Origin,
+    /// Reuse the last result of oneshot search while inputs are unchanged.
+    #[serde(default)]
+    result_cache: bool,
+    refresh: Option<RefreshConfig>,
+    schedule: Option<ScheduleConfig>,
+    #[serde(default)]
+    score: ScoreConfig,
+    #[serde(default)]
+    serve: ServeConfig,
+    /// Show how many stations are updated since the previous run at startup. Opt-in, because it
+    /// would add a line to output of scripts.
+    #[serde(default)]
+    since_last_run: bool,
+    #[serde(default)]
+    skip_bad_records: bool,
+    #[serde(default)]
+    submit: SubmitConfig,
+    /// Threads to load dumps, also bounds rayon's pool. Number of CPUs if not set.
+    threads: Option<usize>,
+    /// Decompress and parse dumps in parallel. `false` is the same as `threads = 1`.
+    #[serde(default = "default_parallel")]
+    parallel: bool,
+    /// Compare economies and types of stations with the previous load.
+    #[serde(default)]
+    track_changes: bool,
+    #[serde(default)]
+    travel: TravelConfig,
+    #[serde(skip)]
+    command: Command,
+    /// Hash of config file and command line arguments.
+    #[serde(skip)]
+    fingerprint: u64,
+    #[serde(skip)]
+    format: Format,
+    /// File to write all results as CSV, instead of showing them.
+    #[serde(skip)]
+    csv_output: Option<PathBuf>,
+    /// Filters written by `--dump-filters`, used instead of filters in config. Marks still apply.
+    #[serde(skip)]
+    filters_file: Option<String>,
+    #[serde(skip)]
+    here: bool,
+    #[serde(skip)]
+    simulate: Option<String>,
+    #[serde(skip)]
+    origin_coords: Option<Coords>,
 }
 
 impl Config {
+    /// Load config file and apply command line arguments.
     pub fn load() -> Result<Config, Fail> {
-        let mut f = File::open("./config.toml").err_msg("failed open config file")?;
-
-        let mut bytes = Vec::new();
-        f.read_to_end(&mut bytes)
-            .err_msg("failed read config file")?;
-
-        let mut cfg: Config = from_slice(&bytes).err_msg("failed parse config")?;
+        let mut cfg = Config::load_file()?;
 
         // args
         let matches = App::new("near-old-stations")
             .version(crate_version!())
+            .arg(
+                Arg::with_name("max_age")
+                    .long("max-age")
+                    .takes_value(true)
+                    .help("Exclude stations outdated for this days or more"),
+            )
             .arg(
                 Arg::with_name("max_dist")
                     .long("max-dist")
@@ -55,9 +159,36 @@ impl Config {
                 Arg::with_name("mode")
                     .long("mode")
                     .takes_value(true)
-                    .possible_values(&["oneshot", "update"])
+                    .possible_values(&[
+                        "oneshot",
+                        "update",
+                        "select",
+                        "poll",
+                        "scheduled",
+                        "route",
+                        "serve",
+                    ])
                     .help("Run mode"),
             )
+            .arg(
+                Arg::with_name("at")
+                    .long("at")
+                    .takes_value(true)
+                    .help("Run time of scheduled mode (\"HH:MM\" or cron expression)"),
+            )
+            .arg(
+                Arg::with_name("listen")
+                    .long("listen")
+                    .takes_value(true)
+                    .value_name("ADDR")
+                    .help("Address of serve mode (\"127.0.0.1:8080\")"),
+            )
+            .arg(
+                Arg::with_name("interval")
+                    .long("interval")
+                    .takes_value(true)
+                    .help("Interval of poll mode in seconds"),
+            )
             .arg(
                 Arg::with_name("pos_origin")
                     .long("pos-origin")
@@ -65,12 +196,330 @@ impl Config {
                     .possible_values(&["current", "Sol"])
                     .help("Disctance calculation origin"),
             )
+            .arg(
+                Arg::with_name("daemon")
+                    .long("daemon")
+                    .help("Run unattended with log file and status file"),
+            )
+            .arg(
+                Arg::with_name("origins_from")
+                    .long("origins-from")
+                    .takes_value(true)
+                    .value_name("FILE|-")
+                    .help("Search from each origin system listed in file or stdin"),
+            )
+            .arg(
+                Arg::with_name("json")
+                    .long("json")
+                    .help("Same as --format json"),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .long("format")
+                    .takes_value(true)
+                    .possible_values(&["text", "json", "csv"])
+                    .help("Output format. With json or csv, only records are written to stdout. csv is only for oneshot mode"),
+            )
+            .arg(
+                Arg::with_name("output")
+                    .long("output")
+                    .takes_value(true)
+                    .possible_values(&["csv"])
+                    .help("Write all results to file instead of showing them"),
+            )
+            .arg(
+                Arg::with_name("output_file")
+                    .long("output-file")
+                    .takes_value(true)
+                    .default_value("stations.csv")
+                    .help("File written by --output"),
+            )
+            .arg(
+                Arg::with_name("dump_filters")
+                    .long("dump-filters")
+                    .help("Write effective filters as TOML (JSON with --format json), to be read by --filters-file"),
+            )
+            .arg(
+                Arg::with_name("filters_file")
+                    .long("filters-file")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("Use filters written by --dump-filters instead of filters in config. Ignored stations are still excluded"),
+            )
+            .arg(
+                Arg::with_name("here")
+                    .long("here")
+                    .help("List all stations in the current system"),
+            )
+            .arg(
+                Arg::with_name("simulate")
+                    .long("simulate-system")
+                    .takes_value(true)
+                    .value_name("SYSTEM|X,Y,Z")
+                    .help("Search from given system or coordinates as not visited anywhere"),
+            )
+            .arg(
+                Arg::with_name("origin_coords")
+                    .long("origin-coords")
+                    .takes_value(true)
+                    .value_name("X,Y,Z")
+                    .conflicts_with("simulate")
+                    .help("Search from given coordinates, with visits in journal"),
+            )
+            .arg(
+                Arg::with_name("skip_bad_records")
+                    .long("skip-bad-records")
+                    .help("Skip dump records which can't be parsed"),
+            )
+            .arg(
+                Arg::with_name("stations_dump")
+                    .long("stations-dump")
+                    .takes_value(true)
+                    .value_name("PATH")
+                    .help("Use local stations dump instead of downloading"),
+            )
+            .arg(
+                Arg::with_name("systems_dump")
+                    .long("systems-dump")
+                    .takes_value(true)
+                    .value_name("PATH")
+                    .help("Use local systems dump instead of downloading"),
+            )
+            .arg(
+                Arg::with_name("compact")
+                    .long("compact")
+                    .help("Show station types as short codes"),
+            )
+            .subcommand(
+                SubCommand::with_name("changes")
+                    .about("List stations whose economy or type changed between loads of dumps")
+                    .arg(
+                        Arg::with_name("days")
+                            .long("days")
+                            .takes_value(true)
+                            .help("Only changes detected within this days"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("clusters")
+                    .about("Show groups of outdated stations close to each other")
+                    .arg(
+                        Arg::with_name("radius")
+                            .long("radius")
+                            .takes_value(true)
+                            .default_value("12")
+                            .help("Maximum distance between neighbor stations in Ly"),
+                    )
+                    .arg(
+                        Arg::with_name("min_size")
+                            .long("min-size")
+                            .takes_value(true)
+                            .default_value("3")
+                            .help("Minimum stations in a cluster"),
+                    )
+                    .arg(
+                        Arg::with_name("expand")
+                            .long("expand")
+                            .takes_value(true)
+                            .help("Show stations in the cluster of given number"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("compare-origins")
+                    .about("Compare search results from multiple origins")
+                    .arg(
+                        Arg::with_name("origins")
+                            .multiple(true)
+                            .min_values(2)
+                            .required(true)
+                            .value_name("SYSTEM|X,Y,Z")
+                            .help("Origin systems or coordinates"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("directions")
+                    .about("Summarize outdated stations by direction and distance")
+                    .arg(
+                        Arg::with_name("band")
+                            .long("band")
+                            .takes_value(true)
+                            .default_value("25")
+                            .help("Width of distance bands in Ly"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("export-coords")
+                    .about("Export results with coordinates as CSV for 3D visualizers")
+                    .arg(
+                        Arg::with_name("cluster_radius")
+                            .long("cluster-radius")
+                            .takes_value(true)
+                            .help("Add cluster column, with given maximum distance between neighbor stations in Ly"),
+                    )
+                    .arg(
+                        Arg::with_name("min_size")
+                            .long("min-size")
+                            .takes_value(true)
+                            .default_value("3")
+                            .help("Minimum stations in a cluster, for --cluster-radius"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .default_value("stations.csv")
+                            .help("Output file"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("extract")
+                    .about("Write stations around a system as a dump subset")
+                    .arg(
+                        Arg::with_name("around")
+                            .long("around")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Center system name"),
+                    )
+                    .arg(
+                        Arg::with_name("radius")
+                            .long("radius")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Radius from center system in Ly"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .default_value("subset.json.gz")
+                            .help("Output file"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("missing")
+                    .about("List docked stations which are missing from the dump, to submit them")
+                    .arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .help("Write report to file"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("nearest")
+                    .about("List stations nearest to current location with their ages, even if not outdated")
+                    .arg(
+                        Arg::with_name("count")
+                            .short("n")
+                            .long("count")
+                            .takes_value(true)
+                            .default_value("10")
+                            .help("Number of stations"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("roll")
+                    .about("Pick today's target randomly, weighted by score"),
+            )
+            .subcommand(
+                SubCommand::with_name("submit-coverage")
+                    .about("Submit aggregated outdated station counts to community endpoint")
+                    .arg(
+                        Arg::with_name("dry_run")
+                            .long("dry-run")
+                            .help("Show what would be sent without sending"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("plan")
+                    .about("Split outdated stations into daily routes")
+                    .arg(
+                        Arg::with_name("days")
+                            .long("days")
+                            .takes_value(true)
+                            .default_value("7")
+                            .help("Number of days"),
+                    )
+                    .arg(
+                        Arg::with_name("jump_range")
+                            .long("jump-range")
+                            .takes_value(true)
+                            .help("Jump range in Ly, instead of jump_range in [travel]"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .default_value("plan_day")
+                            .help("Prefix of route files, written as <prefix><day>.txt"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("tour")
+                    .about("Plan visit order of outdated stations")
+                    .arg(
+                        Arg::with_name("cluster")
+                            .long("cluster")
+                            .takes_value(true)
+                            .help("Visit stations in the cluster of given number (see clusters subcommand)"),
+                    )
+                    .arg(
+                        Arg::with_name("radius")
+                            .long("radius")
+                            .takes_value(true)
+                            .default_value("12")
+                            .help("Maximum distance between neighbor stations in Ly, for --cluster"),
+                    )
+                    .arg(
+                        Arg::with_name("min_size")
+                            .long("min-size")
+                            .takes_value(true)
+                            .default_value("3")
+                            .help("Minimum stations in a cluster, for --cluster"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .help("Export route to file"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("visited")
+                    .about("Move visited stations to another PC or account")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .subcommand(
+                        SubCommand::with_name("export")
+                            .about("Write visited stations to file")
+                            .arg(Arg::with_name("file").required(true).help("Output file")),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("import")
+                            .about("Replace visited stations with file")
+                            .arg(Arg::with_name("file").required(true).help("File written by export"))
+                            .arg(
+                                Arg::with_name("merge")
+                                    .long("merge")
+                                    .help("Keep current visits, with newer time for each station"),
+                            ),
+                    ),
+            )
             .get_matches();
 
+        if let Some(s) = matches.value_of("max_age") {
+            cfg.max_age = Some(s.parse::<i64>().err_msg("can't parse 'max_age' as int")?);
+        }
+
         if let Some(s) = matches.value_of("max_dist") {
-            cfg.max_dist = s
-                .parse::<f64>()
-                .err_msg("can't parse 'max_dist' as float")?;
+            cfg.max_dist = parse_distance(s, Unit::Ly)
+                .map_err(Fail::new)
+                .err_msg("can't parse 'max_dist' as distance")?;
         }
 
         if let Some(s) = matches.value_of("max_entries") {
@@ -83,9 +532,32 @@ impl Config {
             match s {
                 "oneshot" => cfg.mode = Mode::Oneshot,
                 "update" => cfg.mode = Mode::Update,
+                "select" => cfg.mode = Mode::Select,
+                "poll" => cfg.mode = Mode::Poll,
+                "scheduled" => cfg.mode = Mode::Scheduled,
+                "route" => cfg.mode = Mode::Route,
+                "serve" => cfg.mode = Mode::Serve,
                 s => unreachable!("unreachable branch of match 'mode' with {}", s),
             }
         }
+        if let Some(s) = matches.value_of("interval") {
+            cfg.poll_interval = s.parse::<u64>().err_msg("can't parse 'interval' as int")?;
+        }
+        if let Some(s) = matches.value_of("listen") {
+            cfg.serve.listen = s.to_owned();
+        }
+        if let Some(s) = matches.value_of("at") {
+            match cfg.schedule {
+                Some(ref mut schedule) => schedule.at = s.to_owned(),
+                None => {
+                    cfg.schedule = Some(ScheduleConfig {
+                        at: s.to_owned(),
+                        report: None,
+                        webhook: None,
+                    })
+                }
+            }
+        }
         if let Some(s) = matches.value_of("pos_origin") {
             match s {
                 "current" => cfg.pos_origin = Origin::Current,
@@ -93,16 +565,387 @@ impl Config {
                 s => unreachable!("unreachable branch of match 'pos_origin' with {}", s),
             }
         }
+        cfg.here = matches.is_present("here");
+        cfg.filters_file = matches.value_of("filters_file").map(str::to_owned);
+        if let Some(ref path) = cfg.filters_file {
+            // Result cache must not be reused after filters file is edited.
+            let mut hasher = StableHasher::new();
+            cfg.fingerprint.hash(&mut hasher);
+            fs::read(path).unwrap_or_default().hash(&mut hasher);
+            cfg.fingerprint = hasher.finish();
+        }
+        cfg.simulate = matches.value_of("simulate").map(str::to_owned);
+        if let Some(s) = matches.value_of("origin_coords") {
+            let coords = Coords::parse(s)
+                .filter(|c| c.is_valid())
+                .err_msg("can't parse 'origin-coords' as \"x,y,z\"")?;
+            cfg.origin_coords = Some(coords);
+        }
+        if matches.is_present("daemon") && cfg.daemon.is_none() {
+            cfg.daemon = Some(DaemonConfig::default());
+        }
+        if matches.is_present("skip_bad_records") {
+            cfg.skip_bad_records = true;
+        }
+        if matches.is_present("compact") {
+            cfg.display.compact = true;
+        }
+        if let Some(path) = matches.value_of("stations_dump") {
+            cfg.dumps.stations_dump = Some(path.to_owned());
+        }
+        if let Some(path) = matches.value_of("systems_dump") {
+            cfg.dumps.systems_dump = Some(path.to_owned());
+        }
+
+        match matches.subcommand() {
+            ("clusters", Some(m)) => {
+                cfg.command = Command::Clusters {
+                    radius: parse_distance(m.value_of("radius").unwrap(), Unit::Ly)
+                        .map_err(Fail::new)
+                        .err_msg("can't parse 'radius' as distance")?,
+                    min_size: m
+                        .value_of("min_size")
+                        .unwrap()
+                        .parse::<usize>()
+                        .err_msg("can't parse 'min-size' as int")?,
+                    expand: m
+                        .value_of("expand")
+                        .map(str::parse::<usize>)
+                        .transpose()
+                        .err_msg("can't parse 'expand' as int")?,
+                };
+            }
+            ("compare-origins", Some(m)) => {
+                cfg.command = Command::CompareOrigins {
+                    origins: m.values_of("origins").unwrap().map(str::to_owned).collect(),
+                };
+            }
+            ("directions", Some(m)) => {
+                cfg.command = Command::Directions {
+                    band: m
+                        .value_of("band")
+                        .unwrap()
+                        .parse::<f64>()
+                        .err_msg("can't parse 'band' as float")
+                        .and_then(|band| {
+                            if band > 0.0 && band.is_finite() {
+                                Ok(band)
+                            } else {
+                                Err(Fail::new("'band' must be positive"))
+                            }
+                        })?,
+                };
+            }
+            ("export-coords", Some(m)) => {
+                cfg.command = Command::ExportCoords {
+                    cluster_radius: m
+                        .value_of("cluster_radius")
+                        .map(str::parse::<f64>)
+                        .transpose()
+                        .err_msg("can't parse 'cluster-radius' as float")?,
+                    min_size: m
+                        .value_of("min_size")
+                        .unwrap()
+                        .parse::<usize>()
+                        .err_msg("can't parse 'min-size' as int")?,
+                    output: m.value_of("output").unwrap().to_owned(),
+                };
+            }
+            ("extract", Some(m)) => {
+                cfg.command = Command::Extract {
+                    around: m.value_of("around").unwrap().to_owned(),
+                    radius: parse_distance(m.value_of("radius").unwrap(), Unit::Ly)
+                        .map_err(Fail::new)
+                        .err_msg("can't parse 'radius' as distance")?,
+                    output: m.value_of("output").unwrap().to_owned(),
+                };
+            }
+            ("changes", Some(m)) => {
+                cfg.command = Command::Changes {
+                    days: match m.value_of("days") {
+                        Some(s) => Some(s.parse::<i64>().err_msg("can't parse 'days' as int")?),
+                        None => None,
+                    },
+                };
+            }
+            ("missing", Some(m)) => {
+                cfg.command = Command::Missing {
+                    output: m.value_of("output").map(str::to_owned),
+                };
+            }
+            ("nearest", Some(m)) => {
+                cfg.command = Command::Nearest {
+                    count: m
+                        .value_of("count")
+                        .unwrap()
+                        .parse::<usize>()
+                        .err_msg("can't parse 'count' as int")?,
+                };
+            }
+            ("roll", Some(_)) => cfg.command = Command::Roll,
+            ("submit-coverage", Some(m)) => {
+                cfg.command = Command::SubmitCoverage {
+                    dry_run: m.is_present("dry_run"),
+                };
+            }
+            ("plan", Some(m)) => {
+                let days = m
+                    .value_of("days")
+                    .unwrap()
+                    .parse::<usize>()
+                    .err_msg("can't parse 'days' as int")?;
+                if days == 0 {
+                    return Err(Fail::new("'days' must be 1 or more"));
+                }
+                if let Some(jump_range) = m.value_of("jump_range") {
+                    let jump_range = parse_distance(jump_range, Unit::Ly)
+                        .map_err(Fail::new)
+                        .err_msg("can't parse 'jump-range' as distance")?;
+                    if !(jump_range > 0.0 && jump_range.is_finite()) {
+                        return Err(Fail::new("'jump-range' must be positive"));
+                    }
+                    cfg.travel.jump_range = jump_range;
+                }
+                cfg.command = Command::Plan {
+                    days,
+                    output: m.value_of("output").unwrap().to_owned(),
+                };
+            }
+            ("tour", Some(m)) => {
+                cfg.command = Command::Tour {
+                    cluster: m
+                        .value_of("cluster")
+                        .map(str::parse::<usize>)
+                        .transpose()
+                        .err_msg("can't parse 'cluster' as int")?,
+                    radius: parse_distance(m.value_of("radius").unwrap(), Unit::Ly)
+                        .map_err(Fail::new)
+                        .err_msg("can't parse 'radius' as distance")?,
+                    min_size: m
+                        .value_of("min_size")
+                        .unwrap()
+                        .parse::<usize>()
+                        .err_msg("can't parse 'min-size' as int")?,
+                    output: m.value_of("output").map(str::to_owned),
+                };
+            }
+            ("visited", Some(m)) => match m.subcommand() {
+                ("export", Some(m)) => {
+                    cfg.command = Command::VisitedExport {
+                        output: m.value_of("file").unwrap().to_owned(),
+                    };
+                }
+                ("import", Some(m)) => {
+                    cfg.command = Command::VisitedImport {
+                        input: m.value_of("file").unwrap().to_owned(),
+                        merge: m.is_present("merge"),
+                    };
+                }
+                (s, _) => unreachable!("unreachable branch of match 'visited' with {}", s),
+            },
+            _ => {}
+        }
+        if matches.is_present("json") {
+            cfg.format = Format::Json;
+        }
+        match matches.value_of("format") {
+            Some("text") => cfg.format = Format::Text,
+            Some("json") => cfg.format = Format::Json,
+            Some("csv") => cfg.format = Format::Csv,
+            _ => {}
+        }
+        if matches.value_of("output") == Some("csv") {
+            cfg.csv_output = matches.value_of("output_file").map(PathBuf::from);
+        }
+        if matches.is_present("dump_filters") {
+            cfg.command = Command::DumpFilters;
+        }
+        if let Some(source) = matches.value_of("origins_from") {
+            cfg.command = Command::Batch {
+                source: source.to_owned(),
+                format: cfg.format,
+            };
+        }
+        // Records on stdout are written once, to be piped to other tools.
+        if cfg.command == Command::Search
+            && cfg.format == Format::Csv
+            && (cfg.mode != Mode::Oneshot || cfg.daemon.is_some())
+        {
+            return Err(Fail::new(
+                "'--format csv' is supported only in oneshot mode",
+            ));
+        }
 
         Ok(cfg)
     }
 
+    /// Load config file only, for tools which have their own arguments.
+    pub fn load_file() -> Result<Config, Fail> {
+        let mut paths = Paths::resolve();
+        let config_file = paths.config_file();
+        let mut f = File::open(&config_file)
+            .err_msg(format!("failed open config file {:?}", config_file))?;
+
+        let mut bytes = Vec::new();
+        f.read_to_end(&mut bytes)
+            .err_msg("failed read config file")?;
+
+        let mut cfg: Config = from_slice(&bytes).err_msg("failed parse config")?;
+        if cfg!(not(feature = "notify-rust")) && cfg.notify.is_some() {
+            return Err(Fail::new(
+                "'notify' is not supported in this build (enable 'notify-rust' feature)",
+            ));
+        }
+        cfg.travel.validate()?;
+        cfg.days.validate()?;
+        if !(cfg.submit.cell_size > 0.0 && cfg.submit.cell_size.is_finite()) {
+            return Err(Fail::new("'submit.cell_size' must be positive"));
+        }
+        let mut hasher = StableHasher::new();
+        bytes.hash(&mut hasher);
+        env::args().collect::<Vec<_>>().hash(&mut hasher);
+        cfg.fingerprint = hasher.finish();
+
+        if let Some(ref dir) = cfg.paths.cache {
+            paths.cache = PathBuf::from(dir);
+        }
+        if let Some(ref dir) = cfg.paths.state {
+            paths.state = PathBuf::from(dir);
+        }
+        set_paths(paths)?;
+        cfg.init_threads();
+
+        Ok(cfg)
+    }
+
+    /// Bound rayon's global pool by `threads`.
+    #[cfg(feature = "rayon")]
+    fn init_threads(&self) {
+        if let Some(threads) = self.threads {
+            // Pool can be built only once, so changes are ignored on reload.
+            let _ = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build_global();
+        }
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn init_threads(&self) {}
+
+    /// Hash of config file and command line arguments, changed if any setting is changed.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Result cache is used only by oneshot search.
+    pub fn use_result_cache(&self) -> bool {
+        self.result_cache
+            && self.mode == Mode::Oneshot
+            && self.command == Command::Search
+            && self.format == Format::Text
+            && self.csv_output.is_none()
+            && self.daemon.is_none()
+    }
+
+    pub fn submit_config(&self) -> &SubmitConfig {
+        &self.submit
+    }
+
+    /// List stations in the current system instead of search.
+    pub fn is_here(&self) -> bool {
+        self.here
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Whether to show stations updated since the previous run. Not with JSON, which must be alone in stdout.
+    pub fn since_last_run(&self) -> bool {
+        self.since_last_run && self.format != Format::Json
+    }
+
+    /// Whether to check updates of dumps concurrently and show them before loading.
+    pub fn concurrent_checks(&self) -> bool {
+        self.dumps.concurrent_checks
+    }
+
+    pub fn max_dist(&self) -> f64 {
+        self.max_dist
+    }
+
+    pub fn csv_output(&self) -> Option<&PathBuf> {
+        self.csv_output.as_ref()
+    }
+
+    pub fn is_daemon(&self) -> bool {
+        self.daemon.is_some()
+    }
+
+    pub fn hooks(&self) -> Hooks {
+        Hooks {
+            activity: match self.activity_log {
+                Some(ref path) => ActivityLog::new(state_file(path)),
+                None => ActivityLog::disabled(),
+            },
+            alert: self
+                .alert
+                .as_ref()
+                .map(|a| Alert::new(self.travel.jump_range, a.top, a.command.clone())),
+            // Notifications are for playing, when jumps are followed.
+            notifier: match self.mode {
+                Mode::Update => self.notify.as_ref().map(|n| Notifier::new(n.top)),
+                _ => None,
+            },
+            status: self
+                .daemon
+                .as_ref()
+                .map(|d| StatusFile::new(state_file(&d.status))),
+        }
+    }
+
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    /// Filters of the search, with ignored stations of marks.
     pub fn filter(&self) -> Result<Filters, Fail> {
+        let mut filters = self.filter_chain()?;
+        let marks = Marks::load(state_file(MARKS_FILE))?;
+        let mut ignored = HashMap::<String, HashSet<String>>::new();
+        for (system, station) in marks.ignored() {
+            ignored
+                .entry(system.to_owned())
+                .or_default()
+                .insert(station.to_owned());
+        }
+        if !ignored.is_empty() {
+            filters.add(Filter::ExcludeStations(ignored));
+        }
+
+        Ok(filters)
+    }
+
+    /// Filters from config or `--filters-file`, without marks which change while playing.
+    pub fn filter_chain(&self) -> Result<Filters, Fail> {
+        if let Some(ref path) = self.filters_file {
+            return FilterFile::load(path)?.into_filters();
+        }
+
         let mut filters = Filters::new();
 
         filters.add(Filter::Dist(self.max_dist));
         self.days.filter(&mut filters);
-        self.filter.filter(&mut filters)?;
+        if let Some(days) = self.max_age {
+            filters.add(Filter::MaxDays(days));
+        }
+        self.filter
+            .filter(&mut filters, &self.load_options().network)?;
+        if let Some(ref refresh) = self.refresh {
+            if refresh.neglected_only {
+                filters.add(Filter::NeglectedOnly(refresh.neglected_days));
+            }
+        }
 
         Ok(filters)
     }
@@ -111,10 +954,135 @@ impl Config {
         &self.filter
     }
 
-    pub fn get_loc_func(&self) -> GetLocFunc {
-        match self.pos_origin {
-            Origin::Current => load_current_location,
-            Origin::Sol => sol_origin,
+    pub fn get_loc_func(&self) -> Result<GetLocFunc, Fail> {
+        if let Some(ref target) = self.simulate {
+            return Ok(simulated(resolve_location(&self.load_options(), target)?));
+        }
+
+        let get_loc: GetLocFunc = match (self.origin_coords, self.pos_origin) {
+            (Some(coords), _) => coords_origin(coords),
+            (None, Origin::Current) => match self.fallback_origin {
+                Some(ref target) => {
+                    let opts = self.load_options();
+                    let target = target.clone();
+                    // Resolved once, because update mode gets location repeatedly.
+                    let resolved = RefCell::new(Option::<Location>::None);
+                    Box::new(move || {
+                        load_current_location_or(|| {
+                            if let Some(ref loc) = *resolved.borrow() {
+                                return Ok(loc.clone());
+                            }
+                            let loc = resolve_location(&opts, &target)
+                                .err_msg("failed to resolve 'fallback_origin'")?;
+                            *resolved.borrow_mut() = Some(loc.clone());
+                            Ok(loc)
+                        })
+                    })
+                }
+                None => Box::new(load_current_location),
+            },
+            (None, Origin::Sol) => Box::new(sol_origin),
+        };
+
+        Ok(match self.eddiscovery_export {
+            Some(ref path) => {
+                // Loaded once, because export is not updated while running.
+                let exported =
+                    load_exported_visits(path).err_msg("failed to load 'eddiscovery_export'")?;
+                Box::new(move || {
+                    let (location, mut visited) = get_loc()?;
+                    visited.merge(&exported);
+                    Ok((location, visited))
+                })
+            }
+            None => get_loc,
+        })
+    }
+
+    pub fn days_transform(&self) -> DaysTransform {
+        match self.score.transform {
+            Transform::Linear => DaysTransform::Linear,
+            Transform::Log => DaysTransform::Log,
+            Transform::Cap => DaysTransform::Cap(self.score.cap_days),
+        }
+    }
+
+    /// Score multiplier of targeted economies, if configured.
+    pub fn economy_boost(&self) -> Result<Option<EconomyBoost>, Fail> {
+        let b = match self.score.economy_boost {
+            Some(ref b) => b,
+            None => return Ok(None),
+        };
+        if !(b.factor > 0.0 && b.factor.is_finite()) {
+            return Err(Fail::new("'score.economy_boost.factor' must be positive"));
+        }
+        Ok(Some(EconomyBoost {
+            economies: b.list.iter().cloned().collect(),
+            factor: b.factor,
+            include_secondary: b.include_secondary,
+        }))
+    }
+
+    /// Travel time estimate shared by scoring and route planning.
+    pub fn travel_cost(&self) -> Arc<dyn TravelCost + Send + Sync> {
+        let t = &self.travel;
+        let jumps = Jumps {
+            jump_range: t.jump_range,
+            secs_per_jump: t.secs_per_jump,
+            secs_per_station: t.secs_per_station,
+            secs_per_ls: t.secs_per_jump / t.jump_range * self.score.arrival_weight,
+        };
+        match t.model {
+            TravelModel::StraightLine => Arc::new(StraightLine::new(
+                t.jump_range,
+                t.secs_per_jump,
+                self.score.arrival_weight,
+            )),
+            TravelModel::Jumps => Arc::new(jumps),
+            TravelModel::Neutron => Arc::new(Neutron(jumps)),
+            TravelModel::Supercruise => Arc::new(Supercruise(jumps)),
+        }
+    }
+
+    pub fn printer(&self) -> TextPrinter {
+        let mut printer = TextPrinter::new();
+        if self.display.compact {
+            printer.set_compact(self.display.type_codes.clone());
+        }
+        printer.set_danger(self.display.danger);
+        printer.set_max_width(self.display.max_width);
+        printer.set_neglected_days(self.refresh.as_ref().map(|r| r.neglected_days));
+        if let Some(ref daemon) = self.daemon {
+            printer.set_output(Some(state_file(&daemon.log)));
+        }
+        printer
+    }
+
+    pub fn lang(&self) -> Lang {
+        self.lang.unwrap_or_else(Lang::detect)
+    }
+
+    pub fn load_options(&self) -> LoadOptions {
+        let defaults = LoadOptions::default();
+        LoadOptions {
+            network: self.network.options(),
+            skip_bad_records: self.skip_bad_records,
+            stations_urls: or_default(&self.dumps.stations, defaults.stations_urls),
+            systems_urls: or_default(&self.dumps.systems, defaults.systems_urls),
+            stations_dump: self.dumps.stations_dump.as_ref().map(PathBuf::from),
+            systems_dump: self.dumps.systems_dump.as_ref().map(PathBuf::from),
+            full_systems: self.dumps.full_systems,
+            full_systems_urls: or_default(
+                &self.dumps.full_systems_urls,
+                defaults.full_systems_urls,
+            ),
+            threads: if self.parallel {
+                self.threads.unwrap_or(0)
+            } else {
+                1
+            },
+            track_refresh: self.refresh.is_some(),
+            track_changes: self.track_changes,
         }
     }
 
@@ -122,63 +1090,689 @@ impl Config {
         self.max_entries
     }
 
-    pub fn mode(&self) -> mode::Mode {
-        match self.mode {
+    /// Whether entries are fitted to terminal height. Not in daemon, which writes to log file.
+    pub fn fit_height(&self) -> bool {
+        self.display.fit_height && self.daemon.is_none()
+    }
+
+    pub fn mode(&self) -> Result<mode::Mode, Fail> {
+        Ok(match self.mode {
             Mode::Oneshot => mode::Mode::Oneshot,
-            Mode::Update => mode::Mode::Update,
-        }
+            Mode::Update => mode::Mode::Update(mode::UpdateOptions {
+                pause_after: self.pause_after.map(|m| Duration::from_secs(m * 60)),
+                reload_dumps: self.reload_dumps.map(|m| Duration::from_secs(m * 60)),
+            }),
+            // Waits for keys, so that SIGHUP can't be handled.
+            Mode::Select if self.is_daemon() => {
+                return Err(Fail::new("select mode can't run as daemon"));
+            }
+            Mode::Select => mode::Mode::Select,
+            Mode::Poll => mode::Mode::Poll(Duration::from_secs(self.poll_interval)),
+            Mode::Scheduled => {
+                let cfg = self
+                    .schedule
+                    .as_ref()
+                    .err_msg("scheduled mode requires 'schedule.at' or '--at'")?;
+                let schedule = Schedule::parse(&cfg.at).err_msg("failed parse 'schedule.at'")?;
+                let report = Report {
+                    file: cfg.report.as_ref().map(PathBuf::from),
+                    webhook: cfg.webhook.clone(),
+                };
+                mode::Mode::Scheduled(schedule, report)
+            }
+            Mode::Route => mode::Mode::Route(self.travel_cost()),
+            Mode::Serve => mode::Mode::Serve(mode::ServeOptions {
+                listen: self
+                    .serve
+                    .listen
+                    .parse::<SocketAddr>()
+                    .err_msg(format!("can't parse 'serve.listen': {}", self.serve.listen))?,
+                refresh: self.serve.refresh,
+                allow_origin: self.serve.allow_origin.clone(),
+            }),
+        })
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+/// Location from system name or `"x,y,z"` coordinates.
+fn resolve_location(opts: &LoadOptions, target: &str) -> Result<Location, Fail> {
+    if let Some(coords) = Coords::parse(target) {
+        return Ok(Location {
+            star_system: target.to_owned(),
+            star_pos: coords,
+        });
+    }
+
+    let system = find_system(opts, target)?;
+    Ok(Location {
+        star_system: system.name,
+        star_pos: system.coords,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Command {
+    #[default]
+    Search,
+    /// Search from each origin read from `source`.
+    Batch {
+        source: String,
+        format: Format,
+    },
+    /// Recorded changes of economies and types.
+    Changes {
+        days: Option<i64>,
+    },
+    Clusters {
+        radius: f64,
+        min_size: usize,
+        expand: Option<usize>,
+    },
+    CompareOrigins {
+        origins: Vec<String>,
+    },
+    Directions {
+        band: f64,
+    },
+    /// Write effective filter chain to stdout, as TOML or JSON with `--format json`.
+    DumpFilters,
+    ExportCoords {
+        cluster_radius: Option<f64>,
+        min_size: usize,
+        output: String,
+    },
+    Extract {
+        around: String,
+        radius: f64,
+        output: String,
+    },
+    Missing {
+        output: Option<String>,
+    },
+    /// Stations nearest to the origin, regardless of filter.
+    Nearest {
+        count: usize,
+    },
+    /// Tour split into daily routes.
+    Plan {
+        days: usize,
+        output: String,
+    },
+    Roll,
+    SubmitCoverage {
+        dry_run: bool,
+    },
+    Tour {
+        cluster: Option<usize>,
+        radius: f64,
+        min_size: usize,
+        output: Option<String>,
+    },
+    /// Write visited stations to file.
+    VisitedExport {
+        output: String,
+    },
+    /// Read visited stations from file, replacing or merged into current ones.
+    VisitedImport {
+        input: String,
+        merge: bool,
+    },
+}
+
+/// Output format of search results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    /// A line of JSON for each result, by `JsonPrinter`. Warnings are written to stderr.
+    Json,
+    /// Records only, as CSV. Header and warnings are written to stderr.
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct OutdatedDays {
     information: Option<i64>,
     market: Option<i64>,
     shipyard: Option<i64>,
     outfitting: Option<i64>,
+    /// If false, only service timestamps (market, shipyard and outfitting) are used.
+    #[serde(default = "default_use_information")]
+    use_information: bool,
+    /// If true, only information is used for megaships, which often lack services.
+    #[serde(default)]
+    megaship_information_only: bool,
+    /// Days for all categories by station type, instead of above.
+    #[serde(default, deserialize_with = "deserialize_by_type")]
+    by_type: HashMap<StationType, i64>,
+    distance_scale: Option<DistanceScaleConfig>,
+    /// How to treat services without timestamps.
+    #[serde(default)]
+    missing: MissingTimes,
+    /// Only these categories make stations outdated. All categories if empty.
+    #[serde(default)]
+    require: Vec<RequiredCategory>,
+}
+
+fn default_use_information() -> bool {
+    true
 }
 
 impl OutdatedDays {
-    fn filter(&self, filters: &mut Filters) {
-        if let Some(days) = self.information {
-            filters.add(Filter::Days(Days::Information(days)));
+    /// `by_type` only overrides thresholds of categories in use, so it needs one of them.
+    fn validate(&self) -> Result<(), Fail> {
+        if !self.by_type.is_empty() && self.days_list().is_empty() {
+            return Err(Fail::new(
+                "'days.by_type' needs a threshold of a used category, such as 'days.information'",
+            ));
+        }
+        Ok(())
+    }
+
+    fn required(&self, category: Category) -> bool {
+        self.require.is_empty() || self.require.iter().any(|r| r.category() == category)
+    }
+
+    /// Thresholds of used categories.
+    fn days_list(&self) -> Vec<Days> {
+        let mut days_list = Vec::new();
+        if let (Some(days), true) = (self.information, self.use_information) {
+            if self.required(Category::Information) {
+                days_list.push(Days::Information(days));
+            }
+        }
+        if let (Some(days), true) = (self.market, self.required(Category::Market)) {
+            days_list.push(Days::Market(days));
         }
-        if let Some(days) = self.market {
-            filters.add(Filter::Days(Days::Market(days)));
+        if let (Some(days), true) = (self.shipyard, self.required(Category::Shipyard)) {
+            days_list.push(Days::Shipyard(days));
         }
-        if let Some(days) = self.shipyard {
-            filters.add(Filter::Days(Days::Shipyard(days)));
+        if let (Some(days), true) = (self.outfitting, self.required(Category::Outfitting)) {
+            days_list.push(Days::Outfitting(days));
         }
-        if let Some(days) = self.outfitting {
-            filters.add(Filter::Days(Days::Outfitting(days)));
+        days_list
+    }
+
+    fn filter(&self, filters: &mut Filters) {
+        self.missing
+            .filter(filters, |category| self.required(category));
+        if self.megaship_information_only {
+            filters.add(Filter::MegaShipInformationOnly);
+        }
+
+        for days in self.days_list() {
+            let filter = Filter::Days(days, self.by_type.clone());
+            match self.distance_scale {
+                Some(ref scale) => filters.add(Filter::DistanceScaled(
+                    Box::new(filter),
+                    DistanceScale {
+                        from: scale.from,
+                        days_per_ly: scale.days_per_ly,
+                    },
+                )),
+                None => filters.add(filter),
+            }
         }
         filters.add(Filter::Outdated);
     }
 }
 
+/// Handling of missing service timestamps by category.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub struct MissingTimes {
+    #[serde(default)]
+    market: MissingTime,
+    #[serde(default)]
+    shipyard: MissingTime,
+    #[serde(default)]
+    outfitting: MissingTime,
+}
+
+impl MissingTimes {
+    fn filter(&self, filters: &mut Filters, required: impl Fn(Category) -> bool) {
+        let categories = [
+            (Category::Market, self.market),
+            (Category::Shipyard, self.shipyard),
+            (Category::Outfitting, self.outfitting),
+        ];
+        for &(category, missing) in &categories {
+            if missing == MissingTime::Outdated && required(category) {
+                filters.add(Filter::MissingOutdated(category));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequiredCategory {
+    Information,
+    Market,
+    Shipyard,
+    Outfitting,
+}
+
+impl RequiredCategory {
+    fn category(self) -> Category {
+        match self {
+            RequiredCategory::Information => Category::Information,
+            RequiredCategory::Market => Category::Market,
+            RequiredCategory::Shipyard => Category::Shipyard,
+            RequiredCategory::Outfitting => Category::Outfitting,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingTime {
+    /// The station doesn't offer the service, so that never be outdated.
+    #[default]
+    NotApplicable,
+    /// The service is offered but never reported, as if outdated since the game release.
+    Outdated,
+}
+
+/// Threshold days increase with distance from origin.
 #[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DistanceScaleConfig {
+    #[serde(default = "default_scale_from", deserialize_with = "deserialize_ly")]
+    from: f64,
+    days_per_ly: f64,
+}
+
+fn default_scale_from() -> f64 {
+    20.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum Mode {
+    #[default]
     Oneshot,
     Update,
+    Select,
+    Poll,
+    Scheduled,
+    Route,
+    Serve,
+}
+
+fn or_default(list: &[String], default: Vec<String>) -> Vec<String> {
+    if list.is_empty() {
+        default
+    } else {
+        list.to_vec()
+    }
 }
 
-impl Default for Mode {
-    fn default() -> Mode {
-        Mode::Oneshot
+fn default_parallel() -> bool {
+    true
+}
+
+fn default_concurrent_checks() -> bool {
+    true
+}
+
+fn default_poll_interval() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScoreConfig {
+    #[serde(default)]
+    transform: Transform,
+    #[serde(default = "default_cap_days")]
+    cap_days: i64,
+    /// Ly added to distance per Ls of arrival distance.
+    #[serde(default = "default_arrival_weight")]
+    arrival_weight: f64,
+    economy_boost: Option<EconomyBoostConfig>,
+}
+
+impl Default for ScoreConfig {
+    fn default() -> ScoreConfig {
+        ScoreConfig {
+            transform: Transform::default(),
+            cap_days: default_cap_days(),
+            arrival_weight: default_arrival_weight(),
+            economy_boost: None,
+        }
+    }
+}
+
+fn default_cap_days() -> i64 {
+    365
+}
+
+fn default_arrival_weight() -> f64 {
+    DEFAULT_ARRIVAL_WEIGHT
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EconomyBoostConfig {
+    list: Vec<Economy>,
+    #[serde(default = "default_boost_factor")]
+    factor: f64,
+    #[serde(default)]
+    include_secondary: bool,
+}
+
+fn default_boost_factor() -> f64 {
+    2.0
+}
+
+/// Estimate of travel time, see `travel` module.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TravelConfig {
+    #[serde(default)]
+    model: TravelModel,
+    #[serde(default = "default_jump_range", deserialize_with = "deserialize_ly")]
+    jump_range: f64,
+    #[serde(default = "default_secs_per_jump")]
+    secs_per_jump: f64,
+    #[serde(default = "default_secs_per_station")]
+    secs_per_station: f64,
+}
+
+impl Default for TravelConfig {
+    fn default() -> TravelConfig {
+        TravelConfig {
+            model: TravelModel::default(),
+            jump_range: default_jump_range(),
+            secs_per_jump: default_secs_per_jump(),
+            secs_per_station: default_secs_per_station(),
+        }
+    }
+}
+
+impl TravelConfig {
+    /// Rejects values making travel costs zero, negative or NaN.
+    fn validate(&self) -> Result<(), Fail> {
+        if !(self.jump_range > 0.0 && self.jump_range.is_finite()) {
+            return Err(Fail::new("'travel.jump_range' must be positive"));
+        }
+        if !(self.secs_per_jump > 0.0 && self.secs_per_jump.is_finite()) {
+            return Err(Fail::new("'travel.secs_per_jump' must be positive"));
+        }
+        if !(self.secs_per_station >= 0.0 && self.secs_per_station.is_finite()) {
+            return Err(Fail::new("'travel.secs_per_station' must not be negative"));
+        }
+        Ok(())
+    }
+}
+
+fn default_jump_range() -> f64 {
+    DEFAULT_JUMP_RANGE
+}
+
+fn default_secs_per_jump() -> f64 {
+    DEFAULT_SECS_PER_JUMP
+}
+
+fn default_secs_per_station() -> f64 {
+    DEFAULT_SECS_PER_STATION
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TravelModel {
+    #[default]
+    StraightLine,
+    Jumps,
+    Neutron,
+    Supercruise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    #[default]
+    Linear,
+    Log,
+    Cap,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct NetworkConfig {
+    #[serde(default)]
+    hosts: HashMap<String, IpAddr>,
+    ip_version: Option<IpVersionConfig>,
+}
+
+impl NetworkConfig {
+    fn options(&self) -> NetworkOptions {
+        NetworkOptions {
+            hosts: self.hosts.clone(),
+            ip_version: self.ip_version.map(|v| match v {
+                IpVersionConfig::V4 => IpVersion::V4,
+                IpVersionConfig::V6 => IpVersion::V6,
+            }),
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpVersionConfig {
+    V4,
+    V6,
+}
+
+/// Overrides of cache and state directories.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PathsConfig {
+    cache: Option<String>,
+    state: Option<String>,
+}
+
+/// Sound after a jump when one of the top `top` stations is within jump range of `[travel]`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AlertConfig {
+    #[serde(default = "default_alert_top")]
+    top: usize,
+    /// Program and arguments to play sound. Terminal bell if empty.
+    #[serde(default)]
+    command: Vec<String>,
+}
+
+fn default_alert_top() -> usize {
+    3
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NotifyConfig {
+    /// Entries watched for new candidates.
+    #[serde(default = "default_notify_top")]
+    top: usize,
+}
+
+fn default_notify_top() -> usize {
+    3
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DaemonConfig {
+    #[serde(default = "default_daemon_log")]
+    log: String,
+    #[serde(default = "default_daemon_status")]
+    status: String,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> DaemonConfig {
+        DaemonConfig {
+            log: default_daemon_log(),
+            status: default_daemon_status(),
+        }
+    }
+}
+
+fn default_daemon_log() -> String {
+    "near-old-stations.log".to_owned()
+}
+
+fn default_daemon_status() -> String {
+    "status.json".to_owned()
+}
+
+/// Opt-in submission of aggregated statistics. Nothing is sent unless `enabled` and `url` are set.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SubmitConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub url: Option<String>,
+    #[serde(default = "default_cell_size", deserialize_with = "deserialize_ly")]
+    pub cell_size: f64,
+}
+
+impl Default for SubmitConfig {
+    fn default() -> SubmitConfig {
+        SubmitConfig {
+            enabled: false,
+            url: None,
+            cell_size: default_cell_size(),
+        }
+    }
+}
+
+fn default_cell_size() -> f64 {
+    100.0
+}
+
+/// Tracking how often each station is refreshed, over loads of updated dumps.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RefreshConfig {
+    #[serde(default = "default_neglected_days")]
+    neglected_days: i64,
+    #[serde(default)]
+    neglected_only: bool,
+}
+
+fn default_neglected_days() -> i64 {
+    180
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ServeConfig {
+    #[serde(default = "default_serve_listen")]
+    listen: String,
+    /// Seconds to reload the HTML page.
+    #[serde(default = "default_serve_refresh")]
+    refresh: u64,
+    /// Origin allowed to fetch results from other pages, such as `"*"`.
+    allow_origin: Option<String>,
+}
+
+impl Default for ServeConfig {
+    fn default() -> ServeConfig {
+        ServeConfig {
+            listen: default_serve_listen(),
+            refresh: default_serve_refresh(),
+            allow_origin: None,
+        }
+    }
+}
+
+fn default_serve_listen() -> String {
+    "127.0.0.1:8080".to_owned()
+}
+
+fn default_serve_refresh() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScheduleConfig {
+    at: String,
+    report: Option<String>,
+    webhook: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default)]
 pub enum Origin {
     #[serde(rename = "current")]
+    #[default]
     Current,
     Sol,
 }
 
-impl Default for Origin {
-    fn default() -> Origin {
-        Origin::Current
+/// Map keyed by station type. Unlike dumps, unknown type names are rejected.
+fn deserialize_by_type<'de, D, V>(d: D) -> Result<HashMap<StationType, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    HashMap::<String, V>::deserialize(d)?
+        .into_iter()
+        .map(|(name, v)| match StationType::from_known_name(&name) {
+            Some(t) => Ok((t, v)),
+            None => Err(D::Error::custom(format!("unknown station type {:?}", name))),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DisplayConfig {
+    #[serde(default)]
+    compact: bool,
+    #[serde(default, deserialize_with = "deserialize_by_type")]
+    type_codes: HashMap<StationType, String>,
+    #[serde(default)]
+    danger: bool,
+    /// Limit entries to terminal height in update and select modes.
+    #[serde(default = "default_fit_height")]
+    fit_height: bool,
+    max_width: Option<usize>,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> DisplayConfig {
+        DisplayConfig {
+            compact: false,
+            type_codes: HashMap::new(),
+            danger: false,
+            fit_height: default_fit_height(),
+            max_width: None,
+        }
+    }
+}
+
+fn default_fit_height() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DumpsConfig {
+    #[serde(default)]
+    stations: Vec<String>,
+    #[serde(default)]
+    systems: Vec<String>,
+    /// Local stations dump, downloaded out-of-band.
+    stations_dump: Option<String>,
+    /// Local systems dump, downloaded out-of-band.
+    systems_dump: Option<String>,
+    /// Find systems missing from systems dump in the full systems dump, which is very large.
+    #[serde(default)]
+    full_systems: bool,
+    #[serde(default)]
+    full_systems_urls: Vec<String>,
+    /// Check updates of all dumps concurrently and show them in a table before loading.
+    #[serde(default = "default_concurrent_checks")]
+    concurrent_checks: bool,
+}
+
+impl Default for DumpsConfig {
+    fn default() -> DumpsConfig {
+        DumpsConfig {
+            stations: Vec::new(),
+            systems: Vec::new(),
+            stations_dump: None,
+            systems_dump: None,
+            full_systems: false,
+            full_systems_urls: Vec::new(),
+            concurrent_checks: default_concurrent_checks(),
+        }
     }
 }
 
@@ -190,18 +1784,72 @@ pub struct FilterConfig {
     pub exclude_names: Vec<String>,
     #[serde(default)]
     pub exclude_systems: Vec<String>,
+    #[serde(default)]
+    exclude_damaged: bool,
+    #[serde(default)]
+    exclude_powers: Vec<String>,
+    #[serde(default)]
+    exclude_security: Vec<String>,
+    revisit_after_days: Option<i64>,
+    #[serde(default)]
+    scoopable_only: bool,
+    #[serde(default)]
+    skip_visited: bool,
+    #[serde(default)]
+    within_bubble: bool,
+    #[serde(default = "default_bubble_radius", deserialize_with = "deserialize_ly")]
+    bubble_radius: f64,
+    /// Name of a patch in `patches::PATCHES`.
+    updated_before_patch: Option<String>,
 
     distance_to_arrival: Option<DistanceToArrival>,
     economy: Option<EconomyFilter>,
     pad_size: Option<PadSize>,
     planetary: Option<Planetary>,
+    carrier: Option<Carrier>,
+    settlement: Option<Settlement>,
+    thargoid: Option<Thargoid>,
 }
 
 impl FilterConfig {
-    fn filter(&self, filters: &mut Filters) -> Result<(), Fail> {
+    fn filter(&self, filters: &mut Filters, network: &NetworkOptions) -> Result<(), Fail> {
         filters.add(Filter::StationName(self.exclude_names()?));
         filters.add(Filter::SystemName(self.exclude_systems()?));
 
+        if self.exclude_damaged {
+            filters.add(Filter::ExcludeDamaged);
+        }
+        if !self.exclude_powers.is_empty() {
+            let powers = self.exclude_powers.iter().cloned().collect();
+            filters.add(Filter::ExcludePowers(powers));
+        }
+        if !self.exclude_security.is_empty() {
+            let levels = self.exclude_security.iter().cloned().collect();
+            filters.add(Filter::ExcludeSecurity(levels));
+        }
+        if let Some(days) = self.revisit_after_days {
+            filters.add(Filter::RevisitAfter(days));
+        }
+        if self.scoopable_only {
+            filters.add(Filter::ScoopableOnly);
+        }
+        if self.skip_visited {
+            filters.add(Filter::SkipVisited);
+        }
+        if self.within_bubble {
+            filters.add(Filter::WithinBubble(self.bubble_radius));
+        }
+        if let Some(ref name) = self.updated_before_patch {
+            let patch = find_patch(name).ok_or_else(|| {
+                let names: Vec<&str> = PATCHES.iter().map(Patch::name).collect();
+                Fail::new(format!(
+                    "unknown patch in 'updated_before_patch': {} (known: {})",
+                    name,
+                    names.join(", ")
+                ))
+            })?;
+            filters.add(Filter::UpdatedBefore(patch.released()));
+        }
         if let Some(ref f) = self.distance_to_arrival {
             f.filter(filters)?;
         }
@@ -214,21 +1862,35 @@ impl FilterConfig {
         if let Some(ref f) = self.planetary {
             f.filter(filters)?;
         }
+        if let Some(ref f) = self.carrier {
+            f.filter(filters)?;
+        }
+        if let Some(ref f) = self.settlement {
+            f.filter(filters)?;
+        }
+        if let Some(ref f) = self.thargoid {
+            f.filter(filters, network)?;
+        }
 
         Ok(())
     }
 
     pub fn exclude_names(&self) -> Result<RegexSet, Fail> {
-        Ok(RegexSet::new(&self.exclude_names).err_msg("failed parse 'exclude'")?)
+        RegexSet::new(&self.exclude_names).err_msg("failed parse 'exclude'")
     }
 
     pub fn exclude_systems(&self) -> Result<RegexSet, Fail> {
-        Ok(RegexSet::new(&self.exclude_systems).err_msg("failed parse 'exclude_systems'")?)
+        RegexSet::new(&self.exclude_systems).err_msg("failed parse 'exclude_systems'")
     }
 }
 
+fn default_bubble_radius() -> f64 {
+    500.0
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 struct DistanceToArrival {
+    #[serde(deserialize_with = "deserialize_ls")]
     max: f64,
 }
 
@@ -256,13 +1918,18 @@ impl EconomyFilter {
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 struct PadSize {
+    #[serde(default)]
     l_pad_only: bool,
+    #[serde(default)]
+    m_pad_only: bool,
 }
 
 impl PadSize {
     fn filter(&self, filters: &mut Filters) -> Result<(), Fail> {
         if self.l_pad_only {
             filters.add(Filter::LPadOnly);
+        } else if self.m_pad_only {
+            filters.add(Filter::MPadOnly);
         }
         Ok(())
     }
@@ -281,3 +1948,87 @@ impl Planetary {
         Ok(())
     }
 }
+
+/// Fleet carriers, which move around and are updated by their owners.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Carrier {
+    include: bool,
+}
+
+impl Carrier {
+    fn filter(&self, filters: &mut Filters) -> Result<(), Fail> {
+        if !self.include {
+            filters.add(Filter::ExcludeCarriers);
+        }
+        Ok(())
+    }
+}
+
+/// On-foot settlements. They are also planetary.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Settlement {
+    include: bool,
+}
+
+impl Settlement {
+    fn filter(&self, filters: &mut Filters) -> Result<(), Fail> {
+        if !self.include {
+            filters.add(Filter::ExcludeSettlements);
+        }
+        Ok(())
+    }
+}
+
+const THARGOID_SYSTEMS_FILE: &str = "thargoid_systems.txt";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Thargoid {
+    #[serde(default)]
+    systems: Vec<String>,
+    url: Option<String>,
+}
+
+impl Thargoid {
+    fn filter(&self, filters: &mut Filters, network: &NetworkOptions) -> Result<(), Fail> {
+        let mut names: HashSet<String> = self.systems.iter().map(|s| s.to_lowercase()).collect();
+
+        if let Some(ref url) = self.url {
+            let list = load_name_list(THARGOID_SYSTEMS_FILE, url, network)
+                .err_msg("failed to load Thargoid war systems list")?;
+            names.extend(list.iter().map(|s| s.to_lowercase()));
+        }
+
+        filters.add(Filter::ExcludeSystems(names));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn by_type_needs_used_category() {
+        let days = |s: &str| toml::from_str::<OutdatedDays>(s).unwrap().validate();
+        assert!(days("market = 30\n[by_type]\n\"Outpost\" = 60").is_ok());
+        assert!(days("[by_type]\n\"Outpost\" = 60").is_err());
+        assert!(
+            days("use_information = false\ninformation = 30\n[by_type]\n\"Outpost\" = 60").is_err()
+        );
+        assert!(
+            days("market = 30\nrequire = [\"shipyard\"]\n[by_type]\n\"Outpost\" = 60").is_err()
+        );
+        assert!(days("").is_ok());
+    }
+
+    #[test]
+    fn config_rejects_unknown_station_types() {
+        let display: DisplayConfig = toml::from_str("[type_codes]\n\"Outpost\" = \"O\"").unwrap();
+        assert_eq!(display.type_codes[&StationType::Outpost], "O");
+        assert!(toml::from_str::<DisplayConfig>("[type_codes]\n\"Outpots\" = \"O\"").is_err());
+
+        // Dumps keep types unknown to this version.
+        let st_type: StationType = serde_json::from_str("\"Space Elevator\"").unwrap();
+        assert_eq!(st_type, StationType::Unknown("Space Elevator".to_owned()));
+    }
+}
@@ -1,6 +1,14 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+/// Sol is about 26 kLy from the galactic center, and the galaxy is about 100 kLy across.
+const MAX_COORD: f64 = 100_000.0;
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct Coords {
     x: f64,
     y: f64,
@@ -8,6 +16,23 @@ pub struct Coords {
 }
 
 impl Coords {
+    pub fn new(x: f64, y: f64, z: f64) -> Coords {
+        Coords { x, y, z }
+    }
+
+    /// Parse `"x,y,z"`.
+    pub fn parse(s: &str) -> Option<Coords> {
+        let values: Vec<&str> = s.split(',').map(str::trim).collect();
+        if values.len() != 3 {
+            return None;
+        }
+        Some(Coords {
+            x: values[0].parse().ok()?,
+            y: values[1].parse().ok()?,
+            z: values[2].parse().ok()?,
+        })
+    }
+
     pub fn zero() -> Coords {
         Coords {
             x: 0.0,
@@ -16,8 +41,170 @@ impl Coords {
         }
     }
 
+    pub fn x(self) -> f64 {
+        self.x
+    }
+
+    pub fn y(self) -> f64 {
+        self.y
+    }
+
+    pub fn z(self) -> f64 {
+        self.z
+    }
+
+    /// False for NaN, infinite, or out of the galaxy coordinates, which come from corrupt dump rows.
+    pub fn is_valid(self) -> bool {
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|v| v.is_finite() && v.abs() <= MAX_COORD)
+    }
+
     pub fn dist_to(self, other: Coords) -> f64 {
-        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2) + (self.z - other.z).powi(2))
-            .sqrt()
+        (other - self).length()
+    }
+
+    /// Distance in galactic plane, ignoring height (y).
+    pub fn horizontal_dist_to(self, other: Coords) -> f64 {
+        let d = other - self;
+        (d.x * d.x + d.z * d.z).sqrt()
+    }
+
+    /// Coordinates in the frame whose origin is `origin`.
+    pub fn relative_to(self, origin: Coords) -> Coords {
+        self - origin
+    }
+
+    pub fn scale(self, k: f64) -> Coords {
+        Coords {
+            x: self.x * k,
+            y: self.y * k,
+            z: self.z * k,
+        }
+    }
+
+    pub fn dot(self, other: Coords) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    /// Bearing from `self` to `other` in galactic plane, in degrees within `[0, 360)`.
+    ///
+    /// 0 is coreward (+z) and 90 is antispinward (+x). `None` if `other` is right above or below.
+    pub fn bearing_to(self, other: Coords) -> Option<f64> {
+        let d = other - self;
+        if d.x == 0.0 && d.z == 0.0 {
+            return None;
+        }
+        let deg = d.x.atan2(d.z).to_degrees();
+        Some(if deg < 0.0 { deg + 360.0 } else { deg })
+    }
+
+    /// Mean of coordinates. `None` if empty.
+    pub fn centroid<I: IntoIterator<Item = Coords>>(coords: I) -> Option<Coords> {
+        let (sum, n) = coords
+            .into_iter()
+            .fold((Coords::zero(), 0usize), |(sum, n), c| (sum + c, n + 1));
+        if n == 0 {
+            None
+        } else {
+            Some(sum.scale(1.0 / n as f64))
+        }
+    }
+}
+
+impl Add for Coords {
+    type Output = Coords;
+
+    fn add(self, other: Coords) -> Coords {
+        Coords {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl Sub for Coords {
+    type Output = Coords;
+
+    fn sub(self, other: Coords) -> Coords {
+        Coords {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl Neg for Coords {
+    type Output = Coords;
+
+    fn neg(self) -> Coords {
+        self.scale(-1.0)
+    }
+}
+
+impl Mul<f64> for Coords {
+    type Output = Coords;
+
+    fn mul(self, k: f64) -> Coords {
+        self.scale(k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(l: f64, r: f64) -> bool {
+        (l - r).abs() < 1e-9
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Coords::new(1.0, 2.0, 3.0);
+        let b = Coords::new(-4.0, 0.5, 2.0);
+        assert_eq!(a + b, Coords::new(-3.0, 2.5, 5.0));
+        assert_eq!(a - b, Coords::new(5.0, 1.5, 1.0));
+        assert_eq!(a * 2.0, Coords::new(2.0, 4.0, 6.0));
+        assert_eq!(-a, Coords::new(-1.0, -2.0, -3.0));
+        assert_eq!(a.relative_to(b), a - b);
+        assert!(approx(a.dot(b), -4.0 + 1.0 + 6.0));
+    }
+
+    #[test]
+    fn distances() {
+        let a = Coords::new(1.0, 2.0, 3.0);
+        let b = Coords::new(4.0, 6.0, 3.0);
+        assert!(approx(a.dist_to(b), 5.0));
+        assert!(approx(b.dist_to(a), 5.0));
+        assert!(approx(a.horizontal_dist_to(b), 3.0));
+        assert!(approx(Coords::new(3.0, 0.0, 4.0).length(), 5.0));
+    }
+
+    #[test]
+    fn bearing() {
+        let o = Coords::new(10.0, 5.0, -10.0);
+        let at = |x: f64, z: f64| o.bearing_to(o + Coords::new(x, 100.0, z)).unwrap();
+        assert!(approx(at(0.0, 1.0), 0.0));
+        assert!(approx(at(1.0, 0.0), 90.0));
+        assert!(approx(at(0.0, -1.0), 180.0));
+        assert!(approx(at(-1.0, 0.0), 270.0));
+        assert!(approx(at(1.0, 1.0), 45.0));
+        assert_eq!(o.bearing_to(o + Coords::new(0.0, 3.0, 0.0)), None);
+    }
+
+    #[test]
+    fn centroid() {
+        assert_eq!(Coords::centroid(Vec::new()), None);
+        let c = Coords::centroid(vec![
+            Coords::new(0.0, 0.0, 0.0),
+            Coords::new(2.0, 4.0, -6.0),
+        ]);
+        assert_eq!(c, Some(Coords::new(1.0, 2.0, -3.0)));
     }
 }
@@ -0,0 +1,106 @@
+use std::fs::{rename, File};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::Utc;
+#[cfg(feature = "schemars")]
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::Serialize;
+use serde_json::to_writer_pretty;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::diagnostics::warnings;
+use crate::journal::Location;
+use crate::searcher::Record;
+
+static RELOAD: AtomicBool = AtomicBool::new(false);
+
+/// Request reload of config and dumps on SIGHUP. Does nothing on non-Unix platforms.
+#[cfg(unix)]
+pub fn install_reload_handler() {
+    extern "C" fn on_sighup(_: libc::c_int) {
+        RELOAD.store(true, Ordering::SeqCst);
+    }
+
+    unsafe {
+        let handler: extern "C" fn(libc::c_int) = on_sighup;
+        libc::signal(libc::SIGHUP, handler as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_reload_handler() {}
+
+pub fn reload_requested() -> bool {
+    RELOAD.load(Ordering::SeqCst)
+}
+
+/// Returns whether reload was requested, and clears the request.
+pub fn take_reload() -> bool {
+    RELOAD.swap(false, Ordering::SeqCst)
+}
+
+/// JSON Schema of status file.
+#[cfg(feature = "schemars")]
+pub fn schema() -> RootSchema {
+    schema_for!(Status<'static>)
+}
+
+/// JSON file describing current state, for monitoring.
+#[derive(Debug, Clone)]
+pub struct StatusFile {
+    path: PathBuf,
+}
+
+impl StatusFile {
+    pub fn new<P: AsRef<Path>>(path: P) -> StatusFile {
+        StatusFile {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    pub fn write(&self, location: &Location, records: &[Record]) -> Result<(), Fail> {
+        let status = Status {
+            pid: process::id(),
+            updated: Utc::now().to_rfc3339(),
+            origin: &location.star_system,
+            records: records.len(),
+            top: records.first().map(|r| Top {
+                system: &r.station.system_name,
+                station: &r.station.name,
+                distance: r.distance,
+                days: r.outdated().map(|o| o.days),
+            }),
+            warnings: warnings().iter().map(ToString::to_string).collect(),
+        };
+
+        // Write whole file at once, so that readers never see partial content.
+        let tmp_path = self.path.with_extension("tmp");
+        let f = File::create(&tmp_path).err_msg(format!("can't create file: {:?}", tmp_path))?;
+        to_writer_pretty(f, &status).err_msg("can't encode status")?;
+        rename(&tmp_path, &self.path).err_msg(format!("can't write file: {:?}", self.path))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+struct Status<'a> {
+    pid: u32,
+    updated: String,
+    origin: &'a str,
+    records: usize,
+    top: Option<Top<'a>>,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+struct Top<'a> {
+    system: &'a str,
+    station: &'a str,
+    distance: f64,
+    days: Option<i64>,
+}
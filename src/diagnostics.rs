@@ -0,0 +1,77 @@
+use std::fmt;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+use crate::i18n::{trf, Msg};
+
+lazy_static! {
+    static ref WARNINGS: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+}
+
+/// Non-fatal issue found while loading, shown apart from results.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// Stations dropped because their system has NaN or absurd coordinates.
+    InvalidCoords(usize),
+    /// Journal directory or files are not found, so the fallback origin is used as current location.
+    JournalNotFound(String),
+    /// Stations whose system is not in the systems dump.
+    MissingCoords(usize),
+    /// Desktop notification which couldn't be shown.
+    NotificationFailed(String),
+    /// Days since the stations dump was updated.
+    OldDump(i64),
+    /// Server error and days since cached dump was downloaded.
+    ServerUnavailable(String, i64),
+    /// Error of reloading dumps, while previous data is used.
+    ReloadFailed(String),
+    /// Records in the stations dump which can't be parsed.
+    SkippedRecords(usize),
+    /// State file which was broken and restored from its backup.
+    StateRecovered(String),
+    /// Station types not known to this version, with counts of stations.
+    UnknownStationTypes(Vec<(String, usize)>),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Warning::InvalidCoords(n) => trf(Msg::InvalidCoords, &[n]),
+            Warning::JournalNotFound(origin) => trf(Msg::JournalNotFound, &[origin]),
+            Warning::MissingCoords(n) => trf(Msg::MissingCoords, &[n]),
+            Warning::NotificationFailed(e) => trf(Msg::NotificationFailed, &[e]),
+            Warning::OldDump(days) => trf(Msg::OldDump, &[days]),
+            Warning::ServerUnavailable(e, days) => trf(Msg::ServerUnavailable, &[e, days]),
+            Warning::ReloadFailed(e) => trf(Msg::ReloadFailed, &[e]),
+            Warning::SkippedRecords(n) => trf(Msg::SkippedRecords, &[n]),
+            Warning::StateRecovered(path) => trf(Msg::StateRecovered, &[path]),
+            Warning::UnknownStationTypes(types) => {
+                let types: Vec<String> = types
+                    .iter()
+                    .map(|(name, n)| format!("{} ({})", name, n))
+                    .collect();
+                trf(Msg::UnknownStationTypes, &[&types.join(", ")])
+            }
+        };
+        f.write_str(&s)
+    }
+}
+
+/// Record a warning. The same warning is kept only once.
+pub fn warn(warning: Warning) {
+    let mut warnings = WARNINGS.lock().unwrap();
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
+    }
+}
+
+/// Warnings recorded so far.
+pub fn warnings() -> Vec<Warning> {
+    WARNINGS.lock().unwrap().clone()
+}
+
+/// Forget recorded warnings, before reloading.
+pub fn clear_warnings() {
+    WARNINGS.lock().unwrap().clear();
+}
@@ -0,0 +1,55 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::cluster::Cluster;
+use crate::searcher::Record;
+
+/// Write records with coordinates as CSV, for plotting by external 3D visualizers.
+///
+/// `clusters` must be found from the same `records`. Records not in any cluster have empty cluster column.
+pub fn export_coords<P: AsRef<Path>>(
+    path: P,
+    origin: &str,
+    records: &[Record],
+    clusters: &[Cluster],
+) -> Result<(), Fail> {
+    let path = path.as_ref();
+    let f = File::create(path).err_msg(format!("can't create file: {:?}", path))?;
+    let mut w = BufWriter::new(f);
+
+    let mut cluster_ids = vec![None; records.len()];
+    for (id, cluster) in clusters.iter().enumerate() {
+        for &i in &cluster.members {
+            cluster_ids[i] = Some(id + 1);
+        }
+    }
+
+    writeln!(w, "Station,System,X,Y,Z,Distance,Days,Origin,Cluster")?;
+    for (r, cluster_id) in records.iter().zip(cluster_ids) {
+        writeln!(
+            w,
+            "{},{},{},{},{},{:.2},{},{},{}",
+            csv_field(&r.station.name),
+            csv_field(&r.station.system_name),
+            r.station.coords.x(),
+            r.station.coords.y(),
+            r.station.coords.z(),
+            r.distance,
+            r.outdated().map_or(String::new(), |o| o.days.to_string()),
+            csv_field(origin),
+            cluster_id.map_or(String::new(), |id| id.to_string()),
+        )?;
+    }
+    Ok(())
+}
+
+pub(crate) fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
@@ -0,0 +1,140 @@
+//! C API to embed search in other tools. Built with `ffi` feature.
+//!
+//! See `include/near_old_stations.h` for usage.
+//! Strings returned from this API must be freed by `nos_string_free`.
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use serde_json::to_string;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::batch::BatchResult;
+use crate::config::Config;
+use crate::coords::Coords;
+use crate::filter::Filters;
+use crate::searcher::Searcher;
+use crate::stations::load_stations;
+use crate::visited::Visited;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Loaded stations with filters from config file.
+pub struct NosSearcher {
+    searcher: Searcher<Filters>,
+    max_entries: usize,
+}
+
+/// Load config file and stations dump. Returns null on error.
+#[no_mangle]
+pub extern "C" fn nos_searcher_load() -> *mut NosSearcher {
+    let res = guard(|| {
+        let cfg = Config::load_file().err_msg("failed load config")?;
+        let stations =
+            load_stations(&cfg.load_options()).err_msg("failed load stations dump file")?;
+        let mut searcher = Searcher::new(stations, cfg.filter()?);
+        searcher.set_days_transform(cfg.days_transform());
+        searcher.set_travel_cost(cfg.travel_cost());
+        searcher.set_economy_boost(cfg.economy_boost()?);
+        Ok(NosSearcher {
+            searcher,
+            max_entries: cfg.max_entries(),
+        })
+    });
+    match res {
+        Some(s) => Box::into_raw(Box::new(s)),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Free searcher returned by `nos_searcher_load`.
+///
+/// # Safety
+///
+/// `searcher` must be returned by `nos_searcher_load` and not freed yet, or null.
+#[no_mangle]
+pub unsafe extern "C" fn nos_searcher_free(searcher: *mut NosSearcher) {
+    if !searcher.is_null() {
+        drop(Box::from_raw(searcher));
+    }
+}
+
+/// Search around coordinates as not visited anywhere, and return results as JSON.
+///
+/// Result has the same format as an element of `--origins-from --json` output.
+/// `limit` 0 means `max_entries` in config. Returns null on error.
+///
+/// # Safety
+///
+/// `searcher` must be returned by `nos_searcher_load` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn nos_search_at(
+    searcher: *const NosSearcher,
+    x: f64,
+    y: f64,
+    z: f64,
+    limit: usize,
+) -> *mut c_char {
+    let s = match searcher.as_ref() {
+        Some(s) => s,
+        None => {
+            set_error("searcher is null".to_owned());
+            return ptr::null_mut();
+        }
+    };
+
+    let res = guard(|| {
+        let origin = format!("{},{},{}", x, y, z);
+        let records = s.searcher.search_at(Coords::new(x, y, z), &Visited::new());
+        let limit = if limit == 0 { s.max_entries } else { limit };
+        let result = BatchResult::new(&origin, &records, limit);
+        let json = to_string(&result).err_msg("can't encode search result")?;
+        CString::new(json).err_msg("search result contains null character")
+    });
+    res.map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Message of the last error in this thread, or null if no error.
+#[no_mangle]
+pub extern "C" fn nos_last_error() -> *mut c_char {
+    LAST_ERROR
+        .with(|e| e.borrow_mut().take())
+        .and_then(|msg| CString::new(msg).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Free string returned by this API.
+///
+/// # Safety
+///
+/// `s` must be returned by this API and not freed yet, or null.
+#[no_mangle]
+pub unsafe extern "C" fn nos_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Run `f` keeping errors and panics from crossing FFI boundary.
+fn guard<T>(f: impl FnOnce() -> Result<T, Fail>) -> Option<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(v)) => Some(v),
+        Ok(Err(e)) => {
+            set_error(e.to_string());
+            None
+        }
+        Err(_) => {
+            set_error("panicked".to_owned());
+            None
+        }
+    }
+}
+
+fn set_error(msg: String) {
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+}
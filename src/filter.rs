@@ -1,9 +1,16 @@
-use std::collections::HashSet;
+mod spec;
 
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
 use regex::RegexSet;
 
-use crate::searcher::{self, Record};
-use crate::stations::Economy;
+use crate::coords::Coords;
+use crate::patches::GAME_RELEASE;
+use crate::searcher::{self, Category, Record};
+use crate::stations::{Economy, StationType};
+
+pub use spec::FilterFile;
 
 #[derive(Debug, Default, Clone)]
 pub struct Filters(Vec<Filter>);
@@ -31,21 +38,54 @@ impl searcher::Filter for Filters {
 
 #[derive(Debug, Clone)]
 pub enum Filter {
-    Days(Days),
+    /// Outdated days, overridden by station type.
+    Days(Days, HashMap<StationType, i64>),
     Dist(f64),
     DistToArrival(f64),
+    /// `Days` filter with threshold increased by distance from origin.
+    DistanceScaled(Box<Filter>, DistanceScale),
     Economy(HashSet<Economy>, bool),
+    ExcludeCarriers,
+    ExcludeDamaged,
+    ExcludePowers(HashSet<String>),
+    ExcludeSecurity(HashSet<String>),
+    /// Exclude stations by names, keyed by system name. Stations are ignored in interactive mode.
+    ExcludeStations(HashMap<String, HashSet<String>>),
+    /// Exclude on-foot settlements.
+    ExcludeSettlements,
+    /// Exclude systems by name. Names must be lowercase.
+    ExcludeSystems(HashSet<String>),
     IgnorePlanetary,
     LPadOnly,
+    /// Ignore service days of megaships. Must be before `Days`.
+    MegaShipInformationOnly,
+    MPadOnly,
+    /// Only stations whose median refresh interval is given days or more.
+    NeglectedOnly(i64),
+    /// Exclude stations outdated for `days` or more, which are likely removed or renamed in game.
+    /// Missing information marked by `MissingOutdated` is not counted. Must be after `Outdated`.
+    MaxDays(i64),
+    /// Treat missing timestamp of the category as outdated since the game release, unless the dump
+    /// says the station doesn't have the service. Must be before `MegaShipInformationOnly` and `Outdated`.
+    MissingOutdated(Category),
     Outdated,
+    RevisitAfter(i64),
+    /// Exclude systems with unscoopable arrival star. Systems without star info are kept.
+    ScoopableOnly,
+    /// Exclude stations with any recorded visit. Unlike `RevisitAfter`, they are never shown again.
+    SkipVisited,
     StationName(RegexSet),
     SystemName(RegexSet),
+    /// Only stations whose information is updated before given time, such as release of a patch.
+    UpdatedBefore(DateTime<Utc>),
+    /// Exclude stations farther than given Ly from Sol.
+    WithinBubble(f64),
 }
 
 impl searcher::Filter for Filter {
     fn filter<'a>(&self, record: &mut Record<'a>) -> bool {
         match self {
-            Filter::Days(days) => days.filter(record),
+            Filter::Days(days, by_type) => days.by_type(by_type, record).filter(record),
             Filter::Dist(dist) => record.distance <= *dist,
             Filter::DistToArrival(dist) => {
                 if let Some(d) = record.station.distance_to_arrival {
@@ -54,6 +94,17 @@ impl searcher::Filter for Filter {
                     false
                 }
             }
+            Filter::DistanceScaled(inner, scale) => match **inner {
+                Filter::Days(days, ref by_type) => {
+                    let days = days.by_type(by_type, record);
+                    days.with_days(
+                        days.days()
+                            .saturating_add(scale.extra_days(record.distance)),
+                    )
+                    .filter(record)
+                }
+                ref f => f.filter(record),
+            },
             Filter::Economy(list, include_secondary) => {
                 if let Some(economy) = record.station.economy {
                     if list.contains(&economy) {
@@ -67,11 +118,75 @@ impl searcher::Filter for Filter {
                 }
                 false
             }
+            Filter::ExcludeCarriers => !record.station.st_type.is_carrier(),
+            Filter::ExcludeDamaged => !record.station.is_damaged(),
+            Filter::ExcludePowers(powers) => match record.station.power {
+                Some(ref power) => !powers.contains(power),
+                None => true,
+            },
+            Filter::ExcludeSecurity(levels) => match record.station.security_level() {
+                Some(level) => !levels.contains(level),
+                None => true,
+            },
+            Filter::ExcludeStations(names) => names
+                .get(&record.station.system_name)
+                .is_none_or(|stations| !stations.contains(&record.station.name)),
+            Filter::ExcludeSettlements => !record.station.st_type.is_settlement(),
+            Filter::ExcludeSystems(names) => {
+                !names.contains(&record.station.system_name.to_lowercase())
+            }
             Filter::IgnorePlanetary => !record.station.st_type.is_planetary(),
-            Filter::LPadOnly => record.station.st_type.has_l_pad(),
+            Filter::LPadOnly => record.station.has_l_pad(),
+            Filter::MegaShipInformationOnly => {
+                if record.station.st_type == StationType::MegaShip {
+                    record.market_days.ignore();
+                    record.shipyard_days.ignore();
+                    record.outfitting_days.ignore();
+                }
+                true
+            }
+            Filter::MaxDays(max) => [
+                &record.information_days,
+                &record.market_days,
+                &record.shipyard_days,
+                &record.outfitting_days,
+            ]
+            .iter()
+            .all(|d| !d.is_outdated() || d.days().is_none_or(|days| days < *max)),
+            Filter::MissingOutdated(category) => {
+                let info = record.station.update_time().information();
+                let since_release = record.information_days.days().unwrap_or(0)
+                    + info
+                        .signed_duration_since(GAME_RELEASE.released())
+                        .num_days();
+                let st = record.station;
+                let (days, have) = match category {
+                    Category::Information => (&mut record.information_days, None),
+                    Category::Market => (&mut record.market_days, st.have_market),
+                    Category::Shipyard => (&mut record.shipyard_days, st.have_shipyard),
+                    Category::Outfitting => (&mut record.outfitting_days, st.have_outfitting),
+                };
+                if have != Some(false) {
+                    days.mark_missing(since_release);
+                }
+                true
+            }
+            Filter::MPadOnly => record.station.has_m_pad(),
+            Filter::NeglectedOnly(days) => record.station.is_neglected(*days),
             Filter::Outdated => check_outdated(record),
+            Filter::RevisitAfter(days) => record.visited_days.is_none_or(|d| d >= *days),
+            Filter::ScoopableOnly => record
+                .station
+                .primary_star
+                .as_ref()
+                .is_none_or(|star| star.is_scoopable()),
+            Filter::SkipVisited => !record.visited,
             Filter::StationName(rs) => !rs.is_match(&record.station.name),
             Filter::SystemName(rs) => !rs.is_match(&record.station.system_name),
+            Filter::UpdatedBefore(time) => record.station.update_time().information() < *time,
+            Filter::WithinBubble(radius) => {
+                record.station.coords.dist_to(Coords::zero()) <= *radius
+            }
         }
     }
 }
@@ -84,30 +199,213 @@ pub enum Days {
     Outfitting(i64),
 }
 
+impl Days {
+    pub fn days(self) -> i64 {
+        match self {
+            Days::Information(days)
+            | Days::Market(days)
+            | Days::Shipyard(days)
+            | Days::Outfitting(days) => days,
+        }
+    }
+
+    /// Override threshold by station type of the record.
+    fn by_type(self, by_type: &HashMap<StationType, i64>, record: &Record) -> Days {
+        match by_type.get(&record.station.st_type) {
+            Some(&d) => self.with_days(d),
+            None => self,
+        }
+    }
+
+    /// Same category with other threshold.
+    pub fn with_days(self, days: i64) -> Days {
+        match self {
+            Days::Information(_) => Days::Information(days),
+            Days::Market(_) => Days::Market(days),
+            Days::Shipyard(_) => Days::Shipyard(days),
+            Days::Outfitting(_) => Days::Outfitting(days),
+        }
+    }
+}
+
 impl searcher::Filter for Days {
     fn filter<'a>(&self, record: &mut Record<'a>) -> bool {
         match self {
-            Days::Information(days) => {
-                record.information_days.check(|d| d >= *days);
-            }
-            Days::Market(days) => {
-                record.market_days.check(|d| d >= *days);
-            }
-            Days::Shipyard(days) => {
-                record.shipyard_days.check(|d| d >= *days);
-            }
-            Days::Outfitting(days) => {
-                record.outfitting_days.check(|d| d >= *days);
-            }
+            Days::Information(days) => record.information_days.check_threshold(*days),
+            Days::Market(days) => record.market_days.check_threshold(*days),
+            Days::Shipyard(days) => record.shipyard_days.check_threshold(*days),
+            Days::Outfitting(days) => record.outfitting_days.check_threshold(*days),
         }
 
         true
     }
 }
 
+/// Extra days allowed for distance beyond `from` Ly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceScale {
+    pub from: f64,
+    pub days_per_ly: f64,
+}
+
+impl DistanceScale {
+    pub fn extra_days(self, distance: f64) -> i64 {
+        ((distance - self.from).max(0.0) * self.days_per_ly) as i64
+    }
+}
+
 fn check_outdated(record: &mut Record) -> bool {
     record.information_days.is_outdated()
         || record.market_days.is_outdated()
         || record.shipyard_days.is_outdated()
         || record.outfitting_days.is_outdated()
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::searcher::{new_record, Coverage, Filter as _};
+    use crate::stations::Station;
+    use crate::visited::Visited;
+
+    fn any_filter() -> impl Strategy<Value = Filter> {
+        let days = prop_oneof![
+            any::<i64>().prop_map(Days::Information),
+            any::<i64>().prop_map(Days::Market),
+            any::<i64>().prop_map(Days::Shipyard),
+            any::<i64>().prop_map(Days::Outfitting),
+        ];
+        prop_oneof![
+            days.clone().prop_map(|d| Filter::Days(d, HashMap::new())),
+            (days, any::<f64>(), any::<f64>()).prop_map(|(d, from, days_per_ly)| {
+                Filter::DistanceScaled(
+                    Box::new(Filter::Days(d, HashMap::new())),
+                    DistanceScale { from, days_per_ly },
+                )
+            }),
+            any::<f64>().prop_map(Filter::Dist),
+            any::<f64>().prop_map(Filter::DistToArrival),
+            Just(Filter::ExcludeCarriers),
+            Just(Filter::ExcludeDamaged),
+            Just(Filter::ExcludeSettlements),
+            Just(Filter::IgnorePlanetary),
+            Just(Filter::LPadOnly),
+            Just(Filter::MegaShipInformationOnly),
+            Just(Filter::MPadOnly),
+            any::<i64>().prop_map(Filter::MaxDays),
+            any::<i64>().prop_map(Filter::NeglectedOnly),
+            Just(Filter::Outdated),
+            any::<i64>().prop_map(Filter::RevisitAfter),
+            Just(Filter::ScoopableOnly),
+            Just(Filter::SkipVisited),
+            any::<f64>().prop_map(Filter::WithinBubble),
+        ]
+    }
+
+    fn any_coord() -> impl Strategy<Value = f64> {
+        prop_oneof![any::<f64>(), Just(0.0), Just(f64::NAN), Just(f64::INFINITY)]
+    }
+
+    proptest! {
+        #[test]
+        fn filters_never_panic(
+            filters in prop::collection::vec(any_filter(), 0..8),
+            info_days in 0i64..100_000,
+            market_days in prop::option::of(0i64..100_000),
+            x in any_coord(),
+            y in any_coord(),
+            z in any_coord(),
+        ) {
+            let mut station = Station::for_test(info_days, market_days);
+            station.coords = Coords::new(x, y, z);
+            let mut record = new_record(
+                &station,
+                Coords::zero(),
+                &Visited::new(),
+                Coverage::default(),
+                Utc::now(),
+            );
+
+            let mut fs = Filters::new();
+            for f in filters {
+                fs.add(f);
+            }
+            fs.filter(&mut record);
+            let _ = record.outdated();
+        }
+
+        #[test]
+        fn outdated_requires_checked_days(days in any::<i64>(), info_days in 0i64..100_000) {
+            let station = Station::for_test(info_days, None);
+            let mut record = new_record(
+                &station,
+                Coords::zero(),
+                &Visited::new(),
+                Coverage::default(),
+                Utc::now(),
+            );
+            Filter::Days(Days::Market(days), HashMap::new()).filter(&mut record);
+            // No market timestamp, so never outdated by market.
+            prop_assert!(!Filter::Outdated.filter(&mut record));
+        }
+    }
+
+    #[test]
+    fn missing_outdated_only_with_service() {
+        let days = |have_market: Option<bool>| {
+            let mut station = Station::for_test(10, None);
+            station.have_market = have_market;
+            let mut record = new_record(
+                &station,
+                Coords::zero(),
+                &Visited::new(),
+                Coverage::default(),
+                Utc::now(),
+            );
+            Filter::MissingOutdated(Category::Market).filter(&mut record);
+            record.market_days.is_outdated()
+        };
+        assert!(days(Some(true)));
+        assert!(days(None));
+        assert!(!days(Some(false)));
+    }
+
+    #[test]
+    fn m_pad_only_by_type_and_pads() {
+        use crate::stations::{LandingPads, StationType};
+
+        let passes = |st_type: StationType, pads: Option<LandingPads>| {
+            let mut station = Station::for_test(0, None);
+            station.st_type = st_type;
+            station.landing_pads = pads;
+            let mut record = new_record(
+                &station,
+                Coords::zero(),
+                &Visited::new(),
+                Coverage::default(),
+                Utc::now(),
+            );
+            Filter::MPadOnly.filter(&mut record)
+        };
+        let small_only = LandingPads {
+            large: 0,
+            medium: 0,
+            small: 2,
+        };
+        let medium = LandingPads {
+            large: 0,
+            medium: 1,
+            small: 2,
+        };
+
+        assert!(passes(StationType::Outpost, None));
+        assert!(passes(StationType::CoriolisStarport, None));
+        assert!(!passes(StationType::OdysseySettlement, None));
+        assert!(!passes(StationType::Unknown("Space Farm".to_owned()), None));
+        assert!(!passes(StationType::OdysseySettlement, Some(small_only)));
+        assert!(passes(StationType::OdysseySettlement, Some(medium)));
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use super::{Days, DistanceScale, Filter, Filters};
+use crate::searcher;
+use crate::stations::{Economy, StationType};
+
+/// Filter chain in a form which can be written as TOML or JSON and read back.
+///
+/// Each filter is a table with `filter` key naming its kind, in order of the chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterFile {
+    filters: Vec<Spec>,
+}
+
+impl FilterFile {
+    pub fn new(filters: &Filters) -> FilterFile {
+        FilterFile {
+            filters: filters.0.iter().map(Spec::new).collect(),
+        }
+    }
+
+    /// Load file written by `to_toml` or `to_json`. Files with `.json` extension are read as JSON.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<FilterFile, Fail> {
+        let path = path.as_ref();
+        let mut f = File::open(path).err_msg(format!("can't open file: {:?}", path))?;
+        let mut bytes = Vec::new();
+        f.read_to_end(&mut bytes)
+            .err_msg(format!("can't read file: {:?}", path))?;
+
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_slice(&bytes).err_msg("can't parse filters file as JSON")
+        } else {
+            toml::from_slice(&bytes).err_msg("can't parse filters file as TOML")
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String, Fail> {
+        toml::to_string(self).err_msg("can't encode filters as TOML")
+    }
+
+    pub fn to_json(&self) -> Result<String, Fail> {
+        serde_json::to_string_pretty(self).err_msg("can't encode filters as JSON")
+    }
+
+    /// Filters in the file. `outdated` is added if missing, because results must have an
+    /// outdated category.
+    pub fn into_filters(self) -> Result<Filters, Fail> {
+        let has_outdated = self.filters.contains(&Spec::Outdated);
+        let mut filters = Filters::new();
+        for spec in self.filters {
+            filters.add(spec.into_filter()?);
+        }
+        if !has_outdated {
+            filters.add(Filter::Outdated);
+        }
+        Ok(filters)
+    }
+}
+
+/// Serializable form of `Filter`. TOML can't represent tuple variants and non-string keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "filter", rename_all = "snake_case")]
+enum Spec {
+    Days {
+        category: Category,
+        days: i64,
+        #[serde(default)]
+        by_type: Vec<TypeDays>,
+    },
+    Dist {
+        max: f64,
+    },
+    DistToArrival {
+        max: f64,
+    },
+    /// `inner` is last, because TOML values can't follow tables.
+    DistanceScaled {
+        from: f64,
+        days_per_ly: f64,
+        inner: Box<Spec>,
+    },
+    Economy {
+        list: Vec<Economy>,
+        include_secondary: bool,
+    },
+    ExcludeCarriers,
+    ExcludeDamaged,
+    ExcludePowers {
+        names: Vec<String>,
+    },
+    ExcludeSecurity {
+        levels: Vec<String>,
+    },
+    ExcludeStations {
+        stations: BTreeMap<String, Vec<String>>,
+    },
+    ExcludeSettlements,
+    ExcludeSystems {
+        names: Vec<String>,
+    },
+    IgnorePlanetary,
+    LPadOnly,
+    MegaShipInformationOnly,
+    MPadOnly,
+    NeglectedOnly {
+        days: i64,
+    },
+    MaxDays {
+        days: i64,
+    },
+    MissingOutdated {
+        category: Category,
+    },
+    Outdated,
+    RevisitAfter {
+        days: i64,
+    },
+    ScoopableOnly,
+    SkipVisited,
+    StationName {
+        patterns: Vec<String>,
+    },
+    SystemName {
+        patterns: Vec<String>,
+    },
+    UpdatedBefore {
+        time: String,
+    },
+    WithinBubble {
+        radius: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Category {
+    Information,
+    Market,
+    Shipyard,
+    Outfitting,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TypeDays {
+    station_type: StationType,
+    days: i64,
+}
+
+impl Spec {
+    fn new(filter: &Filter) -> Spec {
+        match filter {
+            Filter::Days(days, by_type) => {
+                let (category, days) = match *days {
+                    Days::Information(d) => (Category::Information, d),
+                    Days::Market(d) => (Category::Market, d),
+                    Days::Shipyard(d) => (Category::Shipyard, d),
+                    Days::Outfitting(d) => (Category::Outfitting, d),
+                };
+                let mut by_type: Vec<TypeDays> = by_type
+                    .iter()
+                    .map(|(station_type, &days)| TypeDays {
+                        station_type: station_type.clone(),
+                        days,
+                    })
+                    .collect();
+                by_type.sort_by_key(|t| format!("{:?}", t.station_type));
+                Spec::Days {
+                    category,
+                    days,
+                    by_type,
+                }
+            }
+            Filter::Dist(max) => Spec::Dist { max: *max },
+            Filter::DistToArrival(max) => Spec::DistToArrival { max: *max },
+            Filter::DistanceScaled(inner, scale) => Spec::DistanceScaled {
+                from: scale.from,
+                days_per_ly: scale.days_per_ly,
+                inner: Box::new(Spec::new(inner)),
+            },
+            Filter::Economy(list, include_secondary) => {
+                let mut list: Vec<Economy> = list.iter().cloned().collect();
+                list.sort_by_key(|e| format!("{:?}", e));
+                Spec::Economy {
+                    list,
+                    include_secondary: *include_secondary,
+                }
+            }
+            Filter::ExcludeCarriers => Spec::ExcludeCarriers,
+            Filter::ExcludeDamaged => Spec::ExcludeDamaged,
+            Filter::ExcludeSettlements => Spec::ExcludeSettlements,
+            Filter::ExcludePowers(names) => Spec::ExcludePowers {
+                names: sorted(names),
+            },
+            Filter::ExcludeSecurity(levels) => Spec::ExcludeSecurity {
+                levels: sorted(levels),
+            },
+            Filter::ExcludeStations(stations) => Spec::ExcludeStations {
+                stations: stations
+                    .iter()
+                    .map(|(system, names)| (system.clone(), sorted(names)))
+                    .collect(),
+            },
+            Filter::ExcludeSystems(names) => Spec::ExcludeSystems {
+                names: sorted(names),
+            },
+            Filter::IgnorePlanetary => Spec::IgnorePlanetary,
+            Filter::LPadOnly => Spec::LPadOnly,
+            Filter::MegaShipInformationOnly => Spec::MegaShipInformationOnly,
+            Filter::MPadOnly => Spec::MPadOnly,
+            Filter::NeglectedOnly(days) => Spec::NeglectedOnly { days: *days },
+            Filter::MaxDays(days) => Spec::MaxDays { days: *days },
+            Filter::MissingOutdated(category) => Spec::MissingOutdated {
+                category: match category {
+                    searcher::Category::Information => Category::Information,
+                    searcher::Category::Market => Category::Market,
+                    searcher::Category::Shipyard => Category::Shipyard,
+                    searcher::Category::Outfitting => Category::Outfitting,
+                },
+            },
+            Filter::Outdated => Spec::Outdated,
+            Filter::RevisitAfter(days) => Spec::RevisitAfter { days: *days },
+            Filter::ScoopableOnly => Spec::ScoopableOnly,
+            Filter::SkipVisited => Spec::SkipVisited,
+            Filter::StationName(rs) => Spec::StationName {
+                patterns: rs.patterns().to_vec(),
+            },
+            Filter::SystemName(rs) => Spec::SystemName {
+                patterns: rs.patterns().to_vec(),
+            },
+            Filter::UpdatedBefore(time) => Spec::UpdatedBefore {
+                time: time.to_rfc3339(),
+            },
+            Filter::WithinBubble(radius) => Spec::WithinBubble { radius: *radius },
+        }
+    }
+
+    fn into_filter(self) -> Result<Filter, Fail> {
+        Ok(match self {
+            Spec::Days {
+                category,
+                days,
+                by_type,
+            } => {
+                let days = match category {
+                    Category::Information => Days::Information(days),
+                    Category::Market => Days::Market(days),
+                    Category::Shipyard => Days::Shipyard(days),
+                    Category::Outfitting => Days::Outfitting(days),
+                };
+                let by_type: HashMap<StationType, i64> = by_type
+                    .into_iter()
+                    .map(|t| (t.station_type, t.days))
+                    .collect();
+                Filter::Days(days, by_type)
+            }
+            Spec::Dist { max } => Filter::Dist(max),
+            Spec::DistToArrival { max } => Filter::DistToArrival(max),
+            Spec::DistanceScaled {
+                from,
+                days_per_ly,
+                inner,
+            } => Filter::DistanceScaled(
+                Box::new(inner.into_filter()?),
+                DistanceScale { from, days_per_ly },
+            ),
+            Spec::Economy {
+                list,
+                include_secondary,
+            } => Filter::Economy(list.into_iter().collect(), include_secondary),
+            Spec::ExcludeCarriers => Filter::ExcludeCarriers,
+            Spec::ExcludeDamaged => Filter::ExcludeDamaged,
+            Spec::ExcludeSettlements => Filter::ExcludeSettlements,
+            Spec::ExcludePowers { names } => Filter::ExcludePowers(names.into_iter().collect()),
+            Spec::ExcludeSecurity { levels } => {
+                Filter::ExcludeSecurity(levels.into_iter().collect())
+            }
+            Spec::ExcludeStations { stations } => Filter::ExcludeStations(
+                stations
+                    .into_iter()
+                    .map(|(system, names)| (system, names.into_iter().collect()))
+                    .collect(),
+            ),
+            Spec::ExcludeSystems { names } => {
+                Filter::ExcludeSystems(names.iter().map(|n| n.to_lowercase()).collect())
+            }
+            Spec::IgnorePlanetary => Filter::IgnorePlanetary,
+            Spec::LPadOnly => Filter::LPadOnly,
+            Spec::MegaShipInformationOnly => Filter::MegaShipInformationOnly,
+            Spec::MPadOnly => Filter::MPadOnly,
+            Spec::NeglectedOnly { days } => Filter::NeglectedOnly(days),
+            Spec::MaxDays { days } => Filter::MaxDays(days),
+            Spec::MissingOutdated { category } => Filter::MissingOutdated(match category {
+                Category::Information => searcher::Category::Information,
+                Category::Market => searcher::Category::Market,
+                Category::Shipyard => searcher::Category::Shipyard,
+                Category::Outfitting => searcher::Category::Outfitting,
+            }),
+            Spec::Outdated => Filter::Outdated,
+            Spec::RevisitAfter { days } => Filter::RevisitAfter(days),
+            Spec::ScoopableOnly => Filter::ScoopableOnly,
+            Spec::SkipVisited => Filter::SkipVisited,
+            Spec::StationName { patterns } => Filter::StationName(
+                RegexSet::new(&patterns).err_msg("failed parse 'station_name' patterns")?,
+            ),
+            Spec::SystemName { patterns } => Filter::SystemName(
+                RegexSet::new(&patterns).err_msg("failed parse 'system_name' patterns")?,
+            ),
+            Spec::UpdatedBefore { time } => Filter::UpdatedBefore(
+                DateTime::parse_from_rfc3339(&time)
+                    .err_msg("failed parse 'updated_before' time")?
+                    .with_timezone(&Utc),
+            ),
+            Spec::WithinBubble { radius } => Filter::WithinBubble(radius),
+        })
+    }
+}
+
+fn sorted(set: &HashSet<String>) -> Vec<String> {
+    let mut list: Vec<String> = set.iter().cloned().collect();
+    list.sort();
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::{remove_file, write};
+    use std::process;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// Every kind of filter, with sets and maps of more than one entry.
+    fn all_filters() -> Filters {
+        let set = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<HashSet<_>>();
+        let mut by_type = HashMap::new();
+        by_type.insert(StationType::Outpost, 60);
+        by_type.insert(StationType::CoriolisStarport, 21);
+        let mut stations = HashMap::new();
+        stations.insert("Sol".to_owned(), set(&["Abraham Lincoln", "Daedalus"]));
+
+        let list = vec![
+            Filter::MissingOutdated(searcher::Category::Shipyard),
+            Filter::MegaShipInformationOnly,
+            Filter::Days(Days::Market(30), by_type),
+            Filter::DistanceScaled(
+                Box::new(Filter::Days(Days::Information(10), HashMap::new())),
+                DistanceScale {
+                    from: 20.0,
+                    days_per_ly: 0.5,
+                },
+            ),
+            Filter::Dist(45.5),
+            Filter::DistToArrival(5000.0),
+            Filter::Economy(
+                [Economy::HighTech, Economy::Industrial]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                true,
+            ),
+            Filter::ExcludeCarriers,
+            Filter::ExcludeDamaged,
+            Filter::ExcludePowers(set(&["Zachary Hudson", "Aisling Duval"])),
+            Filter::ExcludeSecurity(set(&["Anarchy", "Low"])),
+            Filter::ExcludeStations(stations),
+            Filter::ExcludeSettlements,
+            Filter::ExcludeSystems(set(&["sol", "achenar"])),
+            Filter::IgnorePlanetary,
+            Filter::LPadOnly,
+            Filter::MPadOnly,
+            Filter::NeglectedOnly(14),
+            Filter::Outdated,
+            Filter::MaxDays(3650),
+            Filter::RevisitAfter(7),
+            Filter::ScoopableOnly,
+            Filter::SkipVisited,
+            Filter::StationName(RegexSet::new(["^Test", "Port$"]).unwrap()),
+            Filter::SystemName(RegexSet::new(["^Col "]).unwrap()),
+            Filter::UpdatedBefore(Utc.with_ymd_and_hms(2021, 5, 19, 0, 0, 0).unwrap()),
+            Filter::WithinBubble(500.0),
+        ];
+        let mut filters = Filters::new();
+        for f in list {
+            filters.add(f);
+        }
+        filters
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let file = FilterFile::new(&all_filters());
+        let parsed: FilterFile = toml::from_str(&file.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, file);
+        assert_eq!(FilterFile::new(&parsed.into_filters().unwrap()), file);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let file = FilterFile::new(&all_filters());
+        let parsed: FilterFile = serde_json::from_str(&file.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, file);
+        assert_eq!(FilterFile::new(&parsed.into_filters().unwrap()), file);
+    }
+
+    #[test]
+    fn load_by_extension() {
+        let file = FilterFile::new(&all_filters());
+        let base = temp_dir().join(format!("near-old-stations-filters-{}", process::id()));
+        for (ext, text) in &[("toml", file.to_toml()), ("json", file.to_json())] {
+            let path = base.with_extension(ext);
+            write(&path, text.as_ref().unwrap()).unwrap();
+            let loaded = FilterFile::load(&path);
+            remove_file(&path).unwrap();
+            assert_eq!(loaded.unwrap(), file);
+        }
+    }
+
+    #[test]
+    fn outdated_is_added_if_missing() {
+        let file: FilterFile = toml::from_str("[[filters]]\nfilter = \"skip_visited\"\n").unwrap();
+        let filters = FilterFile::new(&file.into_filters().unwrap());
+        assert_eq!(filters.filters, vec![Spec::SkipVisited, Spec::Outdated]);
+    }
+
+    #[test]
+    fn bad_patterns_are_errors() {
+        let file: FilterFile =
+            toml::from_str("[[filters]]\nfilter = \"station_name\"\npatterns = [\"(\"]\n").unwrap();
+        assert!(file.into_filters().is_err());
+    }
+}
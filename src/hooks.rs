@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use tiny_fail::Fail;
+
+use crate::activity::ActivityLog;
+use crate::alert::Alert;
+use crate::daemon::StatusFile;
+use crate::journal::Location;
+use crate::notification::Notifier;
+use crate::searcher::Record;
+use crate::stations::Station;
+
+/// Actions run after each search and dock.
+#[derive(Debug, Default, Clone)]
+pub struct Hooks {
+    pub activity: ActivityLog,
+    pub alert: Option<Alert>,
+    pub notifier: Option<Notifier>,
+    pub status: Option<StatusFile>,
+}
+
+impl Hooks {
+    pub fn searched(&self, location: &Location, records: &[Record]) -> Result<(), Fail> {
+        self.activity.search(location, records.first())?;
+        if let Some(ref alert) = self.alert {
+            alert.searched(location, records)?;
+        }
+        if let Some(ref notifier) = self.notifier {
+            notifier.searched(location, records);
+        }
+        if let Some(ref status) = self.status {
+            status.write(location, records)?;
+        }
+        Ok(())
+    }
+
+    pub fn docked(
+        &self,
+        market_id: u64,
+        time: DateTime<Utc>,
+        station: Option<&Station>,
+    ) -> Result<(), Fail> {
+        self.activity.dock(market_id, time, station)
+    }
+}
@@ -0,0 +1,234 @@
+use std::env::var;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::Deserialize;
+
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Lang {
+    #[serde(rename = "en")]
+    En,
+    #[serde(rename = "ja")]
+    Ja,
+}
+
+impl Lang {
+    /// Detect language from locale environment variables.
+    pub fn detect() -> Lang {
+        for key in &["LC_ALL", "LC_MESSAGES", "LANG"] {
+            if let Ok(v) = var(key) {
+                if v.is_empty() {
+                    continue;
+                }
+                if v.starts_with("ja") {
+                    return Lang::Ja;
+                }
+                return Lang::En;
+            }
+        }
+        Lang::En
+    }
+}
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Ja,
+        _ => Lang::En,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    Antispinward,
+    Changed,
+    Changes,
+    Cluster,
+    CompareHeader,
+    Coreward,
+    Coverage,
+    DumpDate,
+    Error,
+    Exported,
+    Extracted,
+    Footer,
+    Freshness,
+    FreshnessModified,
+    FreshnessUnavailable,
+    FreshnessUpToDate,
+    Header,
+    Here,
+    Ignored,
+    Imported,
+    InvalidCoords,
+    JournalNotFound,
+    MissingCoords,
+    MissingStations,
+    MostNeglected,
+    Nearest,
+    Neglected,
+    NewCandidate,
+    NextRun,
+    NotificationFailed,
+    OldDump,
+    Paused,
+    Pinned,
+    PlanDay,
+    ReloadFailed,
+    Rimward,
+    Roll,
+    SelectHelp,
+    ServerUnavailable,
+    Serving,
+    SinceLastRun,
+    SkippedRecords,
+    Spinward,
+    Star,
+    StarUnscoopable,
+    StateRecovered,
+    Submitted,
+    TourTotal,
+    UnknownStationTypes,
+    Unpinned,
+    Visited,
+    Warning,
+    Warnings,
+}
+
+/// Message template in current language. `{}` is placeholder.
+pub fn tr(msg: Msg) -> &'static str {
+    match lang() {
+        Lang::En => match msg {
+            Msg::Antispinward => "antispinward",
+            Msg::CompareHeader => "Origin\tStations\tMedian days\tNearest Ly",
+            Msg::Changed => "economy/type changed",
+            Msg::Changes => "{} changes of economy or type recorded.",
+            Msg::Cluster => "#{}: {} stations within {} Ly around {} ({} Ly away)",
+            Msg::Coreward => "coreward",
+            Msg::Coverage => "{}/{} stations visited in system",
+            Msg::DumpDate => "from {} dump",
+            Msg::Error => "Error: {}",
+            Msg::Exported => "Exported {} stations to {}.",
+            Msg::Extracted => "Extracted {} stations to {}.",
+            Msg::Footer => "Shown {} of {} stations ({} hidden by max_entries). Nearest {} Ly, farthest {} Ly. Median {}d outdated.",
+            Msg::Freshness => "Data freshness:",
+            Msg::FreshnessModified => "update available",
+            Msg::FreshnessUnavailable => "unavailable ({})",
+            Msg::FreshnessUpToDate => "up to date",
+            Msg::Header => "Total {} stations. Last update is {}.",
+            Msg::Here => "{} stations in {}.",
+            Msg::Ignored => "Ignored {}. Remove it from marks.json to show again.",
+            Msg::Imported => "Imported {} visits from {}, {} of them new or newer.",
+            Msg::InvalidCoords => "{} stations are ignored because their systems have invalid coordinates.",
+            Msg::JournalNotFound => "Journal files not found, searching from {}.",
+            Msg::MissingCoords => "{} stations are ignored because their systems are not in the systems dump.",
+            Msg::MissingStations => "{} docked stations are missing from the dump.",
+            Msg::MostNeglected => "Most neglected: {} stations ~{} Ly {}.",
+            Msg::Nearest => "{} nearest stations around {}.",
+            Msg::Neglected => "neglected (median {}d)",
+            Msg::NewCandidate => "New candidate: {} in {}, {} Ly away, {}d outdated.",
+            Msg::NextRun => "Next run at {}.",
+            Msg::NotificationFailed => "Failed to show desktop notification: {}",
+            Msg::OldDump => "Stations dump is {}d old.",
+            Msg::Paused => "Journal is not updated for {} minutes. Paused until the game is running.",
+            Msg::Pinned => "Pinned {} to top.",
+            Msg::PlanDay => "Day {}: {} stations, {} Ly, about {} h -> {}",
+            Msg::ReloadFailed => "Failed to reload dumps, showing previous data: {}",
+            Msg::Rimward => "rimward",
+            Msg::Roll => "Today's target: {} in {} ({} Ly, {}d outdated).",
+            Msg::SelectHelp => "[Up/Down] move  [Space] select  [p] pin  [i] ignore  [e] export  [r] refresh  [q] quit",
+            Msg::ServerUnavailable => "EDSM unreachable ({}), using data from {}d ago.",
+            Msg::Serving => "Serving results at {} (Ctrl-C to stop).",
+            Msg::SinceLastRun => "Since your last run, {} stations were updated by the community, {} of them within {} Ly.",
+            Msg::SkippedRecords => "Skipped {} bad records in stations dump.",
+            Msg::Spinward => "spinward",
+            Msg::Star => "star {}",
+            Msg::StarUnscoopable => "star {} (unscoopable)",
+            Msg::StateRecovered => "{} was broken, restored from backup.",
+            Msg::Submitted => "Submitted {} cells to {}.",
+            Msg::TourTotal => "{} stations, {} Ly in total.",
+            Msg::UnknownStationTypes => "Unknown station types in stations dump: {}.",
+            Msg::Unpinned => "Unpinned {}.",
+            Msg::Visited => "visited {}d ago",
+            Msg::Warning => "Warning: {}",
+            Msg::Warnings => "Warnings:",
+        },
+        Lang::Ja => match msg {
+            Msg::Antispinward => "反回転方向",
+            Msg::CompareHeader => "起点\t件数\t古さの中央値\t最寄り Ly",
+            Msg::Changed => "経済・種類が変化",
+            Msg::Changes => "経済・種類の変化を{}件記録しています。",
+            Msg::Cluster => "#{}: {}件、半径{} Ly（中心 {}、{} Ly先）",
+            Msg::Coreward => "中心方向",
+            Msg::Coverage => "星系内 {}/{} 訪問済み",
+            Msg::DumpDate => "{}のダンプ",
+            Msg::Error => "エラー: {}",
+            Msg::Exported => "{}件のステーションを{}に書き出しました。",
+            Msg::Extracted => "{}件のステーションを{}に抽出しました。",
+            Msg::Footer => "{}件を表示（全{}件、max_entriesにより{}件省略）。最寄り {} Ly、最遠 {} Ly。古さの中央値 {}日。",
+            Msg::Freshness => "データの鮮度:",
+            Msg::FreshnessModified => "更新あり",
+            Msg::FreshnessUnavailable => "確認できません（{}）",
+            Msg::FreshnessUpToDate => "最新",
+            Msg::Header => "全{}件のステーション。最終更新 {}。",
+            Msg::Here => "{}件のステーション（{}）。",
+            Msg::Ignored => "{}を無視リストに追加しました。marks.json から削除すると再表示されます。",
+            Msg::Imported => "{}件の訪問記録を{}から読み込みました（うち{}件が新規または更新）。",
+            Msg::InvalidCoords => "{}件のステーションは星系の座標が不正なため除外しました。",
+            Msg::JournalNotFound => "ジャーナルファイルが見つからないため、{}から検索します。",
+            Msg::MissingCoords => "{}件のステーションは星系がダンプデータに無いため除外しました。",
+            Msg::MissingStations => "入港したステーションのうち{}件がダンプデータにありません。",
+            Msg::MostNeglected => "最も放置されている方向: {}件、約{} Ly {}。",
+            Msg::Nearest => "最寄りのステーション{}件（{}周辺）。",
+            Msg::Neglected => "放置気味（更新間隔の中央値 {}日）",
+            Msg::NewCandidate => "新しい候補: {}（{}）、{} Ly先、{}日前の情報。",
+            Msg::NextRun => "次回の実行は{}です。",
+            Msg::NotificationFailed => "デスクトップ通知を表示できませんでした: {}",
+            Msg::OldDump => "ステーションのダンプデータが{}日前のものです。",
+            Msg::Paused => "ジャーナルが{}分間更新されていないため、ゲームが起動するまで一時停止します。",
+            Msg::Pinned => "{}を先頭に固定しました。",
+            Msg::PlanDay => "{}日目: {}件、{} Ly、約{}時間 -> {}",
+            Msg::ReloadFailed => "ダンプデータを再読み込みできなかったため、前回のデータを表示しています: {}",
+            Msg::Rimward => "外縁方向",
+            Msg::Roll => "今日の目標: {}（{}、{} Ly、{}日更新なし）",
+            Msg::SelectHelp => "[↑/↓] 移動  [Space] 選択  [p] 固定  [i] 無視  [e] 書き出し  [r] 再検索  [q] 終了",
+            Msg::ServerUnavailable => "EDSMに接続できません（{}）。{}日前のデータを使用します。",
+            Msg::Serving => "{} で結果を配信しています（Ctrl-C で終了）。",
+            Msg::SinceLastRun => "前回の実行以降、{}件のステーションが更新されました（うち{}件が{} Ly以内）。",
+            Msg::SkippedRecords => "ステーションのダンプデータの不正な{}行を読み飛ばしました。",
+            Msg::Spinward => "回転方向",
+            Msg::Star => "主星 {}",
+            Msg::StarUnscoopable => "主星 {}（補給不可）",
+            Msg::StateRecovered => "{}が壊れていたため、バックアップから復元しました。",
+            Msg::Submitted => "{}区画の集計を{}に送信しました。",
+            Msg::TourTotal => "{}件、合計 {} Ly。",
+            Msg::UnknownStationTypes => "ステーションのダンプデータに未知の種類があります: {}。",
+            Msg::Unpinned => "{}の固定を解除しました。",
+            Msg::Visited => "{}日前に訪問",
+            Msg::Warning => "警告: {}",
+            Msg::Warnings => "警告:",
+        },
+    }
+}
+
+/// Format message in current language, replacing each `{}` with `args` in order.
+pub fn trf(msg: Msg, args: &[&dyn Display]) -> String {
+    let mut s = String::new();
+    let mut args = args.iter();
+    let mut parts = tr(msg).split("{}");
+    if let Some(first) = parts.next() {
+        s.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            s.push_str(&arg.to_string());
+        }
+        s.push_str(part);
+    }
+    s
+}
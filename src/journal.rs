@@ -1,19 +1,32 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::env::var;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs::{metadata, File};
+use std::io::{self, BufRead, BufReader, ErrorKind};
 use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::process::Command;
+use std::time::{Duration, SystemTime};
 
+use chrono::{DateTime, Utc};
 use regex::Regex;
 use serde::Deserialize;
-use serde_json::from_str;
-use tiny_fail::Fail;
+use serde_json::{from_reader, from_str};
+use tiny_fail::{ErrorMessageExt, Fail};
 
 use crate::coords::Coords;
+use crate::diagnostics::{warn, Warning};
+use crate::paths::state_file;
+use crate::visited::Visited;
 
 const VISITED_VIEW_FILES: usize = 50;
+pub const VISITED_FILE: &str = "visited.json";
 
-pub type GetLocFunc = fn() -> Result<(Location, Visited), Fail>;
+pub type GetLocFunc = Box<dyn Fn() -> Result<(Location, Visited), Fail>>;
+
+/// Fixed location with empty visited state, to preview results from elsewhere.
+pub fn simulated(location: Location) -> GetLocFunc {
+    Box::new(move || Ok((location.clone(), Visited::new())))
+}
 
 pub fn sol_origin() -> Result<(Location, Visited), Fail> {
     let (_, visited) = load_current_location()?;
@@ -21,12 +34,43 @@ pub fn sol_origin() -> Result<(Location, Visited), Fail> {
     Ok((sol(), visited))
 }
 
+/// Fixed location at `coords` with visits in journal, for origins not in systems dumps.
+pub fn coords_origin(coords: Coords) -> GetLocFunc {
+    let location = Location {
+        star_system: format!("{},{},{}", coords.x(), coords.y(), coords.z()),
+        star_pos: coords,
+    };
+    Box::new(move || {
+        let (_, visited) = load_current_location()?;
+        Ok((location.clone(), visited))
+    })
+}
+
 pub fn load_current_location() -> Result<(Location, Visited), Fail> {
-    if let Some(journal_files) = journal_files()? {
-        load_location_from_file(journal_files)
+    load_current_location_or(|| Ok(sol()))
+}
+
+/// Like `load_current_location`, but location is given by `fallback` if journal is not found.
+pub fn load_current_location_or(
+    fallback: impl Fn() -> Result<Location, Fail>,
+) -> Result<(Location, Visited), Fail> {
+    let (location, journal_visited) = if let Some(journal_files) = journal_files()? {
+        load_location_from_file(journal_files)?
     } else {
-        Ok((sol(), Visited::new()))
+        let location = fallback()?;
+        warn(Warning::JournalNotFound(location.star_system.clone()));
+        (location, Visited::new())
+    };
+
+    // Keep visits in state file, because old journal files are not read or may be deleted.
+    let path = state_file(VISITED_FILE);
+    let mut visited = Visited::load(&path)?;
+    if !journal_visited.newer_than(&visited).is_empty() {
+        visited.merge(&journal_visited);
+        visited.save(&path)?;
     }
+
+    Ok((location, visited))
 }
 
 fn sol() -> Location {
@@ -36,35 +80,53 @@ fn sol() -> Location {
     }
 }
 
-fn load_location_from_file(mut journal_files: Vec<PathBuf>) -> Result<(Location, Visited), Fail> {
-    let mut buf = String::new();
-
+/// Last location and visits in journal text, such as a journal file given in browser.
+///
+/// Later events take precedence, so journal files can be concatenated in order of time.
+pub fn parse_journal<R: BufRead>(r: R) -> Result<(Option<Location>, Visited), Fail> {
     let mut location = Option::<Location>::None;
     let mut visited = Visited::new();
+    read_events(r, |event| match event {
+        Event::Location(loc) | Event::FSDJump(loc) => location = Some(loc),
+        Event::Docked(docked) => visited.add(docked.market_id, docked.timestamp),
+        _ => {}
+    })?;
+    Ok((location, visited))
+}
 
-    while let Some(file_path) = journal_files.pop() {
-        let f = File::open(&file_path)?;
-        let mut r = BufReader::new(f);
-
-        loop {
-            r.read_line(&mut buf)?;
-            if buf.is_empty() {
-                break;
-            }
+/// Visits in journal entries exported from EDDiscovery, as one JSON event per line or a JSON array.
+///
+/// EDDiscovery keeps whole history in its database, so this fills visits whose journal files are gone.
+pub fn load_exported_visits<P: AsRef<Path>>(path: P) -> Result<Visited, Fail> {
+    let path = path.as_ref();
+    let f = File::open(path).err_msg(format!("can't open EDDiscovery export: {:?}", path))?;
+    let mut r = BufReader::new(f);
 
-            let event: Event = from_str(&buf).map_err(|e| Fail::new(format!("{}: {}", e, buf)))?;
-            buf.truncate(0);
-            match event {
-                Event::Location(loc) => location = Some(loc),
-                Event::FSDJump(loc) => location = Some(loc),
-                Event::Docked(docked) => {
-                    visited.add(docked.market_id);
-                }
-                _ => {}
-            }
+    let mut visited = Visited::new();
+    let add = |event| {
+        if let Event::Docked(docked) = event {
+            visited.add(docked.market_id, docked.timestamp);
         }
+    };
+    if r.fill_buf()?.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[') {
+        let events: Vec<Event> =
+            from_reader(r).map_err(|e| Fail::new(format!("{}: {:?}", e, path)))?;
+        events.into_iter().for_each(add);
+    } else {
+        read_events(r, add)?;
+    }
+    Ok(visited)
+}
+
+fn load_location_from_file(mut journal_files: Vec<PathBuf>) -> Result<(Location, Visited), Fail> {
+    let mut location = Option::<Location>::None;
+    let mut visited = Visited::new();
 
-        if location.is_some() {
+    while let Some(file_path) = journal_files.pop() {
+        let (loc, v) = parse_journal(BufReader::new(File::open(&file_path)?))?;
+        visited.merge(&v);
+        if loc.is_some() {
+            location = loc;
             break;
         }
     }
@@ -76,21 +138,11 @@ fn load_location_from_file(mut journal_files: Vec<PathBuf>) -> Result<(Location,
         }
         cnt -= 1;
 
-        let f = File::open(&file_path)?;
-        let mut r = BufReader::new(f);
-
-        loop {
-            r.read_line(&mut buf)?;
-            if buf.is_empty() {
-                break;
-            }
-
-            let event: Event = from_str(&buf).map_err(|e| Fail::new(format!("{}: {}", e, buf)))?;
-            buf.truncate(0);
+        read_events(BufReader::new(File::open(&file_path)?), |event| {
             if let Event::Docked(docked) = event {
-                visited.add(docked.market_id);
+                visited.add(docked.market_id, docked.timestamp);
             }
-        }
+        })?;
     }
 
     if let Some(loc) = location {
@@ -101,13 +153,11 @@ fn load_location_from_file(mut journal_files: Vec<PathBuf>) -> Result<(Location,
 }
 
 fn journal_files() -> Result<Option<Vec<PathBuf>>, Fail> {
-    if let Some(journal_dir) = journal_dir() {
-        if !journal_dir.exists() {
-            return Ok(None);
-        }
+    if let Some(journal_dir) = journal_dir()? {
         let journal_regex = Regex::new(r"^Journal\.\d{12}\.\d{2}\.log$")?;
         let journal_files = journal_dir
-            .read_dir()?
+            .read_dir()
+            .map_err(|e| access_error(&journal_dir, e))?
             .filter_map(|f| f.ok())
             .map(|f| f.path())
             .filter(|p| {
@@ -123,22 +173,102 @@ fn journal_files() -> Result<Option<Vec<PathBuf>>, Fail> {
     }
 }
 
-fn journal_dir() -> Option<PathBuf> {
-    if let Ok(home) = var("USERPROFILE") {
-        let journal_dir = Path::new(&home)
-            .join("Saved Games")
-            .join("Frontier Developments")
-            .join("Elite Dangerous");
-        if !journal_dir.exists() {
-            return None;
+/// First existing journal directory. Inaccessible directory is an error, rather than silently using Sol.
+fn journal_dir() -> Result<Option<PathBuf>, Fail> {
+    for dir in journal_dirs() {
+        match metadata(&dir) {
+            Ok(m) if m.is_dir() => return Ok(Some(dir)),
+            Ok(_) => {}
+            Err(ref e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(access_error(&dir, e)),
         }
-        if !journal_dir.is_dir() {
-            return None;
-        }
-        Some(journal_dir)
+    }
+    Ok(None)
+}
+
+/// Candidates of journal directory, in priority order.
+///
+/// Saved Games folder may be moved, or redirected by OneDrive, so the known folder location is tried first.
+fn journal_dirs() -> Vec<PathBuf> {
+    let mut saved_games = Vec::new();
+    if let Some(dir) = saved_games_dir() {
+        saved_games.push(dir);
+    }
+    if let Ok(home) = var("USERPROFILE") {
+        saved_games.push(Path::new(&home).join("Saved Games"));
+        saved_games.push(Path::new(&home).join("OneDrive").join("Saved Games"));
+    }
+
+    saved_games
+        .into_iter()
+        .map(|d| d.join("Frontier Developments").join("Elite Dangerous"))
+        .collect()
+}
+
+fn access_error(dir: &Path, e: io::Error) -> Fail {
+    if e.kind() == ErrorKind::PermissionDenied {
+        Fail::new(format!(
+            "permission denied to journal directory {:?}; allow access to the folder (e.g. Controlled folder access of Windows Security), or use --simulate-system",
+            dir
+        ))
     } else {
-        None
+        Fail::from(e).msg(format!("can't read journal directory {:?}", dir))
+    }
+}
+
+/// Saved Games known folder from registry, which reflects redirection.
+#[cfg(windows)]
+fn saved_games_dir() -> Option<PathBuf> {
+    const SHELL_FOLDERS: &str =
+        r"HKCU\Software\Microsoft\Windows\CurrentVersion\Explorer\User Shell Folders";
+    const SAVED_GAMES: &str = "{4C5C32FF-BB9D-43b0-B5B4-2D72E54EAAA4}";
+
+    let output = Command::new("reg")
+        .args(&["query", SHELL_FOLDERS, "/v", SAVED_GAMES])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Line is like `    {GUID}    REG_EXPAND_SZ    %USERPROFILE%\Saved Games`.
+    let text = String::from_utf8_lossy(&output.stdout);
+    let line = text.lines().find(|l| l.contains(SAVED_GAMES))?;
+    let value = line.splitn(2, "REG_").nth(1)?;
+    let value = value.splitn(2, char::is_whitespace).nth(1)?.trim();
+    Some(PathBuf::from(expand_env(value)))
+}
+
+#[cfg(not(windows))]
+fn saved_games_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Expand `%NAME%` environment variables.
+#[cfg(windows)]
+fn expand_env(s: &str) -> String {
+    let mut result = String::new();
+    let mut parts = s.split('%');
+    if let Some(first) = parts.next() {
+        result.push_str(first);
+    }
+    while let Some(name) = parts.next() {
+        match (var(name), parts.next()) {
+            (Ok(value), Some(rest)) => {
+                result.push_str(&value);
+                result.push_str(rest);
+            }
+            (_, rest) => {
+                result.push('%');
+                result.push_str(name);
+                if let Some(rest) = rest {
+                    result.push('%');
+                    result.push_str(rest);
+                }
+            }
+        }
     }
+    result
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -158,30 +288,95 @@ pub struct Location {
     pub star_pos: Coords,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Visited {
-    visited: HashSet<u64>,
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Docked {
+    #[serde(rename = "MarketID")]
+    pub market_id: u64,
+    #[serde(default)]
+    pub star_system: String,
+    #[serde(default)]
+    pub station_name: String,
+    #[serde(default)]
+    pub station_type: String,
+    #[serde(rename = "timestamp", with = "timestamp_format")]
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Whether the latest journal file is modified within `period`, as a sign that the game is running.
+///
+/// Returns `true` if journal files are not found, because it can't be detected.
+pub fn game_running(period: Duration) -> Result<bool, Fail> {
+    let latest = match journal_files()? {
+        // Names contain start time of each session.
+        Some(files) => files
+            .into_iter()
+            .max_by(|l, r| l.file_name().cmp(&r.file_name())),
+        None => None,
+    };
+    let latest = match latest {
+        Some(latest) => latest,
+        None => return Ok(true),
+    };
+
+    let modified = metadata(&latest)?.modified()?;
+    Ok(SystemTime::now()
+        .duration_since(modified)
+        .map_or(true, |elapsed| elapsed < period))
 }
 
-impl Visited {
-    fn new() -> Visited {
-        Visited {
-            visited: HashSet::new(),
-        }
-    }
+/// Last dock at each station in all journal files, in order of time.
+pub fn load_docks() -> Result<Vec<Docked>, Fail> {
+    let journal_files = match journal_files()? {
+        Some(files) => files,
+        None => return Ok(Vec::new()),
+    };
 
-    fn add(&mut self, id: u64) {
-        self.visited.insert(id);
+    let mut docks = HashMap::<u64, Docked>::new();
+    for file_path in journal_files {
+        read_events(BufReader::new(File::open(&file_path)?), |event| {
+            if let Event::Docked(docked) = event {
+                let last = docks
+                    .entry(docked.market_id)
+                    .or_insert_with(|| docked.clone());
+                if last.timestamp < docked.timestamp {
+                    *last = docked;
+                }
+            }
+        })?;
     }
 
-    pub fn is_visited(&self, id: u64) -> bool {
-        self.visited.contains(&id)
+    let mut docks: Vec<Docked> = docks.into_values().collect();
+    docks.sort_by_key(|d| d.timestamp);
+    Ok(docks)
+}
+
+/// Call `f` with each event in journal lines.
+fn read_events<R: BufRead>(mut r: R, mut f: impl FnMut(Event)) -> Result<(), Fail> {
+    let mut buf = String::new();
+    loop {
+        buf.truncate(0);
+        if r.read_line(&mut buf)? == 0 {
+            return Ok(());
+        }
+        if buf.trim().is_empty() {
+            continue;
+        }
+
+        let event: Event = from_str(&buf).map_err(|e| Fail::new(format!("{}: {}", e, buf)))?;
+        f(event);
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-struct Docked {
-    #[serde(rename = "MarketID")]
-    market_id: u64,
-    timestamp: String,
+mod timestamp_format {
+    use chrono::{DateTime, Utc};
+    use serde::{self, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
 }
@@ -1,8 +1,45 @@
+pub mod activity;
+pub mod alert;
+pub mod analysis;
+pub mod batch;
+pub mod changes;
+pub mod cluster;
+#[cfg(feature = "native")]
 pub mod config;
 pub mod coords;
+pub mod daemon;
+pub mod diagnostics;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod filter;
+pub mod hooks;
+pub mod i18n;
 pub mod journal;
+pub mod lock;
+pub mod marks;
+#[cfg(feature = "native")]
 pub mod mode;
+pub mod notification;
+pub mod patches;
+pub mod paths;
 pub mod printer;
+pub mod refresh;
+pub mod result_cache;
+#[cfg(feature = "native")]
+pub mod schedule;
+#[cfg(feature = "schemars")]
+pub mod schema;
 pub mod searcher;
+pub mod selection;
+pub mod spatial;
+pub mod state;
 pub mod stations;
+#[cfg(feature = "native")]
+pub mod submit;
+pub mod tour;
+pub mod travel;
+pub mod units;
+pub mod visited;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,129 @@
+use std::fs::{read_to_string, remove_file, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use tiny_fail::{ErrorMessageExt, Fail};
+
+/// Lock file containing PID, removed on drop.
+#[derive(Debug)]
+pub struct LockFile {
+    path: PathBuf,
+}
+
+impl LockFile {
+    pub fn acquire<P: AsRef<Path>>(path: P) -> Result<LockFile, Fail> {
+        let path = path.as_ref().to_owned();
+
+        let create = || OpenOptions::new().write(true).create_new(true).open(&path);
+        let mut f = match create() {
+            Ok(f) => f,
+            // Lock left by crashed instance.
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists && is_stale(&path) => {
+                remove_file(&path).err_msg(format!("can't remove stale lock file {:?}", path))?;
+                create().err_msg(format!("can't create lock file {:?}", path))?
+            }
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {
+                return Err(Fail::new(format!(
+                    "another instance is running (remove {:?} if not)",
+                    path
+                )));
+            }
+            Err(e) => return Err(Fail::from(e).msg(format!("can't create lock file {:?}", path))),
+        };
+        writeln!(f, "{}", process::id()).err_msg("can't write lock file")?;
+
+        Ok(LockFile { path })
+    }
+
+    /// Like `acquire`, but waits up to `timeout` for the other instance to release the lock.
+    pub fn acquire_wait<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<LockFile, Fail> {
+        let start = Instant::now();
+        loop {
+            match LockFile::acquire(&path) {
+                Ok(lock) => return Ok(lock),
+                Err(e) if start.elapsed() >= timeout => return Err(e),
+                Err(_) => sleep(LOCK_RETRY_PERIOD),
+            }
+        }
+    }
+}
+
+/// Whether the instance holding lock file at `path` has exited.
+///
+/// Lock files without readable PID are not stale, because the owner may be writing it.
+fn is_stale(path: &Path) -> bool {
+    match read_to_string(path).map(|s| s.trim().parse::<u32>()) {
+        Ok(Ok(pid)) => pid != process::id() && !is_running(pid),
+        _ => false,
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // Signal 0 only checks existence of the process.
+    let res = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    res == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a way to check, owners are assumed to be running.
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+const LOCK_RETRY_PERIOD: Duration = Duration::from_secs(1);
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::write;
+
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        temp_dir().join(format!("near-old-stations-{}-{}.lock", name, process::id()))
+    }
+
+    #[test]
+    fn held_lock_is_not_acquired() {
+        let path = lock_path("held");
+        let lock = LockFile::acquire(&path).unwrap();
+        assert!(LockFile::acquire(&path).is_err());
+        drop(lock);
+        assert!(!path.exists());
+        LockFile::acquire(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stale_lock_is_replaced() {
+        let mut child = process::Command::new("true").spawn().unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+
+        let path = lock_path("stale");
+        write(&path, format!("{}\n", dead)).unwrap();
+        let _lock = LockFile::acquire(&path).unwrap();
+        assert_eq!(
+            read_to_string(&path).unwrap().trim(),
+            process::id().to_string()
+        );
+    }
+
+    #[test]
+    fn lock_without_pid_is_not_stale() {
+        let path = lock_path("empty");
+        write(&path, "").unwrap();
+        assert!(LockFile::acquire(&path).is_err());
+        remove_file(&path).unwrap();
+    }
+}
@@ -1,26 +1,712 @@
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{stdout, Write};
+use std::path::Path;
+
+use chrono::{Local, Utc};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::thread_rng;
 use tiny_fail::{ErrorMessageExt, Fail};
 
-use near_old_stations::config::Config;
-use near_old_stations::printer::TextPrinter;
-use near_old_stations::stations::load_stations;
+use near_old_stations::analysis::DirectionSummary;
+use near_old_stations::batch::{read_origins, write_csv, write_json, BatchResult};
+use near_old_stations::changes::Change;
+use near_old_stations::cluster::find_clusters;
+use near_old_stations::config::{Command, Config, Format};
+use near_old_stations::coords::Coords;
+use near_old_stations::daemon::{install_reload_handler, take_reload};
+use near_old_stations::diagnostics::{clear_warnings, warnings};
+use near_old_stations::export::export_coords;
+use near_old_stations::filter::{FilterFile, Filters};
+use near_old_stations::i18n::{set_lang, tr, trf, Msg};
+use near_old_stations::journal::{load_docks, Docked, VISITED_FILE};
+use near_old_stations::marks::{Marks, MARKS_FILE};
+use near_old_stations::paths::{cache_file, lock_cache, state_file};
+use near_old_stations::printer::{CsvPrinter, JsonPrinter, Printer, Summary};
+use near_old_stations::result_cache::{cache_key, ResultCache};
+use near_old_stations::searcher::{roll_weights, Record, Searcher};
+use near_old_stations::stations::download::Freshness;
+use near_old_stations::stations::{
+    check_dumps, dump_version, extract, load_changes, load_stations, updated_since_last_run,
+    LoadOptions, Stations,
+};
+use near_old_stations::submit::Submission;
+use near_old_stations::tour::{export_route, plan_tour, split_days, tour_length};
+use near_old_stations::visited::Visited;
 
 fn main() {
     if let Err(e) = w_main() {
-        eprintln!("Error: {}", e);
+        eprintln!("{}", trf(Msg::Error, &[&e]));
         std::process::exit(1);
     }
 }
 
+const RESULT_CACHE_FILE: &str = "result_cache.json";
+
 fn w_main() -> Result<(), Fail> {
-    let cfg = Config::load().err_msg("failed load config")?;
+    let mut cfg = Config::load().err_msg("failed load config")?;
+    set_lang(cfg.lang());
+
+    // Other commands don't show warnings with results.
+    let printed = match cfg.command() {
+        Command::Search => !cfg.is_here() && cfg.format() == Format::Text,
+        Command::Batch { format, .. } => *format == Format::Text,
+        Command::CompareOrigins { .. } => true,
+        Command::Clusters { expand, .. } => expand.is_some(),
+        _ => false,
+    };
+
+    let result = match cfg.command() {
+        Command::Search if cfg.is_here() => here(&cfg),
+        Command::Search if cfg.format() == Format::Csv => search_records(&cfg),
+        Command::Search if cfg.is_daemon() => {
+            install_reload_handler();
+            loop {
+                search(&cfg)?;
+                if !take_reload() {
+                    return Ok(());
+                }
+                cfg = Config::load().err_msg("failed reload config")?;
+                set_lang(cfg.lang());
+                clear_warnings();
+            }
+        }
+        Command::Search if cfg.use_result_cache() => search_cached(&cfg),
+        Command::Search => search(&cfg),
+        Command::Changes { days } => changes(&cfg, *days),
+        Command::Clusters {
+            radius,
+            min_size,
+            expand,
+        } => clusters(&cfg, *radius, *min_size, *expand),
+        Command::Batch { source, format } => batch(&cfg, source, *format),
+        Command::CompareOrigins { origins } => compare_origins(&cfg, origins),
+        Command::Directions { band } => directions(&cfg, *band),
+        Command::DumpFilters => dump_filters(&cfg),
+        Command::ExportCoords {
+            cluster_radius,
+            min_size,
+            output,
+        } => export(&cfg, *cluster_radius, *min_size, output),
+        Command::Missing { output } => missing(&cfg, output.as_ref()),
+        Command::Nearest { count } => nearest(&cfg, *count),
+        Command::Plan { days, output } => plan(&cfg, *days, output),
+        Command::Roll => roll(&cfg),
+        Command::SubmitCoverage { dry_run } => submit_coverage(&cfg, *dry_run),
+        Command::Tour {
+            cluster,
+            radius,
+            min_size,
+            output,
+        } => tour(&cfg, *cluster, *radius, *min_size, output.as_ref()),
+        Command::VisitedExport { output } => visited_export(output),
+        Command::VisitedImport { input, merge } => visited_import(input, *merge),
+        Command::Extract {
+            around,
+            radius,
+            output,
+        } => {
+            let count = extract(&cfg.load_options(), around, *radius, Path::new(output))
+                .err_msg("failed to extract stations")?;
+            println!("{}", trf(Msg::Extracted, &[&count, output]));
+            Ok(())
+        }
+    };
+
+    if !printed {
+        for w in warnings() {
+            eprintln!("{}", trf(Msg::Warning, &[&w]));
+        }
+    }
+    result
+}
+
+fn dump_filters(cfg: &Config) -> Result<(), Fail> {
+    let file = FilterFile::new(&cfg.filter_chain()?);
+    if cfg.format() == Format::Json {
+        println!("{}", file.to_json()?);
+    } else {
+        print!("{}", file.to_toml()?);
+    }
+    Ok(())
+}
+
+fn load_searcher(cfg: &Config) -> Result<Searcher<Filters>, Fail> {
+    let stations = load_stations(&cfg.load_options()).err_msg("failed load stations dump file")?;
+    let mut searcher = Searcher::new(stations, cfg.filter()?);
+    searcher.set_days_transform(cfg.days_transform());
+    searcher.set_travel_cost(cfg.travel_cost());
+    searcher.set_economy_boost(cfg.economy_boost()?);
+    Ok(searcher)
+}
+
+fn clusters(cfg: &Config, radius: f64, min_size: usize, expand: Option<usize>) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let last_mod = searcher
+        .stations()
+        .last_mod()
+        .err_msg("No stations update date info.")?
+        .with_timezone(&Utc);
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let records = searcher.search(&location, &visited);
+    let clusters = find_clusters(&records, radius, min_size);
+
+    if let Some(n) = expand {
+        let cluster = clusters
+            .get(n.wrapping_sub(1))
+            .err_msg(format!("no cluster #{}", n))?;
+        let members: Vec<Record> = records
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| cluster.members.binary_search(i).is_ok())
+            .map(|(_, r)| r)
+            .collect();
+        cfg.printer().print(&members, cfg.max_entries(), last_mod)?;
+        return Ok(());
+    }
 
-    let get_loc_func = cfg.get_loc_func();
-    let stations = load_stations().err_msg("failed load stations dump file")?;
+    for (i, cluster) in clusters.iter().enumerate().take(cfg.max_entries()) {
+        let center = &records[cluster.center];
+        println!(
+            "{}",
+            trf(
+                Msg::Cluster,
+                &[
+                    &(i + 1),
+                    &cluster.len(),
+                    &format!("{:.1}", cluster.radius),
+                    &center.station.system_name,
+                    &format!("{:.1}", center.distance),
+                ]
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Search once and write only records to stdout as CSV, so that output can be piped to other tools.
+///
+/// Header and warnings are written to stderr.
+fn search_records(cfg: &Config) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let last_mod = searcher
+        .stations()
+        .last_mod()
+        .err_msg("No stations update date info.")?
+        .with_timezone(&Utc);
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let mut records = searcher.search(&location, &visited);
+    Marks::load(state_file(MARKS_FILE))?.arrange(&mut records);
+    cfg.hooks().searched(&location, &records)?;
+
+    eprintln!("{}", cfg.printer().header(&records, last_mod));
+    let result = BatchResult::new(&location.star_system, &records, cfg.max_entries());
+    write_csv(stdout(), &[result])
+}
+
+fn batch(cfg: &Config, source: &str, format: Format) -> Result<(), Fail> {
+    let origins = read_origins(source)?;
+    let searcher = load_searcher(cfg)?;
+    let last_mod = searcher
+        .stations()
+        .last_mod()
+        .err_msg("No stations update date info.")?
+        .with_timezone(&Utc);
+    let (_, visited) = cfg.get_loc_func()?()?;
+    let printer = cfg.printer();
+
+    let mut results = Vec::with_capacity(origins.len());
+    for origin in &origins {
+        let coords = searcher.stations().systems().locate(origin)?;
+        results.push(searcher.search_at(coords, &visited));
+    }
+
+    if format != Format::Text {
+        let results: Vec<BatchResult> = origins
+            .iter()
+            .zip(&results)
+            .map(|(origin, records)| BatchResult::new(origin, records, cfg.max_entries()))
+            .collect();
+        if format == Format::Json {
+            write_json(stdout(), &results)?;
+            println!();
+        } else {
+            write_csv(stdout(), &results)?;
+        }
+        return Ok(());
+    }
+
+    for (origin, records) in origins.iter().zip(&results) {
+        println!("=== {} ===", origin);
+        print!("{}", printer.render(records, cfg.max_entries(), last_mod));
+        println!();
+    }
+    Ok(())
+}
+
+fn compare_origins(cfg: &Config, origins: &[String]) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let last_mod = searcher
+        .stations()
+        .last_mod()
+        .err_msg("No stations update date info.")?
+        .with_timezone(&Utc);
+    let (_, visited) = cfg.get_loc_func()?()?;
+    let printer = cfg.printer();
+
+    let mut summaries = Vec::with_capacity(origins.len());
+    for origin in origins {
+        let coords = searcher.stations().systems().locate(origin)?;
+        let records = searcher.search_at(coords, &visited);
+
+        println!("=== {} ===", origin);
+        print!("{}", printer.render(&records, cfg.max_entries(), last_mod));
+        println!();
+        summaries.push(Summary::new(&records, cfg.max_entries()));
+    }
+
+    println!("{}", tr(Msg::CompareHeader));
+    for (origin, summary) in origins.iter().zip(&summaries) {
+        println!(
+            "{}\t{}\t{}\t{}",
+            origin,
+            summary.total,
+            summary
+                .median_days
+                .map_or("-".to_owned(), |d| d.to_string()),
+            summary
+                .nearest
+                .map_or("-".to_owned(), |d| format!("{:.2}", d)),
+        );
+    }
+    Ok(())
+}
+
+fn directions(cfg: &Config, band: f64) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let records = searcher.search(&location, &visited);
+
+    print!(
+        "{}",
+        DirectionSummary::new(&records, location.star_pos, band)
+    );
+    Ok(())
+}
+
+fn export(
+    cfg: &Config,
+    cluster_radius: Option<f64>,
+    min_size: usize,
+    output: &str,
+) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let mut records = searcher.search(&location, &visited);
+    records.truncate(cfg.max_entries());
+
+    let clusters = match cluster_radius {
+        Some(radius) => find_clusters(&records, radius, min_size),
+        None => Vec::new(),
+    };
+    export_coords(output, &location.star_system, &records, &clusters)?;
+    println!("{}", trf(Msg::Exported, &[&records.len(), &output]));
+    Ok(())
+}
+
+fn here(cfg: &Config) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let records = searcher.search_system(&location, &visited);
+
+    print!(
+        "{}",
+        cfg.printer().render_system(&location.star_system, &records)
+    );
+    Ok(())
+}
+
+fn nearest(cfg: &Config, count: usize) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let records = searcher.nearest(location.star_pos, &visited, count);
+
+    print!(
+        "{}",
+        cfg.printer()
+            .render_nearest(&location.star_system, &records)
+    );
+    Ok(())
+}
+
+fn changes(cfg: &Config, days: Option<i64>) -> Result<(), Fail> {
+    // Load to compare the current dump with the previous one.
+    let mut opts = cfg.load_options();
+    opts.track_changes = true;
+    load_stations(&opts).err_msg("failed load stations dump file")?;
+
+    let now = Utc::now();
+    let changes: Vec<Change> = load_changes()?
+        .into_iter()
+        .filter(|c| days.is_none_or(|d| now.signed_duration_since(c.detected()).num_days() < d))
+        .collect();
+
+    println!("Detected\tSystem\tStation\tBefore\tAfter");
+    for c in &changes {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            c.detected().with_timezone(&Local).format("%F"),
+            c.system,
+            c.station,
+            c.before,
+            c.after
+        );
+    }
+    println!("{}", trf(Msg::Changes, &[&changes.len()]));
+    Ok(())
+}
+
+fn missing(cfg: &Config, output: Option<&String>) -> Result<(), Fail> {
+    let stations = load_stations(&cfg.load_options()).err_msg("failed load stations dump file")?;
+    // Fleet carriers move around, so they are not expected in the dump.
+    let docks: Vec<Docked> = load_docks()?
+        .into_iter()
+        .filter(|d| d.station_type != "FleetCarrier" && !stations.has_market_id(d.market_id))
+        .collect();
+
+    let mut report = String::from("System\tStation\tMarketID\tLastDocked\n");
+    for d in &docks {
+        report.push_str(&format!(
+            "{}\t{}\t{}\t{}\n",
+            d.star_system,
+            d.station_name,
+            d.market_id,
+            d.timestamp.to_rfc3339()
+        ));
+    }
+
+    match output {
+        Some(path) => {
+            let mut f = File::create(path).err_msg(format!("can't create file: {:?}", path))?;
+            f.write_all(report.as_bytes())?;
+            println!("{}", trf(Msg::Exported, &[&docks.len(), path]));
+        }
+        None => {
+            print!("{}", report);
+            println!("{}", trf(Msg::MissingStations, &[&docks.len()]));
+        }
+    }
+    Ok(())
+}
+
+fn plan(cfg: &Config, days: usize, output: &str) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let records = searcher.search(&location, &visited);
+    let targets: Vec<&Record> = records.iter().take(cfg.max_entries()).collect();
+
+    let points: Vec<Coords> = targets.iter().map(|r| r.station.coords).collect();
+    let travel = cfg.travel_cost();
+    let order = plan_tour(location.star_pos, &points, &*travel);
+    let route: Vec<&Record> = order.iter().map(|&i| targets[i]).collect();
+
+    let mut pos = location.star_pos;
+    let mut dists = Vec::with_capacity(route.len());
+    let mut legs = Vec::with_capacity(route.len());
+    for r in &route {
+        let dist = pos.dist_to(r.station.coords);
+        dists.push(dist);
+        legs.push(travel.cost(dist, r.station.distance_to_arrival.unwrap_or(0.0)));
+        pos = r.station.coords;
+    }
+
+    for (day, range) in split_days(&legs, days).into_iter().enumerate() {
+        let file_name = format!("{}{}.txt", output, day + 1);
+        let dist: f64 = dists[range.clone()].iter().sum();
+        let hours = legs[range.clone()].iter().sum::<f64>() / 3600.0;
+        export_route(&file_name, &route[range.clone()])?;
+        println!(
+            "{}",
+            trf(
+                Msg::PlanDay,
+                &[
+                    &(day + 1),
+                    &range.len(),
+                    &format!("{:.2}", dist),
+                    &format!("{:.1}", hours),
+                    &file_name
+                ]
+            )
+        );
+    }
+    Ok(())
+}
+
+fn roll(cfg: &Config) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let records = searcher.search(&location, &visited);
+
+    let dist = WeightedIndex::new(roll_weights(&records)).err_msg("no outdated station to pick")?;
+    let pick = &records[dist.sample(&mut thread_rng())];
+    println!(
+        "{}",
+        trf(
+            Msg::Roll,
+            &[
+                &pick.station.name,
+                &pick.station.system_name,
+                &format!("{:.2}", pick.distance),
+                &pick.outdated().map_or(0, |o| o.days),
+            ]
+        )
+    );
+    cfg.hooks().activity.roll(&location, pick)
+}
+
+fn search(cfg: &Config) -> Result<(), Fail> {
+    let get_loc_func = cfg.get_loc_func()?;
+    let load_opts = cfg.load_options();
     let filter = cfg.filter()?;
-    let printer = TextPrinter::new();
-    let mode = cfg.mode();
+    let transform = cfg.days_transform();
+    let travel = cfg.travel_cost();
+    let boost = cfg.economy_boost()?;
+    let first_load = Cell::new(true);
+    let load = || {
+        let first = first_load.replace(false);
+        if first && cfg.concurrent_checks() {
+            report_freshness(&load_opts)?;
+        }
+        let stations = load_stations(&load_opts).err_msg("failed load stations dump file")?;
+        if first && cfg.since_last_run() {
+            report_since_last_run(cfg, &stations)?;
+        }
+        let mut searcher = Searcher::new(stations, filter.clone());
+        searcher.set_days_transform(transform);
+        searcher.set_travel_cost(travel.clone());
+        searcher.set_economy_boost(boost.clone());
+        Ok(searcher)
+    };
+    let mode = cfg.mode()?;
+
+    let hooks = cfg.hooks();
+
+    if let Some(path) = cfg.csv_output() {
+        mode.run(
+            load,
+            get_loc_func,
+            CsvPrinter::new(path.clone()),
+            cfg.max_entries(),
+            false,
+            &hooks,
+        )?;
+    } else if cfg.format() == Format::Json {
+        mode.run(
+            load,
+            get_loc_func,
+            JsonPrinter::new(),
+            cfg.max_entries(),
+            false,
+            &hooks,
+        )?;
+    } else {
+        mode.run(
+            load,
+            get_loc_func,
+            cfg.printer(),
+            cfg.max_entries(),
+            cfg.fit_height(),
+            &hooks,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Print update states of all dumps, checked concurrently before loading.
+///
+/// Written to stderr like download progress, so that JSON and CSV output isn't broken.
+fn report_freshness(opts: &LoadOptions) -> Result<(), Fail> {
+    let dumps = check_dumps(opts)?;
+    if dumps.is_empty() {
+        return Ok(());
+    }
+    eprintln!("{}", tr(Msg::Freshness));
+    for (file_name, freshness) in &dumps {
+        let (state, last_mod) = match freshness {
+            Freshness::UpToDate(t) => (tr(Msg::FreshnessUpToDate).to_owned(), *t),
+            Freshness::Modified(t) => (tr(Msg::FreshnessModified).to_owned(), *t),
+            Freshness::Unavailable(e) => (trf(Msg::FreshnessUnavailable, &[e]), None),
+        };
+        let last_mod = last_mod.map_or_else(
+            || "-".to_owned(),
+            |t| t.with_timezone(&Local).format("%F %R").to_string(),
+        );
+        eprintln!("  {:<32} {:<16} {}", file_name, last_mod, state);
+    }
+    Ok(())
+}
+
+/// Print how many stations are updated since the previous run, in total and within `max_dist`.
+fn report_since_last_run(cfg: &Config, stations: &Stations) -> Result<(), Fail> {
+    let updated = match updated_since_last_run(stations)? {
+        Some(updated) => updated,
+        None => return Ok(()),
+    };
+    let (location, _) = cfg.get_loc_func()?()?;
+    let radius = cfg.max_dist();
+    let nearby = updated
+        .iter()
+        .filter(|st| location.star_pos.dist_to(st.coords) <= radius)
+        .count();
+    println!(
+        "{}",
+        trf(Msg::SinceLastRun, &[&updated.len(), &nearby, &radius])
+    );
+    Ok(())
+}
+
+/// Oneshot search, reusing the last records if origin, visited stations, dumps and config are unchanged.
+fn search_cached(cfg: &Config) -> Result<(), Fail> {
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let version = dump_version(&cfg.load_options())?;
+    let marks = Marks::load(state_file(MARKS_FILE))?;
+    let key = cache_key(&location, &visited, &marks, &version, cfg.fingerprint())?;
+    let cache = ResultCache::new(cache_file(RESULT_CACHE_FILE));
+    if let Some(cached) = cache.get(&key) {
+        let records = cached.records();
+        cfg.hooks().searched(&location, &records)?;
+        let output = cfg
+            .printer()
+            .render(&records, cfg.max_entries(), cached.last_mod());
+        print!("{}", output);
+        return Ok(());
+    }
+
+    let searcher = load_searcher(cfg)?;
+    let last_mod = searcher
+        .stations()
+        .last_mod()
+        .err_msg("No stations update date info.")?
+        .with_timezone(&Utc);
+    let mut records = searcher.search(&location, &visited);
+    marks.arrange(&mut records);
+    cfg.hooks().searched(&location, &records)?;
+
+    let output = cfg.printer().render(&records, cfg.max_entries(), last_mod);
+    print!("{}", output);
+    let _lock = lock_cache()?;
+    cache.save(&key, &records, last_mod)
+}
+
+fn submit_coverage(cfg: &Config, dry_run: bool) -> Result<(), Fail> {
+    let submit = cfg.submit_config();
+    if !dry_run && !submit.enabled {
+        return Err(Fail::new(
+            "submission is disabled (set 'submit.enabled = true' to opt in)",
+        ));
+    }
+
+    let searcher = load_searcher(cfg)?;
+    let last_mod = searcher
+        .stations()
+        .last_mod()
+        .err_msg("No stations update date info.")?
+        .with_timezone(&Utc);
+    // Fixed region around Sol, so that neither position nor visits of the commander are revealed.
+    let records = searcher.search_at(Coords::zero(), &Visited::new());
+    let submission = Submission::new(&records, submit.cell_size, last_mod);
+
+    if dry_run {
+        println!("{}", submission.to_json()?);
+        return Ok(());
+    }
+
+    let url = submit
+        .url
+        .as_ref()
+        .err_msg("'submit.url' is not configured")?;
+    submission.send(url)?;
+    println!("{}", trf(Msg::Submitted, &[&submission.cells(), url]));
+    Ok(())
+}
+
+fn tour(
+    cfg: &Config,
+    cluster: Option<usize>,
+    radius: f64,
+    min_size: usize,
+    output: Option<&String>,
+) -> Result<(), Fail> {
+    let searcher = load_searcher(cfg)?;
+    let (location, visited) = cfg.get_loc_func()?()?;
+    let records = searcher.search(&location, &visited);
+
+    let targets: Vec<&Record> = match cluster {
+        Some(n) => {
+            let clusters = find_clusters(&records, radius, min_size);
+            let cluster = clusters
+                .get(n.wrapping_sub(1))
+                .err_msg(format!("no cluster #{}", n))?;
+            cluster.members.iter().map(|&i| &records[i]).collect()
+        }
+        None => records.iter().take(cfg.max_entries()).collect(),
+    };
+
+    let points: Vec<Coords> = targets.iter().map(|r| r.station.coords).collect();
+    let order = plan_tour(location.star_pos, &points, &*cfg.travel_cost());
+    let route: Vec<&Record> = order.iter().map(|&i| targets[i]).collect();
+
+    let mut pos = location.star_pos;
+    for (i, r) in route.iter().enumerate() {
+        println!(
+            "{:>3} {:>7.2} Ly  {:<25} {}",
+            i + 1,
+            pos.dist_to(r.station.coords),
+            r.station.name,
+            r.station.system_name
+        );
+        pos = r.station.coords;
+    }
+    let total = tour_length(location.star_pos, &points, &order);
+    println!(
+        "{}",
+        trf(Msg::TourTotal, &[&route.len(), &format!("{:.2}", total)])
+    );
+
+    if let Some(output) = output {
+        export_route(output, &route)?;
+        println!("{}", trf(Msg::Exported, &[&route.len(), output]));
+    }
+    Ok(())
+}
+
+fn visited_export(output: &str) -> Result<(), Fail> {
+    let visited = Visited::load(state_file(VISITED_FILE))?;
+    visited.export(output)?;
+    println!("{}", trf(Msg::Exported, &[&visited.len(), &output]));
+    Ok(())
+}
+
+/// Import visits from file. Without `merge`, current visits are replaced.
+fn visited_import(input: &str, merge: bool) -> Result<(), Fail> {
+    if !Path::new(input).exists() {
+        return Err(Fail::new(format!("file not found: {}", input)));
+    }
+    let imported = Visited::load(input)?;
 
-    mode.run(stations, get_loc_func, filter, printer, cfg.max_entries())?;
+    let path = state_file(VISITED_FILE);
+    let current = Visited::load(&path)?;
+    let (count, newer) = (imported.len(), imported.newer_than(&current).len());
+    let visited = if merge {
+        let mut visited = current;
+        visited.merge(&imported);
+        visited
+    } else {
+        imported
+    };
+    visited.save(&path)?;
 
+    println!("{}", trf(Msg::Imported, &[&count, &input, &newer]));
     Ok(())
 }
@@ -0,0 +1,90 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tiny_fail::Fail;
+
+use crate::searcher::Record;
+use crate::selection::Key;
+use crate::state::StateFile;
+use crate::stations::Station;
+
+pub const MARKS_FILE: &str = "marks.json";
+/// Format version of marks file.
+const VERSION: u32 = 1;
+
+fn state_file(path: &Path) -> StateFile {
+    StateFile::new(path, "marks", VERSION).pretty()
+}
+
+/// Stations pinned to top or ignored in interactive mode.
+#[derive(Debug, Clone, Serialize)]
+pub struct Marks {
+    #[serde(skip)]
+    path: PathBuf,
+    lists: Lists,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct Lists {
+    #[serde(default)]
+    pinned: BTreeSet<Key>,
+    #[serde(default)]
+    ignored: BTreeSet<Key>,
+}
+
+impl Marks {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Marks, Fail> {
+        let path = path.as_ref().to_owned();
+        let lists = state_file(&path).load()?.unwrap_or_default();
+        Ok(Marks { path, lists })
+    }
+
+    pub fn save(&self) -> Result<(), Fail> {
+        state_file(&self.path).save(&self.lists)
+    }
+
+    pub fn is_pinned(&self, station: &Station) -> bool {
+        self.lists.pinned.contains(&Key::new(station))
+    }
+
+    /// Returns `true` if pinned, `false` if unpinned.
+    pub fn toggle_pin(&mut self, station: &Station) -> bool {
+        let key = Key::new(station);
+        if self.lists.pinned.remove(&key) {
+            false
+        } else {
+            self.lists.pinned.insert(key);
+            true
+        }
+    }
+
+    pub fn is_ignored(&self, station: &Station) -> bool {
+        self.lists.ignored.contains(&Key::new(station))
+    }
+
+    /// Ignore station permanently. It is also unpinned.
+    pub fn ignore(&mut self, station: &Station) {
+        let key = Key::new(station);
+        self.lists.pinned.remove(&key);
+        self.lists.ignored.insert(key);
+    }
+
+    /// Ignored stations as `(system, station)` pairs.
+    pub fn ignored(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lists
+            .ignored
+            .iter()
+            .map(|k| (k.system.as_str(), k.station.as_str()))
+    }
+
+    /// Remove ignored stations and move pinned stations to top, each ordered by score as search result.
+    pub fn arrange(&self, records: &mut Vec<Record>) {
+        records.retain(|r| !self.is_ignored(r.station));
+        records.sort_by(|l, r| {
+            self.is_pinned(r.station)
+                .cmp(&self.is_pinned(l.station))
+                .then_with(|| l.cmp(r).reverse())
+        });
+    }
+}
@@ -1,74 +1,402 @@
+mod serve;
+mod tasks;
+
+use std::sync::Arc;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
-use chrono::Utc;
+use chrono::{DateTime, Local, Utc};
+use console::{Key, Term};
 use tiny_fail::{ErrorMessageExt, Fail};
 
-use crate::journal::GetLocFunc;
+use crate::coords::Coords;
+use crate::daemon::reload_requested;
+use crate::diagnostics::{clear_warnings, warn, warnings, Warning};
+use crate::hooks::Hooks;
+use crate::i18n::{tr, trf, Msg};
+use crate::journal::{game_running, GetLocFunc, Location};
+use crate::lock::LockFile;
+use crate::marks::{Marks, MARKS_FILE};
+use crate::paths::state_file;
 use crate::printer::Printer;
+use crate::schedule::{Report, Schedule};
 use crate::searcher::{Filter, Searcher};
+use crate::selection::Selection;
 use crate::stations::Stations;
+use crate::tour::{cumulative_lengths, plan_tour};
+use crate::travel::TravelCost;
+use crate::visited::Visited;
+
+pub use serve::ServeOptions;
+use tasks::Tasks;
 
 const UPDATE_POOL_PERIOD: Duration = Duration::from_secs(5);
 const FORCE_UPDATE_PERIOD: Duration = Duration::from_secs(60);
+const SELECTION_FILE: &str = "selection.json";
+const SELECTION_EXPORT_FILE: &str = "selection.txt";
+const SCHEDULE_CHECK_PERIOD: Duration = Duration::from_secs(5);
+const SCHEDULE_LOCK_FILE: &str = "scheduled.lock";
+/// Lines of header, footer and the last line where cursor stays.
+const FRAME_LINES: usize = 3;
 
 pub enum Mode {
     Oneshot,
-    Update,
+    Update(UpdateOptions),
+    Select,
+    Poll(Duration),
+    Scheduled(Schedule, Report),
+    /// Top entries ordered into a visiting route.
+    Route(Arc<dyn TravelCost + Send + Sync>),
+    /// HTTP server of results, for other devices and overlays.
+    Serve(ServeOptions),
+}
+
+/// Intervals of activities in update mode, other than polling journal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateOptions {
+    /// Pause while journal is not updated for this duration.
+    pub pause_after: Option<Duration>,
+    /// Reload stations dumps, downloading if outdated, at this interval.
+    pub reload_dumps: Option<Duration>,
+}
+
+/// Shared by tasks of update mode.
+struct UpdateState<F> {
+    searcher: Searcher<F>,
+    last_mod: DateTime<Utc>,
+    location: Location,
+    visited: Visited,
+    limit: usize,
+    last_update: Instant,
+    paused: bool,
+    /// Location, visits or stations changed since last display.
+    changed: bool,
 }
 
 impl Mode {
-    pub fn run(
+    pub fn run<F: Filter>(
         &self,
-        stations: Stations,
+        load: impl Fn() -> Result<Searcher<F>, Fail>,
         get_loc_func: GetLocFunc,
-        filter: impl Filter,
         mut printer: impl Printer,
         max_entries: usize,
+        fit_height: bool,
+        hooks: &Hooks,
     ) -> Result<(), Fail> {
-        let last_mod = stations
-            .last_mod()
-            .err_msg("No stations update date info.")?
-            .with_timezone(&Utc);
+        if let Mode::Scheduled(..) = self {
+            return self.run_scheduled(load, get_loc_func, &printer, max_entries, hooks);
+        }
 
-        let searcher = Searcher::new(stations, filter);
+        let searcher = load()?;
+        let last_mod = dump_last_mod(searcher.stations())?;
+        let mut marks = Marks::load(state_file(MARKS_FILE))?;
 
         match self {
             Mode::Oneshot => {
                 let (location, visited) = get_loc_func()?;
-                let records = searcher.search(&location, &visited);
+                let mut records = searcher.search(&location, &visited);
+                marks.arrange(&mut records);
+                hooks.searched(&location, &records)?;
                 printer.print(&records, max_entries, last_mod)?;
                 Ok(())
             }
-            Mode::Update => {
+            Mode::Route(travel) => {
                 let (location, visited) = get_loc_func()?;
-                let records = searcher.search(&location, &visited);
-                printer.print(&records, max_entries, last_mod)?;
+                let mut records = searcher.search(&location, &visited);
+                marks.arrange(&mut records);
+                hooks.searched(&location, &records)?;
+                records.truncate(max_entries);
 
-                let mut prev_location = location;
-                let mut prev_visited = visited;
-                let mut last_update = Instant::now();
+                let points: Vec<Coords> = records.iter().map(|r| r.station.coords).collect();
+                let order = plan_tour(location.star_pos, &points, &**travel);
+                let cumulative = cumulative_lengths(location.star_pos, &points, &order);
+                let mut records: Vec<Option<_>> = records.into_iter().map(Some).collect();
+                let route: Vec<_> = order.iter().filter_map(|&i| records[i].take()).collect();
+                printer.print_route(&route, &cumulative, last_mod)
+            }
+            Mode::Update(opts) => {
+                let term = Term::stdout();
+                let entries = || {
+                    if fit_height {
+                        fit_entries(&term, max_entries, 0)
+                    } else {
+                        max_entries
+                    }
+                };
 
-                loop {
-                    sleep(UPDATE_POOL_PERIOD);
+                let (location, visited) = get_loc_func()?;
+                let mut records = searcher.search(&location, &visited);
+                marks.arrange(&mut records);
+                hooks.searched(&location, &records)?;
+                let limit = entries();
+                printer.print(&records, limit, last_mod)?;
+
+                let mut state = UpdateState {
+                    searcher,
+                    last_mod,
+                    location,
+                    visited,
+                    limit,
+                    last_update: Instant::now(),
+                    paused: false,
+                    changed: false,
+                };
+
+                let mut tasks: Tasks<UpdateState<F>> = Tasks::new();
+                tasks.add("journal", UPDATE_POOL_PERIOD, |s| {
+                    if let Some(period) = opts.pause_after {
+                        if !game_running(period)? {
+                            if !s.paused {
+                                println!("{}", trf(Msg::Paused, &[&(period.as_secs() / 60)]));
+                                s.paused = true;
+                            }
+                            return Ok(());
+                        }
+                    }
+                    if s.paused {
+                        s.paused = false;
+                        s.changed = true;
+                    }
 
                     let (location, visited) = get_loc_func()?;
-                    if location == prev_location
-                        && visited == prev_visited
-                        && last_update.elapsed() < FORCE_UPDATE_PERIOD
+                    for (market_id, time) in visited.newer_than(&s.visited) {
+                        let station = s.searcher.stations().by_market_id(market_id);
+                        hooks.docked(market_id, time, station)?;
+                    }
+                    if location != s.location || visited != s.visited {
+                        s.location = location;
+                        s.visited = visited;
+                        s.changed = true;
+                    }
+                    Ok(())
+                });
+                if let Some(interval) = opts.reload_dumps {
+                    tasks.add("dumps", interval, |s| {
+                        // Server or disk may recover until next time, so keep showing current data.
+                        let previous = warnings();
+                        clear_warnings();
+                        let loaded = load().and_then(|searcher| {
+                            let last_mod = dump_last_mod(searcher.stations())?;
+                            Ok((searcher, last_mod))
+                        });
+                        match loaded {
+                            Ok((searcher, last_mod)) => {
+                                s.searcher = searcher;
+                                s.last_mod = last_mod;
+                            }
+                            Err(e) => {
+                                clear_warnings();
+                                previous.into_iter().for_each(warn);
+                                warn(Warning::ReloadFailed(e.to_string()));
+                            }
+                        }
+                        s.changed = true;
+                        Ok(())
+                    });
+                }
+                tasks.add("display", UPDATE_POOL_PERIOD, |s| {
+                    if s.paused {
+                        return Ok(());
+                    }
+                    // Terminal may be resized.
+                    let limit = entries();
+                    if !s.changed
+                        && limit == s.limit
+                        && s.last_update.elapsed() < FORCE_UPDATE_PERIOD
                     {
-                        continue;
+                        return Ok(());
                     }
 
-                    let records = searcher.search(&location, &visited);
+                    let mut records = s.searcher.search(&s.location, &s.visited);
+                    marks.arrange(&mut records);
+                    hooks.searched(&s.location, &records)?;
                     printer.clear()?;
-                    printer.print(&records, max_entries, last_mod)?;
+                    printer.print(&records, limit, s.last_mod)?;
+
+                    s.limit = limit;
+                    s.changed = false;
+                    s.last_update = Instant::now();
+                    Ok(())
+                });
+                tasks.run(&mut state, reload_requested)
+            }
+            Mode::Serve(opts) => serve::serve(
+                opts,
+                &searcher,
+                &get_loc_func,
+                &marks,
+                max_entries,
+                last_mod,
+                hooks,
+            ),
+            Mode::Poll(interval) => loop {
+                let (location, visited) = get_loc_func()?;
+                let mut records = searcher.search(&location, &visited);
+                marks.arrange(&mut records);
+                hooks.searched(&location, &records)?;
+
+                println!("=== {} ===", Local::now().format("%F %T"));
+                printer.print(&records, max_entries, last_mod)?;
+                println!();
+
+                sleep(*interval);
+                if reload_requested() {
+                    return Ok(());
+                }
+            },
+            Mode::Select => {
+                let term = Term::stdout();
+                let mut selection = Selection::load(state_file(SELECTION_FILE))?;
+
+                let (location, visited) = get_loc_func()?;
+                let mut records = searcher.search(&location, &visited);
+                marks.arrange(&mut records);
+                hooks.searched(&location, &records)?;
+                let mut cursor = 0usize;
+                let mut message = String::new();
+
+                loop {
+                    // Help and message lines follow entries.
+                    let limit = if fit_height {
+                        fit_entries(&term, max_entries, 2)
+                    } else {
+                        max_entries
+                    };
+                    cursor = cursor.min(limit.saturating_sub(1));
+
+                    term.clear_screen()?;
+                    printer.print_selection(&records, limit, last_mod, cursor, &selection)?;
+                    println!("{}", tr(Msg::SelectHelp));
+                    if !message.is_empty() {
+                        println!("{}", message);
+                        message.clear();
+                    }
+
+                    match term.read_key()? {
+                        Key::ArrowUp | Key::Char('k') => cursor = cursor.saturating_sub(1),
+                        Key::ArrowDown | Key::Char('j')
+                            if cursor + 1 < records.len().min(limit) =>
+                        {
+                            cursor += 1;
+                        }
+                        Key::Char(' ') => {
+                            if let Some(r) = records.get(cursor) {
+                                selection.toggle(r.station);
+                                selection.save()?;
+                            }
+                        }
+                        Key::Char('p') => {
+                            if let Some(r) = records.get(cursor) {
+                                let name = r.station.name.clone();
+                                let msg = if marks.toggle_pin(r.station) {
+                                    Msg::Pinned
+                                } else {
+                                    Msg::Unpinned
+                                };
+                                marks.save()?;
+                                marks.arrange(&mut records);
+                                message = trf(msg, &[&name]);
+                            }
+                        }
+                        Key::Char('i') => {
+                            if let Some(r) = records.get(cursor) {
+                                let name = r.station.name.clone();
+                                marks.ignore(r.station);
+                                marks.save()?;
+                                marks.arrange(&mut records);
+                                cursor = cursor.min(records.len().saturating_sub(1));
+                                message = trf(Msg::Ignored, &[&name]);
+                            }
+                        }
+                        Key::Char('e') => {
+                            selection.export(SELECTION_EXPORT_FILE, &records)?;
+                            message =
+                                trf(Msg::Exported, &[&selection.len(), &SELECTION_EXPORT_FILE]);
+                        }
+                        Key::Char('r') => {
+                            let (location, visited) = get_loc_func()?;
+                            records = searcher.search(&location, &visited);
+                            marks.arrange(&mut records);
+                            hooks.searched(&location, &records)?;
+                            cursor = cursor.min(records.len().min(limit).saturating_sub(1));
+                        }
+                        Key::Char('q') | Key::Escape => return Ok(()),
+                        _ => {}
+                    }
+                }
+            }
+            Mode::Scheduled(..) => unreachable!("scheduled mode loads stations by itself"),
+        }
+    }
+
+    /// Reload stations and emit report at each scheduled time.
+    ///
+    /// Returns when reload is requested.
+    fn run_scheduled<F: Filter>(
+        &self,
+        load: impl Fn() -> Result<Searcher<F>, Fail>,
+        get_loc_func: GetLocFunc,
+        printer: &impl Printer,
+        max_entries: usize,
+        hooks: &Hooks,
+    ) -> Result<(), Fail> {
+        let (schedule, report) = match self {
+            Mode::Scheduled(schedule, report) => (schedule, report),
+            _ => unreachable!("run_scheduled is called in scheduled mode"),
+        };
+        let _lock = LockFile::acquire(state_file(SCHEDULE_LOCK_FILE))?;
 
-                    prev_location = location;
-                    prev_visited = visited;
-                    last_update = Instant::now();
+        loop {
+            let next = schedule
+                .next_after(Local::now())
+                .err_msg("schedule never matches")?;
+            println!("{}", trf(Msg::NextRun, &[&next.format("%F %T")]));
+
+            // Sleep in short steps, so that missed run after sleep/wake of the machine starts soon,
+            // and reload requests are handled while waiting.
+            while Local::now() < next {
+                if reload_requested() {
+                    return Ok(());
                 }
+                let remaining = next
+                    .signed_duration_since(Local::now())
+                    .to_std()
+                    .unwrap_or_else(|_| Duration::from_secs(0));
+                sleep(remaining.min(SCHEDULE_CHECK_PERIOD));
+            }
+
+            clear_warnings();
+            let result = load().and_then(|searcher| {
+                let last_mod = dump_last_mod(searcher.stations())?;
+                let (location, visited) = get_loc_func()?;
+                let records = searcher.search(&location, &visited);
+                hooks.searched(&location, &records)?;
+                report.emit(&printer.render(&records, max_entries, last_mod))
+            });
+            if let Err(e) = result {
+                eprintln!("{}", trf(Msg::Error, &[&e]));
             }
         }
     }
 }
+
+/// Entries fitting in terminal height with `extra_lines` and warnings, up to `max_entries`.
+/// `max_entries` if terminal height is unknown.
+fn fit_entries(term: &Term, max_entries: usize, extra_lines: usize) -> usize {
+    let warnings = warnings().len();
+    let warning_lines = if warnings == 0 { 0 } else { warnings + 1 };
+    match term.size_checked() {
+        Some((rows, _)) => (rows as usize)
+            .saturating_sub(FRAME_LINES + extra_lines + warning_lines)
+            .max(1)
+            .min(max_entries),
+        None => max_entries,
+    }
+}
+
+fn dump_last_mod(stations: &Stations) -> Result<DateTime<Utc>, Fail> {
+    Ok(stations
+        .last_mod()
+        .err_msg("No stations update date info.")?
+        .with_timezone(&Utc))
+}
@@ -0,0 +1,267 @@
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::daemon::reload_requested;
+use crate::hooks::Hooks;
+use crate::i18n::{trf, Msg};
+use crate::journal::{GetLocFunc, Location};
+use crate::marks::Marks;
+use crate::printer::{HtmlPrinter, JsonPrinter, Printer};
+use crate::searcher::{Filter, Searcher};
+
+/// Interval to check reload requests while no request comes.
+const ACCEPT_POLL_PERIOD: Duration = Duration::from_millis(200);
+/// Slow clients must not block others for long.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Settings of HTTP server mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServeOptions {
+    pub listen: SocketAddr,
+    /// Seconds to reload the HTML page in browsers.
+    pub refresh: u64,
+    /// Value of `Access-Control-Allow-Origin`, to let pages in other origins fetch results.
+    pub allow_origin: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Page {
+    Html,
+    Json,
+}
+
+/// Serve records at `/` as HTML and at `/records.json` as JSON, searching at each request.
+///
+/// `/records.json` is the same document as `--format json`, see `serve-records` schema.
+///
+/// Hooks run when the location changes, not at each request.
+///
+/// Returns when reload is requested.
+pub(super) fn serve<F: Filter>(
+    opts: &ServeOptions,
+    searcher: &Searcher<F>,
+    get_loc_func: &GetLocFunc,
+    marks: &Marks,
+    max_entries: usize,
+    last_mod: DateTime<Utc>,
+    hooks: &Hooks,
+) -> Result<(), Fail> {
+    let listener =
+        TcpListener::bind(opts.listen).err_msg(format!("can't listen on {}", opts.listen))?;
+    listener.set_nonblocking(true)?;
+    println!(
+        "{}",
+        trf(Msg::Serving, &[&format!("http://{}/", opts.listen)])
+    );
+
+    let html = HtmlPrinter::new(Some(opts.refresh));
+    let json = JsonPrinter::new();
+    let last_location = RefCell::new(Option::<Location>::None);
+    let render = |page: Page| -> Result<String, Fail> {
+        let (location, visited) = get_loc_func()?;
+        let mut records = searcher.search(&location, &visited);
+        marks.arrange(&mut records);
+        if last_location.borrow().as_ref() != Some(&location) {
+            // Failures of hooks must not break pages.
+            if let Err(e) = hooks.searched(&location, &records) {
+                eprintln!("{}", trf(Msg::Error, &[&e]));
+            }
+            last_location.replace(Some(location));
+        }
+        Ok(match page {
+            Page::Html => html.render(&records, max_entries, last_mod),
+            Page::Json => json.render(&records, max_entries, last_mod),
+        })
+    };
+
+    loop {
+        if reload_requested() {
+            return Ok(());
+        }
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                sleep(ACCEPT_POLL_PERIOD);
+                continue;
+            }
+            Err(e) => return Err(Fail::from(e).msg("can't accept connection")),
+        };
+        // A broken connection must not stop serving others.
+        if let Err(e) = respond_tcp(stream, opts.allow_origin.as_deref(), &render) {
+            eprintln!("{}", trf(Msg::Error, &[&e]));
+        }
+    }
+}
+
+fn respond_tcp(
+    stream: TcpStream,
+    allow_origin: Option<&str>,
+    render: &dyn Fn(Page) -> Result<String, Fail>,
+) -> Result<(), Fail> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    respond(stream, allow_origin, render)
+}
+
+fn respond<S: Read + Write>(
+    mut stream: S,
+    allow_origin: Option<&str>,
+    render: &dyn Fn(Page) -> Result<String, Fail>,
+) -> Result<(), Fail> {
+    let mut reader = BufReader::new(&mut stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers are not used, but read so that clients don't see reset connections.
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("").split('?').next().unwrap_or("");
+
+    let page = match path {
+        "/" | "/index.html" => Some((Page::Html, "text/html; charset=utf-8")),
+        "/records.json" => Some((Page::Json, "application/json")),
+        _ => None,
+    };
+    let (status, content_type, body) = match page {
+        _ if method != "GET" && method != "HEAD" => (
+            "405 Method Not Allowed",
+            "text/plain; charset=utf-8",
+            "method not allowed\n".to_owned(),
+        ),
+        Some((page, content_type)) => match render(page) {
+            Ok(body) => ("200 OK", content_type, body),
+            Err(e) => (
+                "500 Internal Server Error",
+                "text/plain; charset=utf-8",
+                format!("{}\n", e),
+            ),
+        },
+        None => (
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "not found\n".to_owned(),
+        ),
+    };
+
+    let mut headers = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    if let Some(origin) = allow_origin {
+        headers.push_str(&format!("Access-Control-Allow-Origin: {}\r\n", origin));
+    }
+    headers.push_str("Connection: close\r\n\r\n");
+    stream.write_all(headers.as_bytes())?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes())?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    struct MockStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn request_with(request_line: &str, allow_origin: Option<&str>, fail: bool) -> String {
+        let mut stream = MockStream {
+            input: Cursor::new(format!("{}\r\nHost: localhost\r\n\r\n", request_line).into_bytes()),
+            output: Vec::new(),
+        };
+        let render = |page: Page| {
+            if fail {
+                Err(Fail::new("broken"))
+            } else {
+                Ok(format!("{:?}", page))
+            }
+        };
+        respond(&mut stream, allow_origin, &render).unwrap();
+        String::from_utf8(stream.output).unwrap()
+    }
+
+    fn request(request_line: &str) -> String {
+        request_with(request_line, None, false)
+    }
+
+    #[test]
+    fn routes_by_path() {
+        let res = request("GET / HTTP/1.1");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        assert!(res.ends_with("\r\n\r\nHtml"));
+
+        assert!(request("GET /index.html?top=10 HTTP/1.1").ends_with("\r\n\r\nHtml"));
+
+        let res = request("GET /records.json HTTP/1.1");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.contains("Content-Type: application/json\r\n"));
+        assert!(res.ends_with("\r\n\r\nJson"));
+
+        assert!(request("GET /records.csv HTTP/1.1").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn routes_by_method() {
+        assert!(request("POST / HTTP/1.1").starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(
+            request("DELETE /nothing HTTP/1.1").starts_with("HTTP/1.1 405 Method Not Allowed\r\n")
+        );
+
+        let res = request("HEAD /records.json HTTP/1.1");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.contains("Content-Length: 4\r\n"));
+        assert!(res.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn render_error_is_500() {
+        let res = request_with("GET / HTTP/1.1", None, true);
+        assert!(res.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+        assert!(res.ends_with("\r\n\r\nbroken\n"));
+    }
+
+    #[test]
+    fn allow_origin_is_opt_in() {
+        assert!(!request("GET / HTTP/1.1").contains("Access-Control-Allow-Origin"));
+        let res = request_with("GET / HTTP/1.1", Some("https://example.com"), false);
+        assert!(res.contains("Access-Control-Allow-Origin: https://example.com\r\n"));
+    }
+}
@@ -0,0 +1,161 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use tiny_fail::{ErrorMessageExt, Fail};
+
+/// Longest sleep between checks of shutdown request.
+const STOP_CHECK_PERIOD: Duration = Duration::from_secs(1);
+
+type Run<'a, S> = Box<dyn FnMut(&mut S) -> Result<(), Fail> + 'a>;
+
+/// Activities sharing state `S`, each run at its own interval on the current thread.
+pub struct Tasks<'a, S> {
+    tasks: Vec<Task<'a, S>>,
+}
+
+struct Task<'a, S> {
+    name: &'static str,
+    interval: Duration,
+    next: Instant,
+    run: Run<'a, S>,
+}
+
+impl<'a, S> Tasks<'a, S> {
+    pub fn new() -> Tasks<'a, S> {
+        Tasks { tasks: Vec::new() }
+    }
+
+    /// Add a task first run `interval` after start. Tasks due at once run in order of addition.
+    pub fn add(
+        &mut self,
+        name: &'static str,
+        interval: Duration,
+        run: impl FnMut(&mut S) -> Result<(), Fail> + 'a,
+    ) {
+        self.tasks.push(Task {
+            name,
+            interval,
+            next: Instant::now() + interval,
+            run: Box::new(run),
+        });
+    }
+
+    /// Run tasks until `stop` returns true, which is checked between tasks and while sleeping.
+    ///
+    /// Returns at the first error of any task.
+    pub fn run(&mut self, state: &mut S, stop: impl Fn() -> bool) -> Result<(), Fail> {
+        loop {
+            if stop() {
+                return Ok(());
+            }
+
+            let next = match self.tasks.iter().map(|t| t.next).min() {
+                Some(next) => next,
+                None => return Ok(()),
+            };
+            let now = Instant::now();
+            if now < next {
+                sleep((next - now).min(STOP_CHECK_PERIOD));
+                continue;
+            }
+
+            for task in &mut self.tasks {
+                if task.next > now {
+                    continue;
+                }
+                if stop() {
+                    return Ok(());
+                }
+                (task.run)(state).err_msg(format!("{} task failed", task.name))?;
+                // Skip runs missed while busy or suspended, instead of catching up.
+                task.next = Instant::now() + task.interval;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn tasks_run_at_own_intervals() {
+        let mut tasks: Tasks<Vec<&'static str>> = Tasks::new();
+        tasks.add("fast", TICK, |log| {
+            log.push("fast");
+            Ok(())
+        });
+        tasks.add("slow", TICK * 5, |log| {
+            log.push("slow");
+            Ok(())
+        });
+
+        let mut log = Vec::new();
+        let start = Instant::now();
+        tasks
+            .run(&mut log, || start.elapsed() >= TICK * 12)
+            .unwrap();
+
+        let fast = log.iter().filter(|&&t| t == "fast").count();
+        let slow = log.iter().filter(|&&t| t == "slow").count();
+        assert!(fast > slow, "{:?}", log);
+        assert!((1..=2).contains(&slow), "{:?}", log);
+        assert_eq!(log.first(), Some(&"fast"));
+    }
+
+    #[test]
+    fn due_tasks_run_in_order_of_addition() {
+        let mut tasks: Tasks<Vec<&'static str>> = Tasks::new();
+        tasks.add("first", Duration::from_secs(0), |log| {
+            log.push("first");
+            Ok(())
+        });
+        tasks.add("second", Duration::from_secs(0), |log| {
+            log.push("second");
+            Ok(())
+        });
+
+        let mut log = Vec::new();
+        let runs = Cell::new(0);
+        tasks
+            .run(&mut log, || {
+                runs.set(runs.get() + 1);
+                runs.get() > 3
+            })
+            .unwrap();
+        assert_eq!(log, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn first_error_stops_tasks() {
+        let mut tasks: Tasks<usize> = Tasks::new();
+        tasks.add("count", TICK, |n| {
+            *n += 1;
+            Ok(())
+        });
+        tasks.add("broken", TICK * 3, |_| Err(Fail::new("broken")));
+
+        let mut n = 0;
+        let err = tasks.run(&mut n, || false).unwrap_err();
+        assert!(err.to_string().contains("broken task failed"), "{}", err);
+        assert!(n >= 1);
+    }
+
+    #[test]
+    fn stop_before_first_run() {
+        let mut tasks: Tasks<usize> = Tasks::new();
+        tasks.add("count", Duration::from_secs(0), |n| {
+            *n += 1;
+            Ok(())
+        });
+
+        let mut n = 0;
+        tasks.run(&mut n, || true).unwrap();
+        assert_eq!(n, 0);
+        assert!(Tasks::<usize>::new().run(&mut n, || false).is_ok());
+    }
+}
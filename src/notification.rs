@@ -0,0 +1,96 @@
+use std::cell::RefCell;
+
+use tiny_fail::Fail;
+
+use crate::diagnostics::{warn, Warning};
+use crate::i18n::{trf, Msg};
+use crate::journal::Location;
+use crate::searcher::Record;
+
+/// Desktop notification when a new candidate enters the top entries, right after arriving in another system.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    top: usize,
+    /// System and top entries of the last search.
+    last: RefCell<Option<(String, Vec<StationKey>)>>,
+}
+
+/// System name and station name.
+type StationKey = (String, String);
+
+impl Notifier {
+    pub fn new(top: usize) -> Notifier {
+        Notifier {
+            top,
+            last: RefCell::new(None),
+        }
+    }
+
+    /// Check records searched at `location`. The first search only records the top entries.
+    ///
+    /// Failures to show notifications are reported as warnings, not to stop searching.
+    pub fn searched(&self, location: &Location, records: &[Record]) {
+        let top: Vec<StationKey> = records.iter().take(self.top).map(key).collect();
+        let candidate = {
+            let mut last = self.last.borrow_mut();
+            let candidate = match *last {
+                Some((ref system, ref last_top)) if *system != location.star_system => {
+                    // Changed first entry takes precedence over lower new entries.
+                    records
+                        .first()
+                        .filter(|r| last_top.first() != Some(&key(r)))
+                        .or_else(|| {
+                            records
+                                .iter()
+                                .take(self.top)
+                                .find(|r| !last_top.contains(&key(r)))
+                        })
+                }
+                _ => None,
+            };
+            *last = Some((location.star_system.clone(), top));
+            candidate
+        };
+
+        if let Some(r) = candidate {
+            let body = trf(
+                Msg::NewCandidate,
+                &[
+                    &r.station.name,
+                    &r.station.system_name,
+                    &format!("{:.2}", r.distance),
+                    &r.outdated().map_or(0, |o| o.days),
+                ],
+            );
+            if let Err(e) = show(&body) {
+                warn(Warning::NotificationFailed(e.to_string()));
+            }
+        }
+    }
+}
+
+fn key(r: &Record) -> StationKey {
+    (r.station.system_name.clone(), r.station.name.clone())
+}
+
+#[cfg(feature = "notify-rust")]
+fn show(body: &str) -> Result<(), Fail> {
+    use notify_rust::Notification;
+    use tiny_fail::ErrorMessageExt;
+
+    Notification::new()
+        .appname("near-old-stations")
+        .summary("near-old-stations")
+        .body(body)
+        .show()
+        .err_msg("can't show desktop notification")?;
+    Ok(())
+}
+
+/// Config with `[notify]` is rejected in this build, so this is not called normally.
+#[cfg(not(feature = "notify-rust"))]
+fn show(_body: &str) -> Result<(), Fail> {
+    Err(Fail::new(
+        "desktop notification is not supported in this build (enable 'notify-rust' feature)",
+    ))
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, TimeZone, Utc};
+
+/// Major game update, which changed stations so that older data may be structurally wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Patch {
+    /// Names to specify, matched case-insensitively. First one is the main name.
+    pub names: &'static [&'static str],
+    /// Release date in UTC, as `(year, month, day)`.
+    pub date: (i32, u32, u32),
+}
+
+impl Patch {
+    pub fn name(&self) -> &'static str {
+        self.names[0]
+    }
+
+    /// Start of the release date.
+    pub fn released(&self) -> DateTime<Utc> {
+        let (y, m, d) = self.date;
+        Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap()
+    }
+}
+
+/// Release of the game itself, not in `PATCHES`. No data in dumps is older than this.
+pub const GAME_RELEASE: Patch = Patch {
+    names: &["Release", "1.0"],
+    date: (2014, 12, 16),
+};
+pub const HORIZONS: Patch = Patch {
+    names: &["Horizons", "2.0"],
+    date: (2015, 12, 15),
+};
+pub const BEYOND: Patch = Patch {
+    names: &["Beyond", "3.0"],
+    date: (2018, 2, 27),
+};
+pub const BEYOND_CHAPTER_FOUR: Patch = Patch {
+    names: &["3.3", "BeyondChapterFour"],
+    date: (2018, 12, 11),
+};
+pub const FLEET_CARRIERS: Patch = Patch {
+    names: &["FleetCarriers", "3.7"],
+    date: (2020, 6, 9),
+};
+pub const ODYSSEY: Patch = Patch {
+    names: &["Odyssey", "4.0"],
+    date: (2021, 5, 19),
+};
+pub const UPDATE_14: Patch = Patch {
+    names: &["U14"],
+    date: (2022, 11, 29),
+};
+pub const TRAILBLAZERS: Patch = Patch {
+    names: &["Trailblazers", "U18"],
+    date: (2024, 2, 27),
+};
+
+/// Known patches in order of release.
+pub const PATCHES: &[Patch] = &[
+    HORIZONS,
+    BEYOND,
+    BEYOND_CHAPTER_FOUR,
+    FLEET_CARRIERS,
+    ODYSSEY,
+    UPDATE_14,
+    TRAILBLAZERS,
+];
+
+pub fn find_patch(name: &str) -> Option<&'static Patch> {
+    PATCHES
+        .iter()
+        .find(|p| p.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+}
@@ -0,0 +1,133 @@
+use std::env::var_os;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::lock::LockFile;
+
+const APP_DIR: &str = "near-old-stations";
+const CONFIG_FILE: &str = "config.toml";
+const CACHE_LOCK_FILE: &str = "cache.lock";
+/// Locks of exited instances are removed at once, so this is only waited for running ones.
+const CACHE_LOCK_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+lazy_static! {
+    static ref PATHS: RwLock<Paths> = RwLock::new(Paths::portable());
+    static ref CACHE_LOCK: Mutex<Weak<LockFile>> = Mutex::new(Weak::new());
+}
+
+/// Directories for config, downloaded caches and persistent state.
+///
+/// If `config.toml` exists in the current directory, all files are kept there (portable mode, as before).
+/// Otherwise platform directories are used. Each directory can be overridden by
+/// `NEAR_OLD_STATIONS_CONFIG_DIR`, `NEAR_OLD_STATIONS_CACHE_DIR` and `NEAR_OLD_STATIONS_STATE_DIR`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paths {
+    pub config: PathBuf,
+    pub cache: PathBuf,
+    pub state: PathBuf,
+}
+
+impl Paths {
+    pub fn resolve() -> Paths {
+        let mut paths = if Path::new(CONFIG_FILE).exists() {
+            Paths::portable()
+        } else {
+            Paths::platform().unwrap_or_else(Paths::portable)
+        };
+
+        if let Some(dir) = var_os("NEAR_OLD_STATIONS_CONFIG_DIR") {
+            paths.config = PathBuf::from(dir);
+        }
+        if let Some(dir) = var_os("NEAR_OLD_STATIONS_CACHE_DIR") {
+            paths.cache = PathBuf::from(dir);
+        }
+        if let Some(dir) = var_os("NEAR_OLD_STATIONS_STATE_DIR") {
+            paths.state = PathBuf::from(dir);
+        }
+
+        paths
+    }
+
+    fn portable() -> Paths {
+        Paths {
+            config: PathBuf::from("."),
+            cache: PathBuf::from("."),
+            state: PathBuf::from("."),
+        }
+    }
+
+    #[cfg(windows)]
+    fn platform() -> Option<Paths> {
+        let roaming = PathBuf::from(var_os("APPDATA")?).join(APP_DIR);
+        let local = PathBuf::from(var_os("LOCALAPPDATA")?).join(APP_DIR);
+        Some(Paths {
+            config: roaming,
+            cache: local.join("cache"),
+            state: local,
+        })
+    }
+
+    #[cfg(not(windows))]
+    fn platform() -> Option<Paths> {
+        let home = PathBuf::from(var_os("HOME")?);
+        let xdg = |key: &str, default: &str| {
+            var_os(key)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(default))
+                .join(APP_DIR)
+        };
+        Some(Paths {
+            config: xdg("XDG_CONFIG_HOME", ".config"),
+            cache: xdg("XDG_CACHE_HOME", ".cache"),
+            state: xdg("XDG_STATE_HOME", ".local/state"),
+        })
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.config.join(CONFIG_FILE)
+    }
+}
+
+/// Create directories and use them for following file accesses.
+pub fn set_paths(paths: Paths) -> Result<(), Fail> {
+    for dir in &[&paths.cache, &paths.state] {
+        create_dir_all(dir).err_msg(format!("can't create directory: {:?}", dir))?;
+    }
+    *PATHS.write().expect("paths lock is poisoned") = paths;
+    Ok(())
+}
+
+pub fn paths() -> Paths {
+    PATHS.read().expect("paths lock is poisoned").clone()
+}
+
+/// Path of downloaded or generated file, which can be deleted anytime.
+pub fn cache_file<P: AsRef<Path>>(name: P) -> PathBuf {
+    paths().cache.join(name)
+}
+
+/// Lock cache directory, so that other instances don't rewrite files in use.
+///
+/// The lock is shared in this process, and released when all returned handles are dropped.
+pub fn lock_cache() -> Result<Arc<LockFile>, Fail> {
+    let mut held = CACHE_LOCK.lock().expect("cache lock is poisoned");
+    if let Some(lock) = held.upgrade() {
+        return Ok(lock);
+    }
+    let lock = Arc::new(LockFile::acquire_wait(
+        cache_file(CACHE_LOCK_FILE),
+        CACHE_LOCK_TIMEOUT,
+    )?);
+    *held = Arc::downgrade(&lock);
+    Ok(lock)
+}
+
+/// Path of file to be kept between runs. Absolute `name` is used as is.
+pub fn state_file<P: AsRef<Path>>(name: P) -> PathBuf {
+    paths().state.join(name)
+}
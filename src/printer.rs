@@ -1,11 +1,18 @@
+pub mod csv;
+pub mod html;
+pub mod json;
 pub mod text;
 
+pub use csv::CsvPrinter;
+pub use html::HtmlPrinter;
+pub use json::JsonPrinter;
 pub use text::TextPrinter;
 
 use chrono::{DateTime, Utc};
 use tiny_fail::Fail;
 
 use crate::searcher::Record;
+use crate::selection::Selection;
 
 pub trait Printer {
     fn print(
@@ -16,6 +23,73 @@ pub trait Printer {
     ) -> Result<(), Fail>;
 
     fn clear(&mut self) -> Result<(), Fail>;
+
+    /// Render records as the same content as `print()`.
+    fn render(&self, records: &[Record], limit: usize, last_mod: DateTime<Utc>) -> String;
+
+    /// Print records with a cursor and selection marks for interactive mode.
+    fn print_selection(
+        &mut self,
+        records: &[Record],
+        limit: usize,
+        last_mod: DateTime<Utc>,
+        _cursor: usize,
+        _selection: &Selection,
+    ) -> Result<(), Fail> {
+        self.print(records, limit, last_mod)
+    }
+
+    /// Print records in visiting order. `cumulative[i]` is distance from origin through i-th stop.
+    fn print_route(
+        &mut self,
+        route: &[Record],
+        _cumulative: &[f64],
+        last_mod: DateTime<Utc>,
+    ) -> Result<(), Fail> {
+        self.print(route, route.len(), last_mod)
+    }
+}
+
+/// Statistics of a search result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub total: usize,
+    pub shown: usize,
+    pub nearest: Option<f64>,
+    pub farthest: Option<f64>,
+    pub median_days: Option<i64>,
+}
+
+impl Summary {
+    pub fn new(records: &[Record], limit: usize) -> Summary {
+        let shown = records.len().min(limit);
+
+        let mut nearest = Option::<f64>::None;
+        let mut farthest = Option::<f64>::None;
+        for r in &records[..shown] {
+            nearest = Some(nearest.map_or(r.distance, |d| d.min(r.distance)));
+            farthest = Some(farthest.map_or(r.distance, |d| d.max(r.distance)));
+        }
+
+        let mut days: Vec<i64> = records
+            .iter()
+            .filter_map(|r| r.outdated().map(|o| o.days))
+            .collect();
+        days.sort_unstable();
+        let median_days = days.get(days.len() / 2).cloned();
+
+        Summary {
+            total: records.len(),
+            shown,
+            nearest,
+            farthest,
+            median_days,
+        }
+    }
+
+    pub fn hidden(&self) -> usize {
+        self.total - self.shown
+    }
 }
 
 fn si_fmt(x: Option<f64>) -> String {
@@ -28,3 +102,32 @@ fn si_fmt(x: Option<f64>) -> String {
         Some(x) => format!("{:.0}k", x / 1000.0),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::si_fmt;
+
+    proptest! {
+        #[test]
+        fn si_fmt_any_value(x in prop::option::of(any::<f64>())) {
+            prop_assert!(!si_fmt(x).is_empty());
+        }
+
+        #[test]
+        fn si_fmt_is_short(x in 0.0f64..1e7) {
+            prop_assert!(si_fmt(Some(x)).chars().count() <= 8);
+        }
+    }
+
+    #[test]
+    fn si_fmt_edge_values() {
+        assert_eq!(si_fmt(None), "unknown");
+        assert_eq!(si_fmt(Some(0.0)), "0.00 ");
+        assert_eq!(si_fmt(Some(1_500.0)), "1.50k");
+        si_fmt(Some(f64::NAN));
+        si_fmt(Some(f64::INFINITY));
+        si_fmt(Some(f64::NEG_INFINITY));
+    }
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use super::Printer;
+use crate::export::csv_field;
+use crate::i18n::{trf, Msg};
+use crate::searcher::{Days, Record};
+
+/// Write all records to a CSV file, regardless of `max_entries`, to be analyzed in spreadsheets.
+#[derive(Debug, Clone)]
+pub struct CsvPrinter {
+    path: PathBuf,
+}
+
+impl CsvPrinter {
+    pub fn new(path: PathBuf) -> CsvPrinter {
+        CsvPrinter { path }
+    }
+}
+
+impl Printer for CsvPrinter {
+    /// Overwrite the file with all records. `limit` is ignored.
+    fn print(
+        &mut self,
+        records: &[Record],
+        limit: usize,
+        last_mod: DateTime<Utc>,
+    ) -> Result<(), Fail> {
+        let text = self.render(records, limit, last_mod);
+        let mut f =
+            File::create(&self.path).err_msg(format!("can't create file: {:?}", self.path))?;
+        f.write_all(text.as_bytes())?;
+
+        println!(
+            "{}",
+            trf(Msg::Exported, &[&records.len(), &self.path.display()])
+        );
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), Fail> {
+        Ok(())
+    }
+
+    fn render(&self, records: &[Record], _limit: usize, _last_mod: DateTime<Utc>) -> String {
+        let days = |d: &Days| d.days().map_or(String::new(), |d| d.to_string());
+
+        let mut s = String::from(
+            "Station,System,Type,Distance,DistanceToArrival,Information,Market,Shipyard,Outfitting,Days,Category,Score,Visited,X,Y,Z\n",
+        );
+        for r in records {
+            let outdated = r.outdated();
+            s.push_str(&format!(
+                "{},{},{},{:.2},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                csv_field(&r.station.name),
+                csv_field(&r.station.system_name),
+                r.station.st_type,
+                r.distance,
+                r.station
+                    .distance_to_arrival
+                    .map_or(String::new(), |d| format!("{:.0}", d)),
+                days(&r.information_days),
+                days(&r.market_days),
+                days(&r.shipyard_days),
+                days(&r.outfitting_days),
+                outdated.map_or(String::new(), |o| o.days.to_string()),
+                outdated.map_or(String::new(), |o| o.category.letter().to_string()),
+                r.score(),
+                r.visited,
+                r.station.coords.x(),
+                r.station.coords.y(),
+                r.station.coords.z(),
+            ));
+        }
+        s
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Local, Utc};
+use tiny_fail::Fail;
+
+use super::Printer;
+use crate::i18n::{trf, Msg};
+use crate::searcher::Record;
+
+/// Print records as an HTML page with a table, to be viewed in browsers.
+#[derive(Debug, Default, Clone)]
+pub struct HtmlPrinter {
+    /// Seconds to reload the page in browsers.
+    refresh: Option<u64>,
+}
+
+impl HtmlPrinter {
+    pub fn new(refresh: Option<u64>) -> HtmlPrinter {
+        HtmlPrinter { refresh }
+    }
+}
+
+impl Printer for HtmlPrinter {
+    fn print(
+        &mut self,
+        records: &[Record],
+        limit: usize,
+        last_mod: DateTime<Utc>,
+    ) -> Result<(), Fail> {
+        println!("{}", self.render(records, limit, last_mod));
+        Ok(())
+    }
+
+    /// Each page is a whole document, so nothing to clear.
+    fn clear(&mut self) -> Result<(), Fail> {
+        Ok(())
+    }
+
+    fn render(&self, records: &[Record], limit: usize, last_mod: DateTime<Utc>) -> String {
+        let mut s = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        if let Some(refresh) = self.refresh {
+            s.push_str(&format!(
+                "<meta http-equiv=\"refresh\" content=\"{}\">\n",
+                refresh
+            ));
+        }
+        s.push_str("<title>near-old-stations</title>\n");
+        s.push_str("<style>td.num { text-align: right; } th, td { padding: 0 0.5em; }</style>\n");
+        s.push_str("</head>\n<body>\n");
+
+        let updated = last_mod.with_timezone(&Local).format("%F %T %Z");
+        s.push_str(&format!(
+            "<p>{}</p>\n",
+            escape(&trf(Msg::Header, &[&records.len(), &updated]))
+        ));
+
+        s.push_str("<table>\n<tr><th>Station</th><th>System</th><th>Type</th><th>Distance</th><th>Days</th><th>Category</th></tr>\n");
+        for r in records.iter().take(limit) {
+            let outdated = r.outdated();
+            s.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{:.2} Ly</td><td class=\"num\">{}</td><td>{}</td></tr>\n",
+                escape(&r.station.name),
+                escape(&r.station.system_name),
+                escape(&r.station.st_type.to_string()),
+                r.distance,
+                outdated.map_or(String::new(), |o| o.days.to_string()),
+                outdated.map_or(String::new(), |o| o.category.letter().to_string()),
+            ));
+        }
+        s.push_str("</table>\n</body>\n</html>\n");
+        s
+    }
+}
+
+/// Escape text in HTML elements and attribute values.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape;
+
+    #[test]
+    fn escape_special_chars() {
+        assert_eq!(
+            escape("Smith's <Hub> & \"Port\""),
+            "Smith&#39;s &lt;Hub&gt; &amp; &quot;Port&quot;"
+        );
+        assert_eq!(escape("&amp;"), "&amp;amp;");
+        assert_eq!(escape("Plain ステーション"), "Plain ステーション");
+        assert_eq!(escape(""), "");
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+#[cfg(feature = "schemars")]
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::Serialize;
+use serde_json::to_string;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use super::Printer;
+use crate::coords::Coords;
+use crate::searcher::{Days, Record};
+
+/// Print each result as a JSON document in one line, to be read by other tools.
+#[derive(Debug, Default, Clone)]
+pub struct JsonPrinter;
+
+impl JsonPrinter {
+    pub fn new() -> JsonPrinter {
+        JsonPrinter
+    }
+}
+
+impl Printer for JsonPrinter {
+    fn print(
+        &mut self,
+        records: &[Record],
+        limit: usize,
+        last_mod: DateTime<Utc>,
+    ) -> Result<(), Fail> {
+        let result = JsonResult::new(records, limit, last_mod);
+        println!(
+            "{}",
+            to_string(&result).err_msg("can't encode search result")?
+        );
+        Ok(())
+    }
+
+    /// Documents are separated by lines, so nothing to clear.
+    fn clear(&mut self) -> Result<(), Fail> {
+        Ok(())
+    }
+
+    fn render(&self, records: &[Record], limit: usize, last_mod: DateTime<Utc>) -> String {
+        let result = JsonResult::new(records, limit, last_mod);
+        // Serialization of these types never fails.
+        to_string(&result).expect("can't encode search result")
+    }
+}
+
+/// Output of `JsonPrinter`.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct JsonResult<'a> {
+    /// Last modified time of the stations dump, in RFC 3339.
+    pub last_update: String,
+    /// Number of records before limited by `max_entries`.
+    pub total: usize,
+    pub records: Vec<JsonRecord<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct JsonRecord<'a> {
+    pub station: &'a str,
+    pub system: &'a str,
+    pub distance: f64,
+    pub coords: Coords,
+    pub visited: bool,
+    pub days: CategoryDays,
+    pub categories: CategoryStates,
+}
+
+/// Days since updated in each category. `null` if the station doesn't have the information.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct CategoryDays {
+    pub information: Option<i64>,
+    pub market: Option<i64>,
+    pub shipyard: Option<i64>,
+    pub outfitting: Option<i64>,
+}
+
+/// Staleness of each category, to show badges.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct CategoryStates {
+    pub information: CategoryState,
+    pub market: CategoryState,
+    pub shipyard: CategoryState,
+    pub outfitting: CategoryState,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct CategoryState {
+    /// Days since updated. `null` if the station doesn't have the information.
+    pub days: Option<i64>,
+    pub outdated: bool,
+    /// Days to be outdated, after overrides by station type and distance. `null` if not checked.
+    pub threshold: Option<i64>,
+}
+
+impl CategoryState {
+    fn new(days: &Days) -> CategoryState {
+        CategoryState {
+            days: days.days(),
+            outdated: days.is_outdated(),
+            threshold: days.threshold(),
+        }
+    }
+}
+
+impl<'a> JsonResult<'a> {
+    pub fn new(records: &'a [Record], limit: usize, last_mod: DateTime<Utc>) -> JsonResult<'a> {
+        JsonResult {
+            last_update: last_mod.to_rfc3339(),
+            total: records.len(),
+            records: records
+                .iter()
+                .take(limit)
+                .map(|r| JsonRecord {
+                    station: &r.station.name,
+                    system: &r.station.system_name,
+                    distance: r.distance,
+                    coords: r.station.coords,
+                    visited: r.visited,
+                    days: CategoryDays {
+                        information: r.information_days.days(),
+                        market: r.market_days.days(),
+                        shipyard: r.shipyard_days.days(),
+                        outfitting: r.outfitting_days.days(),
+                    },
+                    categories: CategoryStates {
+                        information: CategoryState::new(&r.information_days),
+                        market: CategoryState::new(&r.market_days),
+                        shipyard: CategoryState::new(&r.shipyard_days),
+                        outfitting: CategoryState::new(&r.outfitting_days),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// JSON Schema of each line printed by `JsonPrinter`.
+#[cfg(feature = "schemars")]
+pub fn schema() -> RootSchema {
+    schema_for!(JsonResult<'static>)
+}
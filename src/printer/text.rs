@@ -1,15 +1,354 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
 use chrono::{DateTime, Local, Utc};
-use tiny_fail::Fail;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use super::{si_fmt, Printer, Summary};
+use crate::diagnostics::warnings;
+use crate::i18n::{tr, trf, Msg};
+use crate::searcher::{Days, Record};
+use crate::selection::Selection;
+use crate::stations::StationType;
 
-use super::{si_fmt, Printer};
-use crate::searcher::Record;
+/// Dumps at least this old are noted in each line.
+const DUMP_LAG_DAYS: i64 = 1;
 
 #[derive(Debug, Default, Clone)]
-pub struct TextPrinter {}
+pub struct TextPrinter {
+    compact: bool,
+    type_codes: HashMap<StationType, String>,
+    danger: bool,
+    neglected_days: Option<i64>,
+    max_width: Option<usize>,
+    output: Option<PathBuf>,
+}
 
 impl TextPrinter {
     pub fn new() -> TextPrinter {
-        TextPrinter {}
+        TextPrinter::default()
+    }
+
+    /// Use short codes for station type column.
+    /// Codes in `type_codes` take precedence over `StationType::short()`.
+    pub fn set_compact(&mut self, type_codes: HashMap<StationType, String>) {
+        self.compact = true;
+        self.type_codes = type_codes;
+    }
+
+    /// Annotate stations in anarchy or low security systems.
+    pub fn set_danger(&mut self, danger: bool) {
+        self.danger = danger;
+    }
+
+    /// Annotate stations whose median refresh interval is `days` or more.
+    pub fn set_neglected_days(&mut self, days: Option<i64>) {
+        self.neglected_days = days;
+    }
+
+    /// Limit line width. Terminal width is also respected.
+    pub fn set_max_width(&mut self, max_width: Option<usize>) {
+        self.max_width = max_width;
+    }
+
+    /// Append output to file instead of stdout.
+    pub fn set_output(&mut self, output: Option<PathBuf>) {
+        self.output = output;
+    }
+
+    /// Render all stations in a system with days since each information is updated.
+    pub fn render_system(&self, system: &str, records: &[Record]) -> String {
+        let mut s = trf(Msg::Here, &[&records.len(), &system]);
+        s.push('\n');
+
+        let days = |d: &Days| d.days().map_or("-".to_owned(), |d| format!("{}d", d));
+        for r in records {
+            let mut line = format!(
+                "{}{:>8} Ls  I:{:>5} M:{:>5} S:{:>5} O:{:>5}  {:<25} ({})",
+                if r.visited { "*" } else { " " },
+                si_fmt(r.station.distance_to_arrival),
+                days(&r.information_days),
+                days(&r.market_days),
+                days(&r.shipyard_days),
+                days(&r.outfitting_days),
+                r.station.name,
+                self.type_name(&r.station.st_type),
+            );
+            if let Some(days) = r.visited_days {
+                line.push_str("  ");
+                line.push_str(&trf(Msg::Visited, &[&days]));
+            }
+            s.push_str(&line);
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Render stations around the origin with distance and days since each information is updated.
+    pub fn render_nearest(&self, origin: &str, records: &[Record]) -> String {
+        let mut s = trf(Msg::Nearest, &[&records.len(), &origin]);
+        s.push('\n');
+
+        let days = |d: &Days| d.days().map_or("-".to_owned(), |d| format!("{}d", d));
+        for r in records {
+            s.push_str(&format!(
+                "{}{:>8.2} Ly  I:{:>5} M:{:>5} S:{:>5} O:{:>5}  {} / {} ({})\n",
+                if r.visited { "*" } else { " " },
+                r.distance,
+                days(&r.information_days),
+                days(&r.market_days),
+                days(&r.shipyard_days),
+                days(&r.outfitting_days),
+                r.station.system_name,
+                r.station.name,
+                self.type_name(&r.station.st_type),
+            ));
+        }
+        s
+    }
+
+    /// Render stations in visiting order with distance of each leg and cumulative distance.
+    pub fn render_route(
+        &self,
+        route: &[Record],
+        cumulative: &[f64],
+        last_mod: DateTime<Utc>,
+    ) -> String {
+        let mut s = self.header(route, last_mod);
+        s.push('\n');
+
+        let mut prev = 0.0;
+        for (i, (r, &total)) in route.iter().zip(cumulative).enumerate() {
+            let (days, letter) = max_outdated(r);
+            s.push_str(&format!(
+                "{:>3}{:<2}{:>7.2} Ly {:>8.2} Ly  {:>3}d({})  {} / {} ({})\n",
+                i + 1,
+                if r.visited { "*" } else { " " },
+                total - prev,
+                total,
+                days,
+                letter,
+                r.station.system_name,
+                r.station.name,
+                self.type_name(&r.station.st_type),
+            ));
+            prev = total;
+        }
+
+        s.push_str(&trf(
+            Msg::TourTotal,
+            &[&route.len(), &format!("{:.2}", prev)],
+        ));
+        s.push('\n');
+        s
+    }
+
+    fn write_out(&self, text: &str) -> Result<(), Fail> {
+        match self.output {
+            Some(ref path) => {
+                let mut f = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .err_msg(format!("can't open file: {:?}", path))?;
+                writeln!(f, "=== {} ===", Local::now().format("%F %T"))?;
+                f.write_all(text.as_bytes())?;
+            }
+            None => print!("{}", text),
+        }
+        Ok(())
+    }
+
+    fn width(&self) -> Option<usize> {
+        match (self.max_width, terminal_width()) {
+            (Some(w), Some(t)) => Some(w.min(t)),
+            (w, t) => w.or(t),
+        }
+    }
+
+    fn line(&self, i: usize, r: &Record) -> String {
+        let mut outdated = String::with_capacity(4);
+        outdated.push(if r.information_days.is_outdated() {
+            'I'
+        } else {
+            ' '
+        });
+        outdated.push(if r.market_days.is_outdated() {
+            'M'
+        } else {
+            ' '
+        });
+        outdated.push(if r.shipyard_days.is_outdated() {
+            'S'
+        } else {
+            ' '
+        });
+        outdated.push(if r.outfitting_days.is_outdated() {
+            'O'
+        } else {
+            ' '
+        });
+
+        let mut notes = String::new();
+        if r.station.is_damaged() {
+            notes.push_str(&format!("  [{}]", r.station.state.as_ref().unwrap()));
+        }
+        if self.danger && r.station.is_dangerous() {
+            notes.push_str(&format!("  !{}", r.station.security_level().unwrap()));
+        }
+        if let Some(days) = self.neglected_days {
+            if r.station.is_neglected(days) {
+                notes.push_str("  ");
+                notes.push_str(&trf(
+                    Msg::Neglected,
+                    &[&r.station.refresh_interval.unwrap()],
+                ));
+            }
+        }
+        if r.station.changed {
+            notes.push_str("  ");
+            notes.push_str(tr(Msg::Changed));
+        }
+        if let Some(ref star) = r.station.primary_star {
+            notes.push_str("  ");
+            if star.is_scoopable() {
+                notes.push_str(&trf(Msg::Star, &[&star.class()]));
+            } else {
+                notes.push_str(&trf(Msg::StarUnscoopable, &[&star.class()]));
+            }
+        }
+        if let Some(dump_date) = r.station.dump_date {
+            // Records from a lagging dump may miss newer updates.
+            if Utc::now().signed_duration_since(dump_date).num_days() >= DUMP_LAG_DAYS {
+                let date = dump_date.with_timezone(&Local).format("%F");
+                notes.push_str("  ");
+                notes.push_str(&trf(Msg::DumpDate, &[&date]));
+            }
+        }
+        if let Some(days) = r.visited_days {
+            notes.push_str("  ");
+            notes.push_str(&trf(Msg::Visited, &[&days]));
+        }
+        if r.coverage.visited > 0 {
+            notes.push_str("  ");
+            notes.push_str(&trf(
+                Msg::Coverage,
+                &[&r.coverage.visited, &r.coverage.total],
+            ));
+        }
+
+        let (days, letter) = max_outdated(r);
+        let prefix = format!(
+            "{:>3}{:<2}{:>6.2} Ly + {:>8} Ls  {:>3}d({}) [{}]  ",
+            i + 1,
+            if r.visited { "*" } else { " " },
+            r.distance,
+            si_fmt(r.station.distance_to_arrival),
+            days,
+            letter,
+            outdated,
+        );
+        let name = &r.station.name;
+        let system = &r.station.system_name;
+        let type_name = self.type_name(&r.station.st_type);
+
+        let line = format!(
+            "{}{:<25} {:<12} ({}){}",
+            prefix, name, system, type_name, notes
+        );
+        let width = match self.width() {
+            Some(width) => width,
+            None => return line,
+        };
+        if line.chars().count() <= width {
+            return line;
+        }
+
+        // Drop optional columns.
+        let line = format!("{}{:<25} {:<12} ({})", prefix, name, system, type_name);
+        if line.chars().count() <= width {
+            return line;
+        }
+        let line = format!("{}{:<25} {}", prefix, name, system);
+        if line.chars().count() <= width {
+            return line;
+        }
+
+        // Elide names.
+        let avail = width.saturating_sub(prefix.chars().count() + 1);
+        let name_width = (avail * 2 / 3).max(1);
+        let system_width = avail.saturating_sub(name_width).max(1);
+        format!(
+            "{}{:<w$} {}",
+            prefix,
+            elide(name, name_width),
+            elide(system, system_width),
+            w = name_width
+        )
+    }
+
+    fn render_text(&self, records: &[Record], limit: usize, last_mod: DateTime<Utc>) -> String {
+        let mut s = self.header(records, last_mod);
+        s.push('\n');
+
+        for (i, r) in records.iter().enumerate() {
+            if i == limit {
+                break;
+            }
+            s.push_str(&self.line(i, r));
+            s.push('\n');
+        }
+
+        if let Some(footer) = self.footer(records, limit) {
+            s.push_str(&footer);
+            s.push('\n');
+        }
+
+        let warnings = warnings();
+        if !warnings.is_empty() {
+            s.push_str(tr(Msg::Warnings));
+            s.push('\n');
+            for w in warnings {
+                s.push_str(&format!("  {}\n", w));
+            }
+        }
+
+        s
+    }
+
+    pub fn header(&self, records: &[Record], last_mod: DateTime<Utc>) -> String {
+        let s = last_mod.with_timezone(&Local).format("%F %T %Z");
+        trf(Msg::Header, &[&records.len(), &s])
+    }
+
+    fn footer(&self, records: &[Record], limit: usize) -> Option<String> {
+        let summary = Summary::new(records, limit);
+        let nearest = summary.nearest?;
+        let farthest = summary.farthest?;
+        let median = summary.median_days?;
+        Some(trf(
+            Msg::Footer,
+            &[
+                &summary.shown,
+                &summary.total,
+                &summary.hidden(),
+                &format!("{:.2}", nearest),
+                &format!("{:.2}", farthest),
+                &median,
+            ],
+        ))
+    }
+
+    fn type_name(&self, st_type: &StationType) -> String {
+        if !self.compact {
+            return st_type.to_string();
+        }
+        match self.type_codes.get(st_type) {
+            Some(code) => code.clone(),
+            None => st_type.short().to_owned(),
+        }
     }
 }
 
@@ -20,55 +359,150 @@ impl Printer for TextPrinter {
         limit: usize,
         last_mod: DateTime<Utc>,
     ) -> Result<(), Fail> {
-        let s = last_mod.with_timezone(&Local).format("%F %T %Z");
-        println!("Total {} stations. Last update is {}.", records.len(), s);
+        let text = self.render_text(records, limit, last_mod);
+        self.write_out(&text)
+    }
+
+    fn print_route(
+        &mut self,
+        route: &[Record],
+        cumulative: &[f64],
+        last_mod: DateTime<Utc>,
+    ) -> Result<(), Fail> {
+        let text = self.render_route(route, cumulative, last_mod);
+        self.write_out(&text)
+    }
+
+    fn render(&self, records: &[Record], limit: usize, last_mod: DateTime<Utc>) -> String {
+        self.render_text(records, limit, last_mod)
+    }
+
+    fn print_selection(
+        &mut self,
+        records: &[Record],
+        limit: usize,
+        last_mod: DateTime<Utc>,
+        cursor: usize,
+        selection: &Selection,
+    ) -> Result<(), Fail> {
+        println!("{}", self.header(records, last_mod));
 
         for (i, r) in records.iter().enumerate() {
             if i == limit {
                 break;
             }
-
-            let mut outdated = String::with_capacity(4);
-            outdated.push(if r.information_days.is_outdated() {
-                'I'
-            } else {
-                ' '
-            });
-            outdated.push(if r.market_days.is_outdated() {
-                'M'
-            } else {
-                ' '
-            });
-            outdated.push(if r.shipyard_days.is_outdated() {
-                'S'
-            } else {
-                ' '
-            });
-            outdated.push(if r.outfitting_days.is_outdated() {
-                'O'
-            } else {
-                ' '
-            });
-
             println!(
-                "{:>3}{:<2}{:>6.2} Ly + {:>8} Ls  {:3}d [{}]  {:<25} {:<12} ({})",
-                i + 1,
-                if r.visited { "*" } else { " " },
-                r.distance,
-                si_fmt(r.station.distance_to_arrival),
-                r.outdated().unwrap(),
-                outdated,
-                r.station.name,
-                r.station.system_name,
-                r.station.st_type,
+                "{}{}{}",
+                if i == cursor { ">" } else { " " },
+                if selection.contains(r.station) {
+                    "[x]"
+                } else {
+                    "[ ]"
+                },
+                self.line(i, r)
             );
         }
 
+        if let Some(footer) = self.footer(records, limit) {
+            println!("{}", footer);
+        }
+
         Ok(())
     }
 
     fn clear(&mut self) -> Result<(), Fail> {
+        if self.output.is_some() {
+            return Ok(());
+        }
         println!("\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n");
         Ok(())
     }
 }
+
+#[cfg(feature = "native")]
+fn terminal_width() -> Option<usize> {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, cols)| cols as usize)
+}
+
+#[cfg(not(feature = "native"))]
+fn terminal_width() -> Option<usize> {
+    None
+}
+
+/// Days and category letter of the most outdated category, or dashes if none is outdated, which
+/// happens with a filters file without `outdated` filter.
+fn max_outdated(r: &Record) -> (String, char) {
+    r.outdated().map_or(("-".to_owned(), '-'), |o| {
+        (o.days.to_string(), o.category.letter())
+    })
+}
+
+fn elide(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.to_owned();
+    }
+    let mut elided: String = s.chars().take(width.saturating_sub(1)).collect();
+    elided.push('…');
+    elided
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::coords::Coords;
+    use crate::searcher::{new_record, Coverage};
+    use crate::stations::Station;
+    use crate::visited::Visited;
+
+    fn line_with_dump_date(dump_days: i64) -> String {
+        let mut st = Station::for_test(10, None);
+        st.dump_date = Some((Utc::now() - Duration::days(dump_days)).fixed_offset());
+        let mut r = new_record(
+            &st,
+            Coords::zero(),
+            &Visited::new(),
+            Coverage::default(),
+            Utc::now(),
+        );
+        r.information_days.check(|d| d >= 0);
+
+        let mut printer = TextPrinter::new();
+        printer.set_max_width(Some(1000));
+        printer.line(0, &r)
+    }
+
+    #[test]
+    fn line_without_outdated_category() {
+        let st = Station::for_test(10, None);
+        let r = new_record(
+            &st,
+            Coords::zero(),
+            &Visited::new(),
+            Coverage::default(),
+            Utc::now(),
+        );
+        let line = TextPrinter::new().line(0, &r);
+        assert!(line.contains("  -d(-) "), "{}", line);
+    }
+
+    #[test]
+    fn dump_date_of_lagging_dump() {
+        let date = (Utc::now() - Duration::days(3))
+            .with_timezone(&Local)
+            .format("%F")
+            .to_string();
+        let line = line_with_dump_date(3);
+        assert!(line.contains(&trf(Msg::DumpDate, &[&date])), "{}", line);
+    }
+
+    #[test]
+    fn no_dump_date_of_fresh_dump() {
+        let date = Local::now().format("%F").to_string();
+        let line = line_with_dump_date(0);
+        assert!(!line.contains(&trf(Msg::DumpDate, &[&date])), "{}", line);
+    }
+}
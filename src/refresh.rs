@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tiny_fail::Fail;
+
+use crate::state::StateFile;
+
+/// Format version of refresh history file.
+const VERSION: u32 = 1;
+/// Observed update times kept for each station.
+const MAX_OBSERVATIONS: usize = 16;
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// History of information update times seen in dumps, keyed by market ID.
+///
+/// Saved as `{"stations": {"<market ID>": [<unix time>, ...]}}`, ordered by market ID.
+/// Only times present in loaded dumps are known, so refreshes between two loads are counted as one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefreshHistory {
+    stations: BTreeMap<u64, Vec<i64>>,
+}
+
+impl RefreshHistory {
+    pub fn new() -> RefreshHistory {
+        RefreshHistory::default()
+    }
+
+    /// Load history saved by `save`. Returns empty history if `path` doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<RefreshHistory, Fail> {
+        Ok(StateFile::new(path, "refresh history", VERSION)
+            .load()?
+            .unwrap_or_default())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Fail> {
+        StateFile::new(path, "refresh history", VERSION).save(self)
+    }
+
+    /// Record update time. Returns `true` if it is new.
+    pub fn observe(&mut self, id: u64, time: DateTime<Utc>) -> bool {
+        let time = time.timestamp();
+        let times = self.stations.entry(id).or_default();
+        if times.contains(&time) {
+            return false;
+        }
+
+        times.push(time);
+        times.sort();
+        if times.len() > MAX_OBSERVATIONS {
+            let excess = times.len() - MAX_OBSERVATIONS;
+            times.drain(..excess);
+        }
+        true
+    }
+
+    /// Median days between observed updates, `None` if never observed.
+    ///
+    /// Days from the last update until `now` are counted as an interval too, so that stations no
+    /// longer updated become neglected, and stations observed only once have days since the update.
+    pub fn median_interval(&self, id: u64, now: DateTime<Utc>) -> Option<i64> {
+        let times = self.stations.get(&id)?;
+        let last = *times.last()?;
+        let mut intervals: Vec<i64> = times
+            .windows(2)
+            .map(|w| (w[1] - w[0]) / SECS_PER_DAY)
+            .collect();
+        intervals.push((now.timestamp() - last).max(0) / SECS_PER_DAY);
+
+        intervals.sort();
+        let mid = intervals.len() / 2;
+        if intervals.len().is_multiple_of(2) {
+            Some((intervals[mid - 1] + intervals[mid]) / 2)
+        } else {
+            Some(intervals[mid])
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn median_interval_includes_open_interval() {
+        let start = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        let day = |n: i64| start + Duration::days(n);
+        let mut history = RefreshHistory::new();
+        assert_eq!(history.median_interval(1, day(0)), None);
+
+        assert!(history.observe(1, day(0)));
+        assert!(!history.observe(1, day(0)));
+        assert_eq!(history.median_interval(1, day(0)), Some(0));
+        assert_eq!(history.median_interval(1, day(40)), Some(40));
+
+        assert!(history.observe(1, day(2)));
+        assert!(history.observe(1, day(4)));
+        // Intervals 2, 2 and 1.
+        assert_eq!(history.median_interval(1, day(5)), Some(2));
+        // Intervals 2, 2 and 100.
+        assert_eq!(history.median_interval(1, day(104)), Some(2));
+        assert!(history.observe(1, day(5)));
+        // Intervals 2, 2, 1 and 100.
+        assert_eq!(history.median_interval(1, day(105)), Some(2));
+        // Intervals 2, 2, 1 and 0, for clock behind dump.
+        assert_eq!(history.median_interval(1, day(0)), Some(1));
+    }
+}
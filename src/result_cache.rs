@@ -0,0 +1,360 @@
+use std::fs::{rename, File};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_reader, to_string, to_writer};
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::coords::Coords;
+use crate::journal::Location;
+use crate::marks::Marks;
+use crate::searcher::{Coverage, Days, Record};
+use crate::stations::{Economy, LandingPads, PrimaryStar, Station, StationType, UpdateTime};
+use crate::visited::Visited;
+
+/// Records of the last search, reused while all inputs are unchanged.
+///
+/// Records are kept instead of rendered text, so that they are rendered with current time and hooks
+/// can run for them.
+#[derive(Debug)]
+pub struct ResultCache {
+    path: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    last_mod: i64,
+    records: Vec<CachedRecord>,
+}
+
+/// Records read from the cache, with the stations they refer.
+#[derive(Debug)]
+pub struct CachedResult {
+    last_mod: DateTime<Utc>,
+    records: Vec<(Station, RecordFields)>,
+}
+
+impl CachedResult {
+    /// Last modified time of the dump the records were searched in.
+    pub fn last_mod(&self) -> DateTime<Utc> {
+        self.last_mod
+    }
+
+    pub fn records(&self) -> Vec<Record<'_>> {
+        self.records
+            .iter()
+            .map(|(station, f)| Record {
+                station,
+                distance: f.distance,
+                visited: f.visited,
+                visited_days: f.visited_days,
+                information_days: f.information_days.clone(),
+                market_days: f.market_days.clone(),
+                shipyard_days: f.shipyard_days.clone(),
+                outfitting_days: f.outfitting_days.clone(),
+                coverage: f.coverage,
+                score: f64::from_bits(f.score_bits),
+            })
+            .collect()
+    }
+}
+
+impl ResultCache {
+    pub fn new<P: AsRef<Path>>(path: P) -> ResultCache {
+        ResultCache {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    /// Cached records for `key`. Unreadable cache is treated as a miss.
+    pub fn get(&self, key: &str) -> Option<CachedResult> {
+        let f = File::open(&self.path).ok()?;
+        let entry: Entry = from_reader(f).ok()?;
+        if entry.key != key {
+            return None;
+        }
+
+        Some(CachedResult {
+            last_mod: Utc.timestamp_opt(entry.last_mod, 0).single()?,
+            records: entry
+                .records
+                .into_iter()
+                .map(|r| Some((r.station.into_station()?, r.fields)))
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    pub fn save(&self, key: &str, records: &[Record], last_mod: DateTime<Utc>) -> Result<(), Fail> {
+        let tmp = self.path.with_extension("tmp");
+        let f = File::create(&tmp).err_msg(format!("can't create file: {:?}", tmp))?;
+        let entry = Entry {
+            key: key.to_owned(),
+            last_mod: last_mod.timestamp(),
+            records: records.iter().map(CachedRecord::new).collect(),
+        };
+        to_writer(f, &entry).err_msg("can't encode result cache")?;
+        rename(&tmp, &self.path).err_msg(format!("can't write file: {:?}", self.path))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedRecord {
+    station: CachedStation,
+    fields: RecordFields,
+}
+
+impl CachedRecord {
+    fn new(r: &Record) -> CachedRecord {
+        CachedRecord {
+            station: CachedStation::new(r.station),
+            fields: RecordFields {
+                distance: r.distance,
+                visited: r.visited,
+                visited_days: r.visited_days,
+                information_days: r.information_days.clone(),
+                market_days: r.market_days.clone(),
+                shipyard_days: r.shipyard_days.clone(),
+                outfitting_days: r.outfitting_days.clone(),
+                coverage: r.coverage,
+                score_bits: r.score.to_bits(),
+            },
+        }
+    }
+}
+
+/// Fields of `Record` other than the station.
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordFields {
+    distance: f64,
+    visited: bool,
+    visited_days: Option<i64>,
+    information_days: Days,
+    market_days: Days,
+    shipyard_days: Days,
+    outfitting_days: Days,
+    coverage: Coverage,
+    /// Score may be infinite, which JSON can't have.
+    score_bits: u64,
+}
+
+/// All fields of `Station`, including ones joined after parsing dumps. Times are UNIX seconds.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedStation {
+    coords: Coords,
+    changed: bool,
+    distance_to_arrival: Option<f64>,
+    dump_date: Option<i64>,
+    economy: Option<Economy>,
+    government: Option<String>,
+    have_market: Option<bool>,
+    have_outfitting: Option<bool>,
+    have_shipyard: Option<bool>,
+    landing_pads: Option<LandingPads>,
+    market_id: Option<u64>,
+    name: String,
+    power: Option<String>,
+    primary_star: Option<PrimaryStar>,
+    refresh_interval: Option<i64>,
+    second_economy: Option<Economy>,
+    security: Option<String>,
+    state: Option<String>,
+    st_type: StationType,
+    system_id: u64,
+    system_name: String,
+    information: i64,
+    market: Option<i64>,
+    shipyard: Option<i64>,
+    outfitting: Option<i64>,
+}
+
+impl CachedStation {
+    fn new(st: &Station) -> CachedStation {
+        let ut = st.update_time();
+        CachedStation {
+            coords: st.coords,
+            changed: st.changed,
+            distance_to_arrival: st.distance_to_arrival,
+            dump_date: st.dump_date.map(|t| t.timestamp()),
+            economy: st.economy,
+            government: st.government.clone(),
+            have_market: st.have_market,
+            have_outfitting: st.have_outfitting,
+            have_shipyard: st.have_shipyard,
+            landing_pads: st.landing_pads,
+            market_id: st.market_id,
+            name: st.name.clone(),
+            power: st.power.clone(),
+            primary_star: st.primary_star.clone(),
+            refresh_interval: st.refresh_interval,
+            second_economy: st.second_economy,
+            security: st.security.clone(),
+            state: st.state.clone(),
+            st_type: st.st_type.clone(),
+            system_id: st.system_id,
+            system_name: st.system_name.clone(),
+            information: ut.information().timestamp(),
+            market: ut.market().map(|t| t.timestamp()),
+            shipyard: ut.shipyard().map(|t| t.timestamp()),
+            outfitting: ut.outfitting().map(|t| t.timestamp()),
+        }
+    }
+
+    fn into_station(self) -> Option<Station> {
+        let time = |secs: i64| Utc.timestamp_opt(secs, 0).single();
+        let opt_time = |secs: Option<i64>| secs.map(time).map_or(Some(None), |t| t.map(Some));
+        Some(Station {
+            coords: self.coords,
+            changed: self.changed,
+            distance_to_arrival: self.distance_to_arrival,
+            dump_date: match self.dump_date {
+                Some(secs) => Some(time(secs)?.fixed_offset()),
+                None => None,
+            },
+            economy: self.economy,
+            government: self.government,
+            have_market: self.have_market,
+            have_outfitting: self.have_outfitting,
+            have_shipyard: self.have_shipyard,
+            landing_pads: self.landing_pads,
+            market_id: self.market_id,
+            name: self.name,
+            power: self.power,
+            primary_star: self.primary_star,
+            refresh_interval: self.refresh_interval,
+            second_economy: self.second_economy,
+            security: self.security,
+            state: self.state,
+            st_type: self.st_type,
+            system_id: self.system_id,
+            system_name: self.system_name,
+            update_time: UpdateTime::new(
+                time(self.information)?,
+                opt_time(self.market)?,
+                opt_time(self.shipyard)?,
+                opt_time(self.outfitting)?,
+            ),
+        })
+    }
+}
+
+/// 64-bit FNV-1a hasher, whose results are stable across builds unlike `DefaultHasher`.
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    pub fn new() -> StableHasher {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> StableHasher {
+        StableHasher::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Key of search inputs: origin, visited stations, marks, dump versions and config.
+pub fn cache_key(
+    location: &Location,
+    visited: &Visited,
+    marks: &Marks,
+    dump_version: &str,
+    config_fingerprint: u64,
+) -> Result<String, Fail> {
+    let mut hasher = StableHasher::new();
+    location.star_system.hash(&mut hasher);
+    format!("{:?}", location.star_pos).hash(&mut hasher);
+    to_string(visited)
+        .err_msg("can't encode visited stations")?
+        .hash(&mut hasher);
+    to_string(marks)
+        .err_msg("can't encode marks")?
+        .hash(&mut hasher);
+    dump_version.hash(&mut hasher);
+    config_fingerprint.hash(&mut hasher);
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::process;
+
+    use super::*;
+    use crate::searcher::new_record;
+
+    #[test]
+    fn stable_hasher_is_fnv1a() {
+        let hash = |bytes: &[u8]| {
+            let mut h = StableHasher::new();
+            h.write(bytes);
+            h.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let mut st = Station::for_test(100, Some(20));
+        st.coords = Coords::new(1.5, -2.0, 3.25);
+        st.changed = true;
+        st.refresh_interval = Some(30);
+        st.dump_date = Some(Utc.timestamp_opt(1_600_000_000, 0).unwrap().fixed_offset());
+        let mut r = new_record(
+            &st,
+            Coords::zero(),
+            &Visited::new(),
+            Coverage {
+                visited: 1,
+                total: 3,
+            },
+            Utc::now(),
+        );
+        r.information_days.check_threshold(30);
+        r.score = f64::INFINITY;
+
+        let path = temp_dir().join(format!("near-old-stations-result-{}.json", process::id()));
+        let cache = ResultCache::new(&path);
+        let last_mod = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        cache.save("key", &[r], last_mod).unwrap();
+
+        assert!(cache.get("other key").is_none());
+        let cached = cache.get("key").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(cached.last_mod(), last_mod);
+        let records = cached.records();
+        assert_eq!(records.len(), 1);
+        let (got, want) = (&records[0], &st);
+        assert_eq!(got.station, want);
+        assert_eq!(
+            got.coverage,
+            Coverage {
+                visited: 1,
+                total: 3
+            }
+        );
+        assert_eq!(got.score, f64::INFINITY);
+        assert_eq!(got.information_days.threshold(), Some(30));
+        assert_eq!(got.outdated().map(|o| o.days), Some(100));
+    }
+}
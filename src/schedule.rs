@@ -0,0 +1,365 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
+use reqwest::blocking::Client;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+/// Run times given as `"HH:MM"` (every day) or 5-field cron expression (`"min hour day month weekday"`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+}
+
+impl Schedule {
+    pub fn parse(s: &str) -> Result<Schedule, Fail> {
+        let s = s.trim();
+
+        if let Some(pos) = s.find(':') {
+            let hour = s[..pos]
+                .parse::<u32>()
+                .err_msg(format!("invalid hour in {:?}", s))?;
+            let minute = s[pos + 1..]
+                .parse::<u32>()
+                .err_msg(format!("invalid minute in {:?}", s))?;
+            if hour > 23 || minute > 59 {
+                return Err(Fail::new(format!("invalid time: {:?}", s)));
+            }
+            return Ok(Schedule {
+                minutes: Field::single(minute, 0, 59),
+                hours: Field::single(hour, 0, 23),
+                days: Field::any(1, 31),
+                months: Field::any(1, 12),
+                weekdays: Field::any(0, 6),
+            });
+        }
+
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(Fail::new(format!(
+                "schedule must be \"HH:MM\" or 5-field cron expression: {:?}",
+                s
+            )));
+        }
+
+        Ok(Schedule {
+            minutes: Field::parse(fields[0], 0, 59)?,
+            hours: Field::parse(fields[1], 0, 23)?,
+            days: Field::parse(fields[2], 1, 31)?,
+            months: Field::parse(fields[3], 1, 12)?,
+            weekdays: Field::parse(fields[4], 0, 7)?.fold_sunday(),
+        })
+    }
+
+    /// First run time strictly after `t`.
+    ///
+    /// Fields are advanced from month to minute, so that each step skips whole unmatched periods.
+    pub fn next_after(&self, t: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut n = t.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+
+        // Any valid schedule matches within 4 years (Feb 29).
+        let limit = n + Duration::days(4 * 366);
+        while n < limit {
+            if !self.months.contains(n.month()) {
+                let (year, month) = if n.month() == 12 {
+                    (n.year() + 1, 1)
+                } else {
+                    (n.year(), n.month() + 1)
+                };
+                n = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.day_matches(n.date()) {
+                n = n.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            match self.hours.next(n.hour()) {
+                Some(h) if h == n.hour() => {}
+                Some(h) => {
+                    n = n.date().and_hms_opt(h, 0, 0)?;
+                    continue;
+                }
+                None => {
+                    n = n.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                    continue;
+                }
+            }
+            match self.minutes.next(n.minute()) {
+                Some(m) if m == n.minute() => {}
+                Some(m) => {
+                    n = n.date().and_hms_opt(n.hour(), m, 0)?;
+                    continue;
+                }
+                None => {
+                    n = n.date().and_hms_opt(n.hour(), 0, 0)? + Duration::hours(1);
+                    continue;
+                }
+            }
+
+            // Skip times in DST gap, and repeated times before `t`.
+            match Local.from_local_datetime(&n).earliest() {
+                Some(local) if local > t => return Some(local),
+                _ => n += Duration::minutes(1),
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days.contains(date.day());
+        let weekday = self
+            .weekdays
+            .contains(date.weekday().num_days_from_sunday());
+        // Like cron, if both day and weekday are restricted, either one matches.
+        match (self.days.is_any(), self.weekdays.is_any()) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    min: u32,
+    allowed: Vec<bool>,
+    any: bool,
+}
+
+impl Field {
+    fn any(min: u32, max: u32) -> Field {
+        Field {
+            min,
+            allowed: vec![true; (max - min + 1) as usize],
+            any: true,
+        }
+    }
+
+    fn single(v: u32, min: u32, max: u32) -> Field {
+        let mut allowed = vec![false; (max - min + 1) as usize];
+        allowed[(v - min) as usize] = true;
+        Field {
+            min,
+            allowed,
+            any: false,
+        }
+    }
+
+    fn parse(s: &str, min: u32, max: u32) -> Result<Field, Fail> {
+        if s == "*" {
+            return Ok(Field::any(min, max));
+        }
+
+        let mut allowed = vec![false; (max - min + 1) as usize];
+        for part in s.split(',') {
+            let (range, step) = match part.find('/') {
+                Some(pos) => (
+                    &part[..pos],
+                    part[pos + 1..]
+                        .parse::<u32>()
+                        .err_msg(format!("invalid step: {:?}", part))?,
+                ),
+                None => (part, 1),
+            };
+            let (from, to) = if range == "*" {
+                (min, max)
+            } else if let Some(pos) = range.find('-') {
+                (parse_value(&range[..pos])?, parse_value(&range[pos + 1..])?)
+            } else {
+                let v = parse_value(range)?;
+                (v, v)
+            };
+            if from < min || to > max || from > to || step == 0 {
+                return Err(Fail::new(format!("out of range: {:?}", part)));
+            }
+
+            let mut v = from;
+            while v <= to {
+                allowed[(v - min) as usize] = true;
+                v += step;
+            }
+        }
+
+        Ok(Field {
+            min,
+            allowed,
+            any: false,
+        })
+    }
+
+    /// Weekday field parsed in 0-7, with both 0 and 7 for Sunday.
+    fn fold_sunday(mut self) -> Field {
+        if self.allowed.len() == 8 {
+            let sunday = self.allowed.pop().unwrap_or(false);
+            self.allowed[0] |= sunday;
+        }
+        self
+    }
+
+    /// Smallest allowed value not less than `v`.
+    fn next(&self, v: u32) -> Option<u32> {
+        (v.max(self.min)..self.min + self.allowed.len() as u32).find(|&v| self.contains(v))
+    }
+
+    fn contains(&self, v: u32) -> bool {
+        v >= self.min
+            && self
+                .allowed
+                .get((v - self.min) as usize)
+                .cloned()
+                .unwrap_or(false)
+    }
+
+    fn is_any(&self) -> bool {
+        self.any
+    }
+}
+
+fn parse_value(s: &str) -> Result<u32, Fail> {
+    s.parse::<u32>()
+        .err_msg(format!("invalid schedule value: {:?}", s))
+}
+
+/// Destination of scheduled reports. Without file and webhook, report is printed to stdout.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    pub file: Option<PathBuf>,
+    pub webhook: Option<String>,
+}
+
+impl Report {
+    pub fn emit(&self, text: &str) -> Result<(), Fail> {
+        if let Some(ref path) = self.file {
+            let mut f = File::create(path).err_msg(format!("can't create file: {:?}", path))?;
+            f.write_all(text.as_bytes())
+                .err_msg("failed to write report")?;
+        }
+
+        if let Some(ref url) = self.webhook {
+            Client::new()
+                .post(url)
+                .body(text.to_owned())
+                .send()
+                .and_then(|res| res.error_for_status())
+                .err_msg(format!("failed to post report to {}", url))?;
+        }
+
+        if self.file.is_none() && self.webhook.is_none() {
+            print!("{}", text);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32, sec: u32) -> DateTime<Local> {
+        Local
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(y, m, d)
+                    .unwrap()
+                    .and_hms_opt(h, min, sec)
+                    .unwrap(),
+            )
+            .unwrap()
+    }
+
+    fn next(schedule: &str, t: DateTime<Local>) -> Option<DateTime<Local>> {
+        Schedule::parse(schedule).unwrap().next_after(t)
+    }
+
+    #[test]
+    fn parse_time_of_day() {
+        assert_eq!(
+            Schedule::parse("07:30").unwrap(),
+            Schedule::parse("30 7 * * *").unwrap()
+        );
+        assert!(Schedule::parse("24:00").is_err());
+        assert!(Schedule::parse("12:60").is_err());
+        assert!(Schedule::parse("noon").is_err());
+    }
+
+    #[test]
+    fn parse_cron_fields() {
+        assert!(Schedule::parse("0,15,30,45 */2 1-10 1,7 1-5").is_ok());
+        assert!(Schedule::parse("60 * * * *").is_err());
+        assert!(Schedule::parse("* 24 * * *").is_err());
+        assert!(Schedule::parse("* * 0 * *").is_err());
+        assert!(Schedule::parse("* * * 13 *").is_err());
+        assert!(Schedule::parse("* * * * 8").is_err());
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+        assert!(Schedule::parse("5-1 * * * *").is_err());
+        assert!(Schedule::parse("* * * *").is_err());
+    }
+
+    #[test]
+    fn parse_sunday_as_7() {
+        assert_eq!(
+            Schedule::parse("0 0 * * 7").unwrap(),
+            Schedule::parse("0 0 * * 0").unwrap()
+        );
+        assert_eq!(
+            Schedule::parse("0 0 * * 1-7").unwrap(),
+            Schedule::parse("0 0 * * 0-6").unwrap()
+        );
+        // Only weekday 7 means Sunday, not the digit in other values.
+        assert_eq!(
+            Schedule::parse("17 7 * * 7").unwrap(),
+            Schedule::parse("17 7 * * 0").unwrap()
+        );
+        assert!(Schedule::parse("0 0 * * 17").is_err());
+        assert!(Schedule::parse("0 0 * * */7").is_ok());
+    }
+
+    #[test]
+    fn next_after_is_strictly_after() {
+        let t = local(2024, 1, 10, 7, 30, 0);
+        assert_eq!(next("07:30", t), Some(local(2024, 1, 11, 7, 30, 0)));
+        let t = local(2024, 1, 10, 7, 29, 59);
+        assert_eq!(next("07:30", t), Some(local(2024, 1, 10, 7, 30, 0)));
+    }
+
+    #[test]
+    fn next_after_steps() {
+        let t = local(2024, 1, 10, 10, 7, 30);
+        assert_eq!(next("*/15 * * * *", t), Some(local(2024, 1, 10, 10, 15, 0)));
+        assert_eq!(next("5 * * * *", t), Some(local(2024, 1, 10, 11, 5, 0)));
+        assert_eq!(next("0 9 * * *", t), Some(local(2024, 1, 11, 9, 0, 0)));
+        // 2024-01-10 is Wednesday.
+        assert_eq!(next("0 9 * * 1", t), Some(local(2024, 1, 15, 9, 0, 0)));
+        assert_eq!(next("0 9 * * 7", t), Some(local(2024, 1, 14, 9, 0, 0)));
+        assert_eq!(next("0 0 1 * *", t), Some(local(2024, 2, 1, 0, 0, 0)));
+        assert_eq!(next("0 0 1 1 *", t), Some(local(2025, 1, 1, 0, 0, 0)));
+        assert_eq!(
+            next("59 23 31 12 *", t),
+            Some(local(2024, 12, 31, 23, 59, 0))
+        );
+    }
+
+    #[test]
+    fn next_after_day_or_weekday() {
+        // Either day of month or weekday matches, like cron.
+        let t = local(2024, 1, 2, 0, 0, 0);
+        assert_eq!(next("0 12 1 * 0", t), Some(local(2024, 1, 7, 12, 0, 0)));
+        let t = local(2024, 1, 28, 13, 0, 0);
+        assert_eq!(next("0 12 1 * 3", t), Some(local(2024, 1, 31, 12, 0, 0)));
+        assert_eq!(next("0 12 1 * 5", t), Some(local(2024, 2, 1, 12, 0, 0)));
+    }
+
+    #[test]
+    fn next_after_rare_and_impossible_days() {
+        let t = local(2024, 3, 1, 0, 0, 0);
+        assert_eq!(next("0 0 29 2 *", t), Some(local(2028, 2, 29, 0, 0, 0)));
+        assert_eq!(next("0 0 31 2 *", t), None);
+        assert_eq!(next("0 0 31 4,6,9,11 *", t), None);
+    }
+}
@@ -0,0 +1,19 @@
+use schemars::schema::RootSchema;
+
+/// JSON Schemas of all JSON outputs, with names to save.
+///
+/// - `activity`: each line of `activity_log`
+/// - `status`: status file of daemon mode
+/// - `batch`: `--origins-from` output with `--format json`
+/// - `search`: each line of search output with `--format json`
+/// - `serve-records`: `/records.json` responses of serve mode
+pub fn schemas() -> Vec<(&'static str, RootSchema)> {
+    vec![
+        ("activity", crate::activity::schema()),
+        ("status", crate::daemon::schema()),
+        ("batch", crate::batch::schema()),
+        ("search", crate::printer::json::schema()),
+        // Serve mode responds the same document as search output.
+        ("serve-records", crate::printer::json::schema()),
+    ]
+}
@@ -1,61 +1,134 @@
-use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-use crate::journal::{Location, Visited};
-use crate::stations::{Station, Stations};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::coords::Coords;
+use crate::journal::Location;
+use crate::spatial::SpatialIndex;
+use crate::stations::{Economy, Station, Stations};
+use crate::travel::{StraightLine, TravelCost};
+use crate::visited::Visited;
 
 pub struct Searcher<F> {
     stations: Stations,
+    index: SpatialIndex,
     filter: F,
+    transform: DaysTransform,
+    travel: Arc<dyn TravelCost + Send + Sync>,
+    boost: Option<EconomyBoost>,
 }
 
 impl<F: Filter> Searcher<F> {
     pub fn new(stations: Stations, filter: F) -> Searcher<F> {
-        Searcher { stations, filter }
+        let index = SpatialIndex::new(stations.stations().map(|st| st.coords));
+        Searcher {
+            stations,
+            index,
+            filter,
+            transform: DaysTransform::Linear,
+            travel: Arc::new(StraightLine::default()),
+            boost: None,
+        }
+    }
+
+    pub fn set_days_transform(&mut self, transform: DaysTransform) {
+        self.transform = transform;
+    }
+
+    /// Travel time estimate that outdated days are divided by in score.
+    pub fn set_travel_cost(&mut self, travel: Arc<dyn TravelCost + Send + Sync>) {
+        self.travel = travel;
+    }
+
+    pub fn set_economy_boost(&mut self, boost: Option<EconomyBoost>) {
+        self.boost = boost;
     }
 
-    pub fn search(&self, loc: &Location, visited: &Visited) -> Vec<Record> {
+    pub fn stations(&self) -> &Stations {
+        &self.stations
+    }
+
+    pub fn search(&self, loc: &Location, visited: &Visited) -> Vec<Record<'_>> {
+        self.search_at(loc.star_pos, visited)
+    }
+
+    /// All stations in the current system regardless of filter, ordered by distance to arrival.
+    ///
+    /// Records are not checked as outdated.
+    pub fn search_system(&self, loc: &Location, visited: &Visited) -> Vec<Record<'_>> {
         let now = Utc::now();
+        let stations: Vec<&Station> = self
+            .stations
+            .stations()
+            .filter(|st| st.system_name.eq_ignore_ascii_case(&loc.star_system))
+            .collect();
+        let coverage = Coverage {
+            visited: stations
+                .iter()
+                .filter(|st| st.market_id.is_some_and(|id| visited.is_visited(id)))
+                .count(),
+            total: stations.len(),
+        };
 
-        let mut records = Vec::new();
+        let mut records: Vec<Record> = stations
+            .into_iter()
+            .map(|st| new_record(st, loc.star_pos, visited, coverage, now))
+            .collect();
+        records.sort_by(|l, r| {
+            let l = l.station.distance_to_arrival.unwrap_or(f64::MAX);
+            let r = r.station.distance_to_arrival.unwrap_or(f64::MAX);
+            l.partial_cmp(&r).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        records
+    }
+
+    /// Up to `count` stations nearest to `origin` regardless of filter, nearest first.
+    ///
+    /// Records are not checked as outdated and have no coverage.
+    pub fn nearest(&self, origin: Coords, visited: &Visited, count: usize) -> Vec<Record<'_>> {
+        let now = Utc::now();
+        self.index
+            .nearest(origin, count)
+            .into_iter()
+            .filter_map(|(i, _)| self.stations.get(i))
+            .map(|st| new_record(st, origin, visited, Coverage::default(), now))
+            .collect()
+    }
+
+    /// Search around arbitrary coordinates, without journal location.
+    pub fn search_at(&self, origin: Coords, visited: &Visited) -> Vec<Record<'_>> {
+        self.search_at_time(origin, visited, Utc::now())
+    }
+
+    /// Like `search_at`, but days are counted until `now`.
+    ///
+    /// Needed where system clock is unavailable, such as WebAssembly.
+    pub fn search_at_time(
+        &self,
+        origin: Coords,
+        visited: &Visited,
+        now: DateTime<Utc>,
+    ) -> Vec<Record<'_>> {
+        let mut coverage = HashMap::<u64, Coverage>::new();
         for station in self.stations.stations() {
-            let distance = loc.star_pos.dist_to(station.coords);
-            let visited = station
-                .market_id
-                .map(|id| visited.is_visited(id))
-                .unwrap_or(false);
-
-            let update_time = station.update_time();
-            let information_days = Days::new(
-                now.signed_duration_since(update_time.information())
-                    .num_days(),
-            );
-            let market_days = if let Some(t) = update_time.market() {
-                Days::new(now.signed_duration_since(t).num_days())
-            } else {
-                Days::empty()
-            };
-            let shipyard_days = if let Some(t) = update_time.shipyard() {
-                Days::new(now.signed_duration_since(t).num_days())
-            } else {
-                Days::empty()
-            };
-            let outfitting_days = if let Some(t) = update_time.outfitting() {
-                Days::new(now.signed_duration_since(t).num_days())
-            } else {
-                Days::empty()
-            };
-
-            let mut record = Record {
-                station,
-                distance,
-                visited,
-                information_days,
-                market_days,
-                shipyard_days,
-                outfitting_days,
-            };
+            let c = coverage.entry(station.system_id).or_default();
+            c.total += 1;
+            if station.market_id.is_some_and(|id| visited.is_visited(id)) {
+                c.visited += 1;
+            }
+        }
 
+        let mut records = Vec::new();
+        for station in self.stations.stations() {
+            let mut record =
+                new_record(station, origin, visited, coverage[&station.system_id], now);
             if self.filter.filter(&mut record) {
+                record.score = record.calc_score(self.transform, &*self.travel);
+                if let Some(ref boost) = self.boost {
+                    record.score *= boost.factor_of(station);
+                }
                 records.push(record);
             }
         }
@@ -65,59 +138,183 @@ impl<F: Filter> Searcher<F> {
     }
 }
 
+pub(crate) fn new_record<'a>(
+    station: &'a Station,
+    origin: Coords,
+    visited: &Visited,
+    coverage: Coverage,
+    now: DateTime<Utc>,
+) -> Record<'a> {
+    let distance = origin.dist_to(station.coords);
+    let visited_days = station
+        .market_id
+        .and_then(|id| visited.last_visit(id))
+        .map(|t| now.signed_duration_since(t).num_days());
+
+    let update_time = station.update_time();
+    let information_days = Days::new(
+        now.signed_duration_since(update_time.information())
+            .num_days(),
+    );
+    let market_days = if let Some(t) = update_time.market() {
+        Days::new(now.signed_duration_since(t).num_days())
+    } else {
+        Days::empty()
+    };
+    let shipyard_days = if let Some(t) = update_time.shipyard() {
+        Days::new(now.signed_duration_since(t).num_days())
+    } else {
+        Days::empty()
+    };
+    let outfitting_days = if let Some(t) = update_time.outfitting() {
+        Days::new(now.signed_duration_since(t).num_days())
+    } else {
+        Days::empty()
+    };
+
+    Record {
+        station,
+        distance,
+        visited: visited_days.is_some(),
+        visited_days,
+        information_days,
+        market_days,
+        shipyard_days,
+        outfitting_days,
+        coverage,
+        score: 0.0,
+    }
+}
+
+/// Higher score of stations in targeted economies, which keeps others unlike economy filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EconomyBoost {
+    pub economies: HashSet<Economy>,
+    /// Score multiplier of matching stations.
+    pub factor: f64,
+    pub include_secondary: bool,
+}
+
+impl EconomyBoost {
+    fn factor_of(&self, station: &Station) -> f64 {
+        let matches = |e: Option<Economy>| e.is_some_and(|e| self.economies.contains(&e));
+        if matches(station.economy) || (self.include_secondary && matches(station.second_economy)) {
+            self.factor
+        } else {
+            1.0
+        }
+    }
+}
+
 pub trait Filter {
     fn filter(&self, record: &mut Record) -> bool;
 }
 
+/// Weights to pick one of `records` randomly by score.
+///
+/// Stations without travel cost have infinite score, and take all the weight. Negative and NaN scores get none.
+pub fn roll_weights(records: &[Record]) -> Vec<f64> {
+    let infinite = records.iter().any(|r| r.score == f64::INFINITY);
+    records
+        .iter()
+        .map(|r| match r.score {
+            f64::INFINITY => 1.0,
+            _ if infinite => 0.0,
+            s if s.is_finite() && s > 0.0 => s,
+            _ => 0.0,
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct Record<'a> {
     pub station: &'a Station,
     pub distance: f64,
     pub visited: bool,
+    pub visited_days: Option<i64>,
     pub information_days: Days,
     pub market_days: Days,
     pub shipyard_days: Days,
     pub outfitting_days: Days,
+    /// Visited stations in the station's system.
+    pub coverage: Coverage,
+    pub(crate) score: f64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Coverage {
+    pub visited: usize,
+    pub total: usize,
 }
 
 impl<'a> Record<'a> {
-    fn score(&self) -> f64 {
-        if let Some(days) = self.outdated() {
-            let dist =
-                self.distance + 0.000_000_1 * self.station.distance_to_arrival.unwrap_or(0.0);
-            (days as f64) / dist
+    /// Outdated days per second of estimated travel time, used for ordering.
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    fn calc_score(&self, transform: DaysTransform, travel: &dyn TravelCost) -> f64 {
+        if let Some(Outdated { days, .. }) = self.outdated() {
+            let cost = travel.cost(
+                self.distance,
+                self.station.distance_to_arrival.unwrap_or(0.0),
+            );
+            transform.apply(days) / cost
         } else {
             0.0
         }
     }
 
-    pub fn outdated(&self) -> Option<i64> {
-        let mut max = i64::min_value();
+    /// Maximum outdated days and the category which has it.
+    pub fn outdated(&self) -> Option<Outdated> {
+        let mut max = Option::<Outdated>::None;
 
-        if let Some(v) = self.information_days.outdated() {
-            max = max.max(v);
-        }
-        if let Some(v) = self.market_days.outdated() {
-            max = max.max(v);
-        }
-        if let Some(v) = self.shipyard_days.outdated() {
-            max = max.max(v);
-        }
-        if let Some(v) = self.outfitting_days.outdated() {
-            max = max.max(v);
+        let categories = [
+            (Category::Information, &self.information_days),
+            (Category::Market, &self.market_days),
+            (Category::Shipyard, &self.shipyard_days),
+            (Category::Outfitting, &self.outfitting_days),
+        ];
+        for &(category, days) in &categories {
+            if let Some(v) = days.outdated() {
+                if max.is_none_or(|m| m.days < v) {
+                    max = Some(Outdated { days: v, category });
+                }
+            }
         }
 
-        if max > i64::min_value() {
-            Some(max)
-        } else {
-            None
+        max
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outdated {
+    pub days: i64,
+    pub category: Category,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Information,
+    Market,
+    Shipyard,
+    Outfitting,
+}
+
+impl Category {
+    pub fn letter(self) -> char {
+        match self {
+            Category::Information => 'I',
+            Category::Market => 'M',
+            Category::Shipyard => 'S',
+            Category::Outfitting => 'O',
         }
     }
 }
 
 impl<'a> PartialEq for Record<'a> {
     fn eq(&self, other: &Record) -> bool {
-        self.score() == other.score()
+        self.cmp(other) == std::cmp::Ordering::Equal
     }
 }
 
@@ -125,20 +322,52 @@ impl<'a> Eq for Record<'a> {}
 
 impl<'a> PartialOrd for Record<'a> {
     fn partial_cmp(&self, other: &Record) -> Option<std::cmp::Ordering> {
-        self.score().partial_cmp(&other.score())
+        Some(self.cmp(other))
     }
 }
 
 impl<'a> Ord for Record<'a> {
     fn cmp(&self, other: &Record) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        // NaN score is lower than any other, so that sort gets a total order.
+        match (self.score().is_nan(), other.score().is_nan()) {
+            (true, true) => std::cmp::Ordering::Equal,
+            (true, false) => std::cmp::Ordering::Less,
+            (false, true) => std::cmp::Ordering::Greater,
+            (false, false) => self
+                .score()
+                .partial_cmp(&other.score())
+                .unwrap_or(std::cmp::Ordering::Equal),
+        }
     }
 }
 
-#[derive(Debug)]
+/// Transform of outdated days before dividing by travel time in scoring.
+///
+/// With `Log` or `Cap`, very old stations don't dominate the ranking over moderately old nearby ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DaysTransform {
+    Linear,
+    /// `ln(1 + days)`
+    Log,
+    /// `min(days, cap)`
+    Cap(i64),
+}
+
+impl DaysTransform {
+    pub fn apply(self, days: i64) -> f64 {
+        match self {
+            DaysTransform::Linear => days as f64,
+            DaysTransform::Log => (days.max(0) as f64).ln_1p(),
+            DaysTransform::Cap(cap) => days.min(cap) as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Days {
     days: Option<i64>,
     outdated: Option<i64>,
+    threshold: Option<i64>,
 }
 
 impl Days {
@@ -146,6 +375,7 @@ impl Days {
         Days {
             days: Some(days),
             outdated: None,
+            threshold: None,
         }
     }
 
@@ -153,6 +383,7 @@ impl Days {
         Days {
             days: None,
             outdated: None,
+            threshold: None,
         }
     }
 
@@ -164,11 +395,184 @@ impl Days {
         }
     }
 
+    /// Outdated if updated `threshold` days ago or before. The threshold is kept for output.
+    pub fn check_threshold(&mut self, threshold: i64) {
+        self.threshold = Some(threshold);
+        self.check(|d| d >= threshold);
+    }
+
+    /// Treat missing information as outdated for `days`. Days since updated are still `None`.
+    pub fn mark_missing(&mut self, days: i64) {
+        if self.days.is_none() {
+            self.outdated = Some(days);
+        }
+    }
+
+    /// Treat as no information, so that never be outdated.
+    pub fn ignore(&mut self) {
+        self.days = None;
+        self.outdated = None;
+        self.threshold = None;
+    }
+
     fn outdated(&self) -> Option<i64> {
         self.outdated
     }
 
+    /// Days since updated. `None` if the station doesn't have the information.
+    pub fn days(&self) -> Option<i64> {
+        self.days
+    }
+
     pub fn is_outdated(&self) -> bool {
         self.outdated.is_some()
     }
+
+    /// Days threshold checked by `check_threshold`, if any.
+    pub fn threshold(&self) -> Option<i64> {
+        self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn record(station: &Station, score: f64) -> Record<'_> {
+        let mut r = new_record(
+            station,
+            Coords::zero(),
+            &Visited::new(),
+            Coverage::default(),
+            Utc::now(),
+        );
+        r.score = score;
+        r
+    }
+
+    fn any_distance() -> impl Strategy<Value = f64> {
+        prop_oneof![0.0f64..1e6, Just(0.0), Just(f64::NAN), Just(f64::INFINITY),]
+    }
+
+    fn any_score() -> impl Strategy<Value = f64> {
+        prop_oneof![any::<f64>(), Just(f64::NAN), Just(f64::INFINITY), Just(0.0),]
+    }
+
+    proptest! {
+        #[test]
+        fn sort_never_panics(scores in prop::collection::vec(any_score(), 0..50)) {
+            let st = Station::for_test(0, None);
+            let mut records: Vec<Record> = scores.iter().map(|&s| record(&st, s)).collect();
+            records.sort_by(|l, r| l.cmp(r).reverse());
+            prop_assert_eq!(records.len(), scores.len());
+        }
+
+        #[test]
+        fn ordering_is_antisymmetric(a in any_score(), b in any_score()) {
+            let st = Station::for_test(0, None);
+            let (a, b) = (record(&st, a), record(&st, b));
+            prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+        }
+
+        #[test]
+        fn ordering_follows_score(a in -1e9f64..1e9, b in -1e9f64..1e9) {
+            let st = Station::for_test(0, None);
+            prop_assert_eq!(record(&st, a).cmp(&record(&st, b)), a.partial_cmp(&b).unwrap());
+        }
+
+        #[test]
+        fn days_check_marks_outdated(days in 0i64..100_000, threshold in 0i64..100_000) {
+            let mut d = Days::new(days);
+            d.check(|v| v >= threshold);
+            prop_assert_eq!(d.is_outdated(), days >= threshold);
+        }
+
+        #[test]
+        fn empty_days_never_outdated(threshold in any::<i64>()) {
+            let mut d = Days::empty();
+            d.check(|v| v >= threshold);
+            prop_assert!(!d.is_outdated());
+        }
+
+        #[test]
+        fn transform_is_finite_and_monotonic(a in 0i64..1_000_000, b in 0i64..1_000_000, cap in 1i64..10_000) {
+            let (lo, hi) = (a.min(b), a.max(b));
+            for t in &[DaysTransform::Linear, DaysTransform::Log, DaysTransform::Cap(cap)] {
+                prop_assert!(t.apply(lo).is_finite());
+                prop_assert!(t.apply(lo) <= t.apply(hi));
+            }
+        }
+
+        #[test]
+        fn score_of_edge_records(info_days in 0i64..10_000, market_days in prop::option::of(0i64..10_000), distance in any_distance()) {
+            let st = Station::for_test(info_days, market_days);
+            let mut r = record(&st, 0.0);
+            r.distance = distance;
+            r.information_days.check(|d| d >= 0);
+            r.market_days.check(|d| d >= 0);
+            let score = r.calc_score(DaysTransform::Log, &StraightLine::default());
+            prop_assert!(!score.is_nan() || distance.is_nan());
+        }
+    }
+
+    #[test]
+    fn score_of_zero_distance_record_is_finite() {
+        let mut st = Station::for_test(100, None);
+        st.distance_to_arrival = None;
+        let mut r = record(&st, 0.0);
+        r.information_days.check(|d| d >= 0);
+        let score = r.calc_score(DaysTransform::Linear, &StraightLine::default());
+        assert_eq!(score, 100.0 / crate::travel::MIN_SECS);
+    }
+
+    #[test]
+    fn roll_weights_of_infinite_score() {
+        let st = Station::for_test(100, None);
+        let records = vec![
+            record(&st, 2.0),
+            record(&st, f64::INFINITY),
+            record(&st, f64::NAN),
+            record(&st, -1.0),
+        ];
+        assert_eq!(roll_weights(&records), vec![0.0, 1.0, 0.0, 0.0]);
+
+        let records = vec![record(&st, 2.0), record(&st, f64::NAN), record(&st, -1.0)];
+        assert_eq!(roll_weights(&records), vec![2.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn nan_score_is_lowest() {
+        let st = Station::for_test(0, None);
+        let nan = record(&st, f64::NAN);
+        assert_eq!(nan.cmp(&record(&st, f64::NEG_INFINITY)), Ordering::Less);
+        assert_eq!(nan.cmp(&record(&st, f64::NAN)), Ordering::Equal);
+    }
+
+    #[test]
+    fn zero_days_zero_distance() {
+        let st = Station::for_test(0, Some(0));
+        let mut r = record(&st, 0.0);
+        r.distance = 0.0;
+        r.information_days.check(|d| d >= 0);
+        assert_eq!(r.outdated().map(|o| o.days), Some(0));
+        let _ = r.calc_score(DaysTransform::Linear, &StraightLine::default());
+    }
+
+    #[test]
+    fn economy_boost_secondary_only_if_included() {
+        let mut st = Station::for_test(0, None);
+        st.second_economy = Some(Economy::HighTech);
+        let mut boost = EconomyBoost {
+            economies: vec![Economy::HighTech].into_iter().collect(),
+            factor: 2.0,
+            include_secondary: false,
+        };
+        assert_eq!(boost.factor_of(&st), 1.0);
+        boost.include_secondary = true;
+        assert_eq!(boost.factor_of(&st), 2.0);
+    }
 }
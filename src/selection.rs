@@ -0,0 +1,93 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::searcher::Record;
+use crate::state::StateFile;
+use crate::stations::Station;
+
+/// Format version of selection file.
+const VERSION: u32 = 1;
+
+fn state_file(path: &Path) -> StateFile {
+    StateFile::new(path, "selection", VERSION).pretty()
+}
+
+/// Stations marked as "will visit" in interactive mode.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    path: PathBuf,
+    keys: BTreeSet<Key>,
+}
+
+impl Selection {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Selection, Fail> {
+        let path = path.as_ref().to_owned();
+        let keys = state_file(&path).load()?.unwrap_or_default();
+        Ok(Selection { path, keys })
+    }
+
+    pub fn save(&self) -> Result<(), Fail> {
+        state_file(&self.path).save(&self.keys)
+    }
+
+    pub fn contains(&self, station: &Station) -> bool {
+        self.keys.contains(&Key::new(station))
+    }
+
+    pub fn toggle(&mut self, station: &Station) {
+        let key = Key::new(station);
+        if !self.keys.remove(&key) {
+            self.keys.insert(key);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Write selected stations as a route list, in order of `records`.
+    /// Selected stations which are not in `records` follow them.
+    pub fn export<P: AsRef<Path>>(&self, path: P, records: &[Record]) -> Result<(), Fail> {
+        let path = path.as_ref();
+        let f = File::create(path).err_msg(format!("can't create file: {:?}", path))?;
+        let mut w = BufWriter::new(f);
+
+        let mut rest = self.keys.clone();
+        for r in records {
+            let key = Key::new(r.station);
+            if rest.remove(&key) {
+                writeln!(w, "{}\t{}", key.system, key.station)?;
+            }
+        }
+        for key in rest {
+            writeln!(w, "{}\t{}", key.system, key.station)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Station identified by system and station names.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub(crate) struct Key {
+    pub(crate) system: String,
+    pub(crate) station: String,
+}
+
+impl Key {
+    pub(crate) fn new(station: &Station) -> Key {
+        Key {
+            system: station.system_name.clone(),
+            station: station.name.clone(),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::coords::Coords;
+
+/// Edge length of grid cells in Ly.
+pub const CELL_SIZE: f64 = 50.0;
+
+type Cell = (i64, i64, i64);
+
+/// Uniform grid over points, to find nearest ones without scanning all of them.
+#[derive(Debug, Default)]
+pub struct SpatialIndex {
+    cells: HashMap<Cell, Vec<usize>>,
+    points: Vec<Coords>,
+}
+
+impl SpatialIndex {
+    /// Build an index; returned indices of queries are positions in `points`.
+    pub fn new<I: IntoIterator<Item = Coords>>(points: I) -> SpatialIndex {
+        let points: Vec<Coords> = points.into_iter().collect();
+        let mut cells = HashMap::<Cell, Vec<usize>>::new();
+        for (i, p) in points.iter().enumerate() {
+            cells.entry(cell_of(*p)).or_default().push(i);
+        }
+        SpatialIndex { cells, points }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Up to `count` nearest points to `origin` with their distance, nearest first.
+    pub fn nearest(&self, origin: Coords, count: usize) -> Vec<(usize, f64)> {
+        if count == 0 || self.is_empty() {
+            return Vec::new();
+        }
+
+        let center = cell_of(origin);
+        let mut found: Vec<(usize, f64)> = Vec::new();
+        let mut ring = 0;
+        loop {
+            // Scanning all occupied cells is cheaper than a sparse ring far from points.
+            let ring_cells = (2 * ring + 1_i64).pow(3) - (2 * ring - 1_i64).max(0).pow(3);
+            if ring_cells as usize > self.cells.len() {
+                found = self.scan(origin);
+                break;
+            }
+
+            for dx in -ring..=ring {
+                for dy in -ring..=ring {
+                    for dz in -ring..=ring {
+                        if dx.abs().max(dy.abs()).max(dz.abs()) != ring {
+                            continue;
+                        }
+                        let cell = (center.0 + dx, center.1 + dy, center.2 + dz);
+                        if let Some(ids) = self.cells.get(&cell) {
+                            found.extend(ids.iter().map(|&i| (i, origin.dist_to(self.points[i]))));
+                        }
+                    }
+                }
+            }
+
+            // Points outside the scanned rings are at least this far.
+            let covered = ring as f64 * CELL_SIZE;
+            if found.len() >= count {
+                sort_by_distance(&mut found);
+                if found[count - 1].1 <= covered {
+                    break;
+                }
+            }
+            ring += 1;
+        }
+
+        sort_by_distance(&mut found);
+        found.truncate(count);
+        found
+    }
+
+    fn scan(&self, origin: Coords) -> Vec<(usize, f64)> {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, origin.dist_to(*p)))
+            .collect()
+    }
+}
+
+fn cell_of(p: Coords) -> Cell {
+    (
+        (p.x() / CELL_SIZE).floor() as i64,
+        (p.y() / CELL_SIZE).floor() as i64,
+        (p.z() / CELL_SIZE).floor() as i64,
+    )
+}
+
+fn sort_by_distance(found: &mut [(usize, f64)]) {
+    found.sort_by(|l, r| l.1.partial_cmp(&r.1).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn any_point() -> impl Strategy<Value = Coords> {
+        (-1000.0..1000.0, -200.0..200.0, -1000.0..1000.0).prop_map(|(x, y, z)| Coords::new(x, y, z))
+    }
+
+    proptest! {
+        #[test]
+        fn nearest_is_same_as_scan(
+            points in prop::collection::vec(any_point(), 0..300),
+            origin in any_point(),
+            count in 0usize..20,
+        ) {
+            let index = SpatialIndex::new(points.clone());
+            let found = index.nearest(origin, count);
+
+            let mut expected: Vec<f64> = points.iter().map(|p| origin.dist_to(*p)).collect();
+            expected.sort_by(|l, r| l.partial_cmp(r).unwrap());
+            expected.truncate(count);
+            let dists: Vec<f64> = found.iter().map(|&(_, d)| d).collect();
+            prop_assert_eq!(dists, expected);
+            for (i, d) in found {
+                prop_assert_eq!(origin.dist_to(points[i]), d);
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_of_clustered_points() {
+        // Far cluster makes the index scan rings around the origin first.
+        let mut points = vec![Coords::new(1000.0, 0.0, 0.0); 50];
+        points.push(Coords::new(60.0, 0.0, 0.0));
+        points.push(Coords::new(-10.0, 0.0, 0.0));
+        let index = SpatialIndex::new(points);
+
+        assert_eq!(index.len(), 52);
+        assert_eq!(index.nearest(Coords::zero(), 0), vec![]);
+        assert_eq!(
+            index.nearest(Coords::zero(), 2),
+            vec![(51, 10.0), (50, 60.0)]
+        );
+        assert_eq!(index.nearest(Coords::zero(), 100).len(), 52);
+        assert!(SpatialIndex::new(vec![])
+            .nearest(Coords::zero(), 1)
+            .is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+use std::fs::{rename, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{from_reader, from_value, to_writer, to_writer_pretty, Value};
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::diagnostics::{warn, Warning};
+
+/// Persistent state saved as JSON with format version.
+///
+/// Saved as `{"version": <version>, "data": <value>}`. Files written before versioning, which only
+/// have the value, are read as the current version.
+/// Writes go through a temporary file, and the previous file is kept as `<name>.bak` to recover
+/// from a broken file.
+#[derive(Debug, Clone)]
+pub struct StateFile {
+    path: PathBuf,
+    name: &'static str,
+    version: u32,
+    pretty: bool,
+}
+
+impl StateFile {
+    /// `name` is used in messages, such as `"visited"`.
+    pub fn new<P: AsRef<Path>>(path: P, name: &'static str, version: u32) -> StateFile {
+        StateFile {
+            path: path.as_ref().to_owned(),
+            name,
+            version,
+            pretty: false,
+        }
+    }
+
+    /// Write indented JSON, for files users may read or edit.
+    pub fn pretty(mut self) -> StateFile {
+        self.pretty = true;
+        self
+    }
+
+    /// Load saved value. Returns `None` if neither the file nor its backup exists.
+    ///
+    /// If the file is broken, it is moved to `<name>.broken` and the backup is used with a warning.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<Option<T>, Fail> {
+        let backup = self.backup_path();
+        if !self.path.exists() {
+            // Interrupted between moving the file to backup and moving new one in place.
+            if backup.exists() {
+                return self.read(&backup).map(Some).map_err(ReadError::into_fail);
+            }
+            return Ok(None);
+        }
+
+        match self.read(&self.path) {
+            Ok(value) => Ok(Some(value)),
+            // Older program must not replace the file with older format.
+            Err(ReadError::Newer(e)) => Err(e),
+            Err(ReadError::Broken(e)) => {
+                if !backup.exists() {
+                    return Err(e);
+                }
+                let value = match self.read(&backup) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        return Err(e.msg(format!("{} file and its backup are broken", self.name)))
+                    }
+                };
+                // Keep the broken file out of the way, so that next save doesn't back it up.
+                let broken = self.sibling("broken");
+                rename(&self.path, &broken)
+                    .err_msg(format!("can't move broken file: {:?}", self.path))?;
+                warn(Warning::StateRecovered(self.path.display().to_string()));
+                Ok(Some(value))
+            }
+        }
+    }
+
+    /// Save value atomically, keeping the previous file as backup.
+    pub fn save<T: Serialize>(&self, value: &T) -> Result<(), Fail> {
+        let tmp = self.sibling("tmp");
+        let f = File::create(&tmp).err_msg(format!("can't create file: {:?}", tmp))?;
+        let mut w = BufWriter::new(f);
+        let versioned = Versioned {
+            version: self.version,
+            data: value,
+        };
+        if self.pretty {
+            to_writer_pretty(&mut w, &versioned)
+        } else {
+            to_writer(&mut w, &versioned)
+        }
+        .err_msg(format!("can't encode {} file", self.name))?;
+        w.flush()?;
+        w.get_ref()
+            .sync_all()
+            .err_msg(format!("can't write file: {:?}", tmp))?;
+
+        if self.path.exists() {
+            rename(&self.path, self.backup_path())
+                .err_msg(format!("can't back up file: {:?}", self.path))?;
+        }
+        rename(&tmp, &self.path).err_msg(format!("can't write file: {:?}", self.path))?;
+        Ok(())
+    }
+
+    fn read<T: DeserializeOwned>(&self, path: &Path) -> Result<T, ReadError> {
+        let broken = |e: Fail| {
+            ReadError::Broken(e.msg(format!("can't parse {} file: {:?}", self.name, path)))
+        };
+
+        let f = File::open(path)
+            .err_msg(format!("can't open file: {:?}", path))
+            .map_err(ReadError::Broken)?;
+        let value: Value = from_reader(BufReader::new(f)).map_err(|e| broken(e.into()))?;
+
+        let data = match value {
+            Value::Object(mut obj) if obj.len() == 2 && obj.contains_key("data") => {
+                let version = obj
+                    .get("version")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| broken(Fail::new("no version")))?;
+                if version > u64::from(self.version) {
+                    return Err(ReadError::Newer(Fail::new(format!(
+                        "{} file is written by newer version (format {}): {:?}",
+                        self.name, version, path
+                    ))));
+                }
+                obj.remove("data").unwrap()
+            }
+            // Written before versioning.
+            value => value,
+        };
+        from_value(data).map_err(|e| broken(e.into()))
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        self.sibling("bak")
+    }
+
+    /// `<name>.<ext>` in the same directory.
+    fn sibling(&self, ext: &str) -> PathBuf {
+        let name = self
+            .path
+            .file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+        self.path.with_file_name(format!("{}.{}", name, ext))
+    }
+}
+
+enum ReadError {
+    /// Can't be read or parsed, which may be recovered from backup.
+    Broken(Fail),
+    /// Written in newer format.
+    Newer(Fail),
+}
+
+impl ReadError {
+    fn into_fail(self) -> Fail {
+        match self {
+            ReadError::Broken(e) | ReadError::Newer(e) => e,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    version: u32,
+    data: &'a T,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file, write};
+    use std::process;
+
+    use serde_json::{from_str, json};
+
+    use super::*;
+    use crate::diagnostics::warnings;
+
+    /// Paths of a state file and its siblings, removed on drop.
+    struct TempState(PathBuf);
+
+    impl TempState {
+        fn new(name: &str) -> TempState {
+            TempState(temp_dir().join(format!(
+                "near-old-stations-state-{}-{}.json",
+                name,
+                process::id()
+            )))
+        }
+
+        fn file(&self, version: u32) -> StateFile {
+            StateFile::new(&self.0, "test", version)
+        }
+
+        fn sibling(&self, ext: &str) -> PathBuf {
+            self.file(1).sibling(ext)
+        }
+    }
+
+    impl Drop for TempState {
+        fn drop(&mut self) {
+            for path in &[
+                self.0.clone(),
+                self.sibling("tmp"),
+                self.sibling("bak"),
+                self.sibling("broken"),
+            ] {
+                let _ = remove_file(path);
+            }
+        }
+    }
+
+    #[test]
+    fn save_keeps_previous_as_backup() {
+        let state = TempState::new("save");
+        assert_eq!(state.file(1).load::<u32>().unwrap(), None);
+
+        state.file(1).save(&1u32).unwrap();
+        assert!(!state.sibling("bak").exists());
+        state.file(1).save(&2u32).unwrap();
+        assert!(!state.sibling("tmp").exists());
+
+        let saved: Value = from_str(&read_to_string(&state.0).unwrap()).unwrap();
+        assert_eq!(saved, json!({"version": 1, "data": 2}));
+        let backup: Value = from_str(&read_to_string(state.sibling("bak")).unwrap()).unwrap();
+        assert_eq!(backup, json!({"version": 1, "data": 1}));
+        assert_eq!(state.file(1).load::<u32>().unwrap(), Some(2));
+    }
+
+    #[test]
+    fn broken_file_is_restored_from_backup() {
+        let state = TempState::new("broken");
+        state.file(1).save(&1u32).unwrap();
+        state.file(1).save(&2u32).unwrap();
+        write(&state.0, "{broken").unwrap();
+
+        assert_eq!(state.file(1).load::<u32>().unwrap(), Some(1));
+        assert!(!state.0.exists());
+        assert_eq!(read_to_string(state.sibling("broken")).unwrap(), "{broken");
+        assert!(warnings().contains(&Warning::StateRecovered(state.0.display().to_string())));
+
+        // Next save must not back up the broken file.
+        state.file(1).save(&3u32).unwrap();
+        assert_eq!(state.file(1).load::<u32>().unwrap(), Some(3));
+        let backup: Value = from_str(&read_to_string(state.sibling("bak")).unwrap()).unwrap();
+        assert_eq!(backup, json!({"version": 1, "data": 1}));
+    }
+
+    #[test]
+    fn backup_is_used_without_file() {
+        let state = TempState::new("interrupted");
+        write(state.sibling("bak"), r#"{"version": 1, "data": 5}"#).unwrap();
+        assert_eq!(state.file(1).load::<u32>().unwrap(), Some(5));
+    }
+
+    #[test]
+    fn broken_file_without_backup_is_error() {
+        let state = TempState::new("no-backup");
+        write(&state.0, "{broken").unwrap();
+        assert!(state.file(1).load::<u32>().is_err());
+        assert!(state.0.exists());
+    }
+
+    #[test]
+    fn versions() {
+        let state = TempState::new("versions");
+        // Written before versioning.
+        write(&state.0, "7").unwrap();
+        assert_eq!(state.file(1).load::<u32>().unwrap(), Some(7));
+
+        state.file(2).save(&8u32).unwrap();
+        assert_eq!(state.file(3).load::<u32>().unwrap(), Some(8));
+        // Newer file is not replaced by its backup.
+        assert!(state.file(1).load::<u32>().is_err());
+        assert!(state.0.exists());
+    }
+}
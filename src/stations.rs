@@ -1,125 +1,673 @@
 pub mod download;
 
+mod binary;
 mod date_format;
 mod date_format_opt;
+mod extract;
+mod fingerprint;
+mod read_ahead;
+mod station_cache;
+mod system_cache;
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::fs::{metadata, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, FixedOffset, Utc};
 use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use flate2::Compression;
 use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
-use serde_json::{from_reader, from_str, to_writer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::from_str;
 use tiny_fail::{ErrorMessageExt, Fail};
 
+use crate::changes::{Change, Snapshot};
 use crate::coords::Coords;
-use download::Downloader;
+use crate::diagnostics::{warn, Warning};
+use crate::paths::{cache_file, lock_cache, state_file};
+use crate::refresh::RefreshHistory;
+use crate::state::StateFile;
+use download::{cached_time, Downloader, Freshness, NetworkOptions};
+use read_ahead::ReadAhead;
+
+pub use extract::extract;
 
 const SYTEMS_DUMP_URL: &str = "https://www.edsm.net/dump/systemsPopulated.json.gz";
 const SYTEMS_DUMP_FILE: &str = "systemsPopulated.json.gz";
-const SYTEMS_COORDS_FILE: &str = "coordinates.json.gz";
+const SYTEMS_CACHE_FILE: &str = "systems.bin.gz";
+const FULL_SYSTEMS_DUMP_URL: &str = "https://www.edsm.net/dump/systemsWithCoordinates.json.gz";
+const FULL_SYSTEMS_DUMP_FILE: &str = "systemsWithCoordinates.json.gz";
+/// Systems found in full systems dump, only which have stations.
+const UNPOPULATED_CACHE_FILE: &str = "systems_unpopulated.bin.gz";
+/// Unpopulated systems cache is kept over updates of full systems dump, as systems don't move.
+const UNPOPULATED_CACHE_KEY: &str = "";
+/// IDs of systems not found in the last scan of full systems dump.
+const UNPOPULATED_MISSING_FILE: &str = "systems_unpopulated_missing.json";
+/// Format version of `UNPOPULATED_MISSING_FILE`.
+const UNPOPULATED_MISSING_VERSION: u32 = 1;
 const STATIONS_DUMP_URL: &str = "https://www.edsm.net/dump/stations.json.gz";
 const STATIONS_DUMP_FILE: &str = "stations.json.gz";
+const STATIONS_CACHE_FILE: &str = "stations.bin.gz";
+/// EDSM updates dumps daily.
+const OLD_DUMP_DAYS: i64 = 3;
+const REFRESH_HISTORY_FILE: &str = "refresh_history.json";
+const SNAPSHOT_FILE: &str = "station_snapshot.json";
+const FINGERPRINT_FILE: &str = "fingerprint.bin.gz";
+
+/// Download a list of names, one per line. Empty lines and lines starting with `#` are ignored.
+pub fn load_name_list(
+    file_name: &str,
+    url: &str,
+    network: &NetworkOptions,
+) -> Result<Vec<String>, Fail> {
+    let downloader = Downloader::new(network)?;
+    downloader
+        .download(file_name, url)
+        .err_msg(format!("failed to download {}", url))?;
+
+    let r = open_dump(cache_file(file_name))?;
+    let mut list = Vec::new();
+    for line in r.lines() {
+        let line = line?;
+        let name = line.trim();
+        if name.is_empty() || name.starts_with('#') {
+            continue;
+        }
+        list.push(name.to_owned());
+    }
+
+    Ok(list)
+}
+
+/// Find populated system by name (case-insensitive).
+pub fn find_system(opts: &LoadOptions, name: &str) -> Result<System, Fail> {
+    let systems = load_systems(opts, false)?;
+    systems
+        .by_name(name)
+        .cloned()
+        .err_msg(format!("unknown system: {}", name))
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    pub network: NetworkOptions,
+    pub skip_bad_records: bool,
+    /// Stations dump URLs, tried in order.
+    pub stations_urls: Vec<String>,
+    /// Systems dump URLs, tried in order.
+    pub systems_urls: Vec<String>,
+    /// Local stations dump used instead of downloading.
+    pub stations_dump: Option<PathBuf>,
+    /// Local systems dump used instead of downloading.
+    pub systems_dump: Option<PathBuf>,
+    /// Find systems of stations missing from systems dump in full systems dump.
+    pub full_systems: bool,
+    /// Full systems dump URLs, tried in order.
+    pub full_systems_urls: Vec<String>,
+    /// Threads to decompress and parse dumps. 0 means number of CPUs.
+    pub threads: usize,
+    /// Record update times of each load, to know how often stations are refreshed.
+    pub track_refresh: bool,
+    /// Compare economies and types with the previous load, to find changed stations.
+    pub track_changes: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> LoadOptions {
+        LoadOptions {
+            network: NetworkOptions::default(),
+            skip_bad_records: false,
+            stations_urls: vec![STATIONS_DUMP_URL.to_owned()],
+            systems_urls: vec![SYTEMS_DUMP_URL.to_owned()],
+            stations_dump: None,
+            systems_dump: None,
+            full_systems: false,
+            full_systems_urls: vec![FULL_SYSTEMS_DUMP_URL.to_owned()],
+            threads: 0,
+            track_refresh: false,
+            track_changes: false,
+        }
+    }
+}
+
+pub fn load_stations(opts: &LoadOptions) -> Result<Stations, Fail> {
+    let stations = load_raw_stations(opts)?;
+    let mut systems = load_systems(opts, false)?;
+    if opts.full_systems {
+        let missing: HashSet<u64> = stations
+            .stations()
+            .map(|st| st.system_id)
+            .filter(|&id| systems.by_id(id).is_none())
+            .collect();
+        if !missing.is_empty() {
+            let mut list = systems.into_list();
+            list.extend(load_unpopulated_systems(opts, &missing)?);
+            systems = Systems::new(list);
+        }
+    }
+    let mut stations = join_systems(stations, systems);
+
+    if opts.track_refresh {
+        track_refresh(&mut stations.list)?;
+    }
+    if opts.track_changes {
+        track_changes(&mut stations.list)?;
+    }
+
+    if let Some(t) = stations.last_mod {
+        let days = Utc::now().signed_duration_since(t).num_days();
+        if days >= OLD_DUMP_DAYS {
+            warn(Warning::OldDump(days));
+        }
+    }
+
+    Ok(stations)
+}
+
+/// Check updates of all downloaded dumps concurrently, by file name.
+///
+/// Results are reused by `load_stations` following soon, so that dumps aren't checked one by one.
+/// Dumps given as local files aren't checked.
+pub fn check_dumps(opts: &LoadOptions) -> Result<Vec<(&'static str, Freshness)>, Fail> {
+    let mut dumps: Vec<(&'static str, &[String])> = Vec::new();
+    if opts.stations_dump.is_none() {
+        dumps.push((STATIONS_DUMP_FILE, &opts.stations_urls));
+    }
+    if opts.systems_dump.is_none() {
+        dumps.push((SYTEMS_DUMP_FILE, &opts.systems_urls));
+    }
+    if opts.full_systems {
+        dumps.push((FULL_SYSTEMS_DUMP_FILE, &opts.full_systems_urls));
+    }
+    if dumps.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Cache lock is released before loading.
+    let results = Downloader::new(&opts.network)?.check_all(&dumps);
+    Ok(dumps
+        .into_iter()
+        .map(|(file_name, _)| file_name)
+        .zip(results)
+        .collect())
+}
 
-pub fn load_stations() -> Result<Stations, Fail> {
-    let downloader = Downloader::new()?;
+/// Parse stations and systems dumps given in memory, such as files dropped in browser.
+///
+/// Dumps may be compressed. Unlike `load_stations`, nothing is downloaded or cached.
+pub fn parse_stations<R, S>(
+    stations: R,
+    systems: S,
+    skip_bad_records: bool,
+) -> Result<Stations, Fail>
+where
+    R: BufRead + Send + 'static,
+    S: BufRead + Send + 'static,
+{
+    let mut decoder = Decoder::new(decompress(stations)?.0);
+    decoder.skip_bad_records(skip_bad_records);
+    let mut list = Vec::new();
+    while let Some(batch) = decoder.next_batch::<Station>()? {
+        list.extend(batch);
+    }
+    let stations = Stations {
+        list,
+        last_mod: None,
+        missing_coords_stations: Vec::new(),
+        skipped_records: decoder.skipped(),
+        systems: Systems::default(),
+    };
 
-    let stations = load_raw_stations(&downloader)?;
-    let coords_table = load_coords(&downloader, false)?;
+    let mut decoder = Decoder::new(decompress(systems)?.0);
+    let mut list = Vec::new();
+    while let Some(batch) = decoder.next_batch::<System>()? {
+        list.extend(batch);
+    }
+
+    Ok(join_systems(stations, Systems::new(list)))
+}
 
+/// Set system information to each station. Stations in unknown systems are kept separately.
+fn join_systems(stations: Stations, systems: Systems) -> Stations {
     let last_mod = stations.last_mod();
+    let skipped_records = stations.skipped_records();
     let mut list = Vec::new();
     let mut missing_coords_stations = Vec::new();
+    let mut invalid_coords = 0;
+    let mut unknown_types = BTreeMap::new();
     for mut st in stations.into_list() {
-        if let Some(&c) = coords_table.get(&st.system_id) {
-            st.coords = c;
+        if let StationType::Unknown(ref name) = st.st_type {
+            *unknown_types.entry(name.clone()).or_insert(0) += 1;
+        }
+        if let Some(sys) = systems.by_id(st.system_id) {
+            if !sys.coords.is_valid() {
+                invalid_coords += 1;
+                continue;
+            }
+            st.coords = sys.coords;
+            // Keep values from stations dump if systems dump doesn't have them.
+            st.power = sys.power.clone().or_else(|| st.power.take());
+            st.security = sys.security.clone().or_else(|| st.security.take());
+            st.primary_star = sys.primary_star.clone();
             list.push(st);
         } else {
             missing_coords_stations.push(st);
         }
     }
 
-    Ok(Stations {
+    if skipped_records > 0 {
+        warn(Warning::SkippedRecords(skipped_records));
+    }
+    if !missing_coords_stations.is_empty() {
+        warn(Warning::MissingCoords(missing_coords_stations.len()));
+    }
+    if invalid_coords > 0 {
+        warn(Warning::InvalidCoords(invalid_coords));
+    }
+    if !unknown_types.is_empty() {
+        warn(Warning::UnknownStationTypes(
+            unknown_types.into_iter().collect(),
+        ));
+    }
+
+    Stations {
         list,
         last_mod,
         missing_coords_stations,
-    })
+        skipped_records,
+        systems,
+    }
 }
 
-fn load_raw_stations(downloader: &Downloader) -> Result<Stations, Fail> {
-    let last_mod = downloader
-        .download(STATIONS_DUMP_FILE, STATIONS_DUMP_URL)
-        .err_msg("failed to download stations dump file")?;
+/// Add update times to history, and set median refresh interval of each station.
+fn track_refresh(list: &mut [Station]) -> Result<(), Fail> {
+    let path = state_file(REFRESH_HISTORY_FILE);
+    let mut history = RefreshHistory::load(&path)?;
 
-    let mut decoder = Decoder::open(STATIONS_DUMP_FILE)?;
+    let now = Utc::now();
+    let mut updated = false;
+    for st in list.iter_mut() {
+        if let Some(id) = st.market_id {
+            updated |= history.observe(id, st.update_time.information());
+            st.refresh_interval = history.median_interval(id, now);
+        }
+    }
 
-    let mut list = Vec::new();
-    while let Some(st) = decoder.next::<Station>()? {
-        list.push(st);
+    if updated {
+        history.save(&path)?;
+    }
+    Ok(())
+}
+
+/// Compare stations with the snapshot of the previous load, and mark stations with recorded changes.
+fn track_changes(list: &mut [Station]) -> Result<(), Fail> {
+    let path = state_file(SNAPSHOT_FILE);
+    let mut snapshot = Snapshot::load(&path)?;
+
+    if snapshot.update(list, Utc::now()) {
+        snapshot.save(&path)?;
+    }
+
+    let changed: HashSet<u64> = snapshot.changes().iter().map(|c| c.market_id).collect();
+    for st in list.iter_mut() {
+        st.changed = st.market_id.is_some_and(|id| changed.contains(&id));
+    }
+    Ok(())
+}
+
+/// Recorded changes of economies and types, loaded by `load_stations` with `track_changes`.
+pub fn load_changes() -> Result<Vec<Change>, Fail> {
+    Ok(Snapshot::load(state_file(SNAPSHOT_FILE))?
+        .changes()
+        .to_vec())
+}
+
+/// Stations whose information is updated since the previous call, then saves current update times.
+///
+/// Returns `None` at the first call, when there is nothing to compare.
+pub fn updated_since_last_run(stations: &Stations) -> Result<Option<Vec<&Station>>, Fail> {
+    let path = state_file(FINGERPRINT_FILE);
+    // Held from reading to writing, so that concurrent runs don't interleave or report twice.
+    let _lock = lock_cache()?;
+    // Broken fingerprint is just made again.
+    let previous = if path.exists() {
+        fingerprint::read(&path).unwrap_or(None)
+    } else {
+        None
+    };
+
+    let updated = previous.map(|previous| {
+        stations
+            .stations()
+            .filter(|st| {
+                st.market_id
+                    .and_then(|id| previous.get(&id))
+                    .is_some_and(|&t| st.update_time.information().timestamp() > t)
+            })
+            .collect()
+    });
+
+    fingerprint::write(&path, &fingerprint::new(stations.stations()))
+        .err_msg("failed to write stations fingerprint")?;
+    Ok(updated)
+}
+
+/// Version of local dump files, which changes when dumps are updated.
+///
+/// Stations dump is updated from server before checking, like `load_stations`.
+pub fn dump_version(opts: &LoadOptions) -> Result<String, Fail> {
+    let (stations_path, _) = stations_dump_file(opts)?;
+
+    let mut version = String::new();
+    for path in &[
+        stations_path,
+        cache_file(SYTEMS_CACHE_FILE),
+        cache_file(UNPOPULATED_CACHE_FILE),
+    ] {
+        if let Ok(meta) = metadata(path) {
+            version.push_str(&format!(
+                "{:?}:{}:{:?};",
+                path,
+                meta.len(),
+                meta.modified().ok()
+            ));
+        }
+    }
+    Ok(version)
+}
+
+/// Path and last modified time of stations dump.
+fn stations_dump_file(
+    opts: &LoadOptions,
+) -> Result<(PathBuf, Option<DateTime<FixedOffset>>), Fail> {
+    dump_file(
+        opts.stations_dump.as_deref(),
+        STATIONS_DUMP_FILE,
+        &opts.stations_urls,
+        &opts.network,
+    )
+    .err_msg("failed to download stations dump file")
+}
+
+/// Local dump file if given, otherwise the file downloaded to cache directory.
+fn dump_file(
+    local: Option<&Path>,
+    file_name: &str,
+    urls: &[String],
+    network: &NetworkOptions,
+) -> Result<(PathBuf, Option<DateTime<FixedOffset>>), Fail> {
+    if let Some(path) = local {
+        let modified = cached_time(path).err_msg(format!("can't read dump file: {:?}", path))?;
+        return Ok((path.to_owned(), Some(modified.fixed_offset())));
+    }
+
+    let last_mod = Downloader::new(network)?.download_from(file_name, urls)?;
+    Ok((cache_file(file_name), last_mod))
+}
+
+fn load_raw_stations(opts: &LoadOptions) -> Result<Stations, Fail> {
+    let (path, last_mod) = stations_dump_file(opts)?;
+
+    let cache_path = cache_file(STATIONS_CACHE_FILE);
+    let key = match dump_key(&path) {
+        Ok(key) => format!("{}:{}", key, opts.skip_bad_records),
+        Err(_) => String::new(),
+    };
+    // Broken cache is just made again.
+    let cached = if cache_path.exists() {
+        station_cache::read(&cache_path, &key).unwrap_or(None)
+    } else {
+        None
+    };
+
+    let (mut list, skipped_records) = match cached {
+        Some(parsed) => parsed,
+        None => {
+            let mut decoder = Decoder::open(&path, opts.threads)?;
+            decoder.skip_bad_records(opts.skip_bad_records);
+
+            let mut list = Vec::new();
+            while let Some(batch) = decoder.next_batch::<Station>()? {
+                list.extend(batch);
+            }
+            let _lock = lock_cache()?;
+            station_cache::write(&cache_path, &key, &list, decoder.skipped())
+                .err_msg("failed to write stations cache")?;
+            (list, decoder.skipped())
+        }
+    };
+    for st in &mut list {
+        st.dump_date = last_mod;
     }
 
     Ok(Stations {
         list,
         last_mod,
         missing_coords_stations: Vec::new(),
+        skipped_records,
+        systems: Systems::default(),
     })
 }
 
-fn load_coords(downloader: &Downloader, force_update: bool) -> Result<HashMap<u64, Coords>, Fail> {
-    let coords_file_path = Path::new(SYTEMS_COORDS_FILE);
+fn load_systems(opts: &LoadOptions, force_update: bool) -> Result<Systems, Fail> {
+    let cache_path = cache_file(SYTEMS_CACHE_FILE);
 
-    // Update coords file.
-    if force_update || !coords_file_path.exists() {
-        update_coords(downloader)?;
-    }
+    // Cache is made again when the dump is replaced, or another dump is used.
+    let key = match opts.systems_dump {
+        Some(ref path) => dump_key(path).err_msg(format!("can't read dump file: {:?}", path))?,
+        None => dump_key(&cache_file(SYTEMS_DUMP_FILE)).unwrap_or_default(),
+    };
+
+    // Update system info cache.
+    let cached = if force_update || !cache_path.exists() {
+        None
+    } else {
+        system_cache::read(&cache_path, &key)?
+    };
+    let list = match cached {
+        Some(list) => list,
+        None => update_systems(opts)?,
+    };
+
+    Ok(Systems::new(list))
+}
 
-    let f = File::open(coords_file_path).err_msg("can't open coordinates file")?;
-    let r = GzDecoder::new(f);
-    let list: Vec<System> = from_reader(r).err_msg("failed to decode coordinates")?;
+fn update_systems(opts: &LoadOptions) -> Result<Vec<System>, Fail> {
+    let (path, _) = dump_file(
+        opts.systems_dump.as_deref(),
+        SYTEMS_DUMP_FILE,
+        &opts.systems_urls,
+        &opts.network,
+    )
+    .err_msg("failed to download systemsPopulated dump file")?;
+    let key = dump_key(&path).err_msg(format!("can't read dump file: {:?}", path))?;
 
-    let mut table = HashMap::new();
-    for sys in list {
-        table.insert(sys.id, sys.coords);
+    // Systems are kept from the same pass as writing the cache, instead of reading it back,
+    // so that only the result and a batch of parsed records are in memory.
+    let cache_path = cache_file(SYTEMS_CACHE_FILE);
+    let mut decoder = Decoder::open(path, opts.threads)?;
+    let _lock = lock_cache()?;
+    let mut cache =
+        system_cache::Writer::create(&cache_path, &key).err_msg("failed to write system cache")?;
+    let mut list = Vec::new();
+    while let Some(batch) = decoder.next_batch::<System>()? {
+        for sys in batch {
+            cache.push(&sys)?;
+            list.push(sys);
+        }
     }
+    cache.finish().err_msg("failed to write system cache")?;
+    list.shrink_to_fit();
 
-    Ok(table)
+    Ok(list)
 }
 
-fn update_coords(downloader: &Downloader) -> Result<(), Fail> {
-    downloader
-        .download(SYTEMS_DUMP_FILE, SYTEMS_DUMP_URL)
-        .err_msg("failed to download systemsPopulated dump file")?;
+/// Path, size and modified time of a dump file.
+///
+/// A downloaded dump with new ETag is written again, so these identify the dump.
+fn dump_key(path: &Path) -> io::Result<String> {
+    let meta = metadata(path)?;
+    Ok(format!(
+        "{:?}:{}:{:?}",
+        path,
+        meta.len(),
+        meta.modified().ok()
+    ))
+}
 
-    let mut decoder = Decoder::open(SYTEMS_DUMP_FILE)?;
-    let mut list = Vec::new();
-    while let Some(sys) = decoder.next::<System>()? {
-        list.push(sys);
+/// Systems in `ids` from full systems dump.
+///
+/// Found systems and IDs not found are cached, and the dump is checked and scanned again only if
+/// some of `ids` are in neither of them.
+fn load_unpopulated_systems(opts: &LoadOptions, ids: &HashSet<u64>) -> Result<Vec<System>, Fail> {
+    let cache_path = cache_file(UNPOPULATED_CACHE_FILE);
+    let missing_file = StateFile::new(
+        cache_file(UNPOPULATED_MISSING_FILE),
+        "missing systems",
+        UNPOPULATED_MISSING_VERSION,
+    );
+    let (mut list, known_missing) = if cache_path.exists() {
+        (
+            system_cache::read(&cache_path, UNPOPULATED_CACHE_KEY)?.unwrap_or_default(),
+            missing_file.load::<HashSet<u64>>()?.unwrap_or_default(),
+        )
+    } else {
+        (Vec::new(), HashSet::new())
+    };
+    list.retain(|sys| ids.contains(&sys.id));
+    let found: HashSet<u64> = list.iter().map(|sys| sys.id).collect();
+    if ids
+        .iter()
+        .all(|id| found.contains(id) || known_missing.contains(id))
+    {
+        return Ok(list);
     }
 
-    let f = File::create(SYTEMS_COORDS_FILE).err_msg("failed to create coordinates file")?;
-    let w = GzEncoder::new(f, Compression::best());
-    to_writer(w, &list).err_msg("failed to encode coordinates")?;
+    let (path, _) = dump_file(
+        None,
+        FULL_SYSTEMS_DUMP_FILE,
+        &opts.full_systems_urls,
+        &opts.network,
+    )
+    .err_msg("failed to download systemsWithCoordinates dump file")?;
 
-    Ok(())
+    // Too large to keep all, so that only wanted systems are kept while streaming.
+    // A bad record shouldn't waste the whole long scan.
+    let mut decoder = Decoder::open(path, opts.threads)?;
+    decoder.skip_bad_records(true);
+    let mut found = Vec::new();
+    while let Some(batch) = decoder.next_batch::<System>()? {
+        found.extend(batch.into_iter().filter(|sys| ids.contains(&sys.id)));
+    }
+
+    let found_ids: HashSet<u64> = found.iter().map(|sys| sys.id).collect();
+    let missing: HashSet<u64> = ids.difference(&found_ids).cloned().collect();
+    let _lock = lock_cache()?;
+    system_cache::write(&cache_path, UNPOPULATED_CACHE_KEY, &found)
+        .err_msg("failed to write system cache")?;
+    missing_file.save(&missing)?;
+    Ok(found)
 }
 
+/// Records parsed at once by `Decoder::next_batch`.
+const BATCH_LINES: usize = 4096;
+
 struct Decoder<R: BufRead> {
     r: R,
     buf: String,
+    line: usize,
+    offset: u64,
+    skip_bad_records: bool,
+    skipped: usize,
+    ended: bool,
+    /// Parse batches in parallel, if built with `rayon` feature.
+    parallel: bool,
+}
+
+impl Decoder<Box<dyn BufRead + Send>> {
+    /// Open dump file. With `threads` other than 1, compressed dump is decompressed in background
+    /// and records are parsed in parallel.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        threads: usize,
+    ) -> Result<Decoder<Box<dyn BufRead + Send>>, Fail> {
+        let (r, compressed) = open_dump_raw(path)?;
+        let mut decoder = if compressed && threads != 1 {
+            Decoder::new(Box::new(BufReader::new(ReadAhead::spawn(r))) as Box<dyn BufRead + Send>)
+        } else {
+            Decoder::new(r)
+        };
+        decoder.parallel = threads != 1;
+        Ok(decoder)
+    }
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Open dump file, detecting compression by magic bytes.
+fn open_dump<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead + Send>, Fail> {
+    Ok(open_dump_raw(path)?.0)
+}
+
+/// Open dump file, and tell whether it is compressed.
+fn open_dump_raw<P: AsRef<Path>>(path: P) -> Result<(Box<dyn BufRead + Send>, bool), Fail> {
+    let f =
+        File::open(&path).err_msg(format!("failed to open file {:?} to decode", path.as_ref()))?;
+    decompress(BufReader::new(f))
+}
+
+/// Decompress dump by magic bytes, and tell whether it is compressed.
+fn decompress<R: BufRead + Send + 'static>(
+    mut r: R,
+) -> Result<(Box<dyn BufRead + Send>, bool), Fail> {
+    let head = r.fill_buf()?;
+    if head.starts_with(GZIP_MAGIC) {
+        Ok((Box::new(BufReader::new(GzDecoder::new(r))), true))
+    } else if head.starts_with(ZSTD_MAGIC) {
+        Ok((open_zstd(r)?, true))
+    } else {
+        Ok((Box::new(r), false))
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn open_zstd<R: BufRead + Send + 'static>(r: R) -> Result<Box<dyn BufRead + Send>, Fail> {
+    let d = zstd::stream::read::Decoder::with_buffer(r)?;
+    Ok(Box::new(BufReader::new(d)))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn open_zstd<R: BufRead + Send + 'static>(_r: R) -> Result<Box<dyn BufRead + Send>, Fail> {
+    Err(Fail::new(
+        "zstd compressed dump is not supported in this build (enable 'zstd' feature)",
+    ))
 }
 
-impl Decoder<BufReader<GzDecoder<File>>> {
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Decoder<BufReader<GzDecoder<File>>>, Fail> {
-        let f = File::open(&path)
-            .err_msg(format!("failed to open file {:?} to decode", path.as_ref()))?;
-        let r = BufReader::new(GzDecoder::new(f));
-        Ok(Decoder::new(r))
+/// A record line with its position, for error messages.
+struct RecordLine {
+    line: usize,
+    offset: u64,
+    text: String,
+}
+
+impl RecordLine {
+    /// Returns error message instead of `Fail`, which can't be sent between threads.
+    fn decode<D: DeserializeOwned>(&self) -> Result<D, String> {
+        from_str(&self.text).map_err(|e| {
+            format!(
+                "line {}, byte {}{}: {}: {}",
+                self.line,
+                self.offset,
+                record_name(&self.text)
+                    .map(|n| format!(" ({:?})", n))
+                    .unwrap_or_default(),
+                e,
+                self.text
+            )
+        })
     }
 }
 
@@ -128,34 +676,130 @@ impl<R: BufRead> Decoder<R> {
         Decoder {
             r,
             buf: String::new(),
+            line: 0,
+            offset: 0,
+            skip_bad_records: false,
+            skipped: 0,
+            ended: false,
+            parallel: true,
         }
     }
 
-    pub fn next<D: DeserializeOwned>(&mut self) -> Result<Option<D>, Fail> {
+    pub fn skip_bad_records(&mut self, skip: bool) {
+        self.skip_bad_records = skip;
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Decode next records at once, in parallel if built with `rayon` feature.
+    /// Returns `None` at end of dump.
+    pub fn next_batch<D: DeserializeOwned + Send>(&mut self) -> Result<Option<Vec<D>>, Fail> {
+        let mut lines = Vec::with_capacity(BATCH_LINES);
+        while lines.len() < BATCH_LINES {
+            match self.next_line()? {
+                Some(line) => lines.push(line),
+                None => break,
+            }
+        }
+        if lines.is_empty() {
+            return Ok(None);
+        }
+
+        let mut items = Vec::with_capacity(lines.len());
+        for res in decode_lines(&lines, self.parallel) {
+            match res {
+                Ok(item) => items.push(item),
+                Err(e) => self.bad_record(e)?,
+            }
+        }
+        Ok(Some(items))
+    }
+
+    fn bad_record(&mut self, msg: String) -> Result<(), Fail> {
+        if self.skip_bad_records {
+            self.skipped += 1;
+            Ok(())
+        } else {
+            Err(Fail::new(msg))
+        }
+    }
+
+    /// Next line of a record, without array brackets and trailing comma.
+    fn next_line(&mut self) -> Result<Option<RecordLine>, Fail> {
+        if self.ended {
+            return Ok(None);
+        }
+
         loop {
-            self.r.read_line(&mut self.buf)?;
+            self.buf.truncate(0);
+            let line_offset = self.offset;
+            let n = self.r.read_line(&mut self.buf).err_msg(format!(
+                "failed to read line {} (byte {})",
+                self.line + 1,
+                line_offset
+            ))?;
+            if n == 0 {
+                self.ended = true;
+                return Ok(None);
+            }
+            self.line += 1;
+            self.offset += n as u64;
+
             let s = self.buf.trim().trim_end_matches(',');
             if s == "[" {
-                self.buf.truncate(0);
                 continue;
             }
             if s == "]" {
+                self.ended = true;
                 return Ok(None);
             }
 
-            let item: D = from_str(s).map_err(|e| Fail::new(format!("{}: {}", e, s)))?;
-            self.buf.truncate(0);
-
-            return Ok(Some(item));
+            return Ok(Some(RecordLine {
+                line: self.line,
+                offset: line_offset,
+                text: s.to_owned(),
+            }));
         }
     }
 }
 
+#[cfg(feature = "rayon")]
+fn decode_lines<D: DeserializeOwned + Send>(
+    lines: &[RecordLine],
+    parallel: bool,
+) -> Vec<Result<D, String>> {
+    use rayon::prelude::*;
+
+    if parallel {
+        lines.par_iter().map(RecordLine::decode).collect()
+    } else {
+        lines.iter().map(RecordLine::decode).collect()
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+fn decode_lines<D: DeserializeOwned + Send>(
+    lines: &[RecordLine],
+    _parallel: bool,
+) -> Vec<Result<D, String>> {
+    lines.iter().map(RecordLine::decode).collect()
+}
+
+/// Extracts `name` field from a record that can't be decoded as expected type.
+fn record_name(s: &str) -> Option<String> {
+    let v: serde_json::Value = from_str(s).ok()?;
+    v.get("name")?.as_str().map(str::to_owned)
+}
+
 #[derive(Debug)]
 pub struct Stations {
     list: Vec<Station>,
     missing_coords_stations: Vec<Station>,
     last_mod: Option<DateTime<FixedOffset>>,
+    skipped_records: usize,
+    systems: Systems,
 }
 
 impl Stations {
@@ -163,6 +807,23 @@ impl Stations {
         self.list.iter()
     }
 
+    /// Station at the position in `stations()` order.
+    pub fn get(&self, index: usize) -> Option<&Station> {
+        self.list.get(index)
+    }
+
+    pub fn by_market_id(&self, market_id: u64) -> Option<&Station> {
+        self.list.iter().find(|st| st.market_id == Some(market_id))
+    }
+
+    /// Whether the dump has the station, including stations whose system is unknown.
+    pub fn has_market_id(&self, market_id: u64) -> bool {
+        self.list
+            .iter()
+            .chain(self.missing_coords_stations.iter())
+            .any(|st| st.market_id == Some(market_id))
+    }
+
     pub fn into_list(self) -> Vec<Station> {
         self.list
     }
@@ -170,13 +831,130 @@ impl Stations {
     pub fn last_mod(&self) -> Option<DateTime<FixedOffset>> {
         self.last_mod
     }
+
+    pub fn skipped_records(&self) -> usize {
+        self.skipped_records
+    }
+
+    pub fn systems(&self) -> &Systems {
+        &self.systems
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+/// Populated systems, indexed by id and name.
+#[derive(Debug, Default)]
+pub struct Systems {
+    list: Vec<System>,
+    by_id: HashMap<u64, usize>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Systems {
+    pub fn new(list: Vec<System>) -> Systems {
+        let mut by_id = HashMap::with_capacity(list.len());
+        let mut by_name = HashMap::with_capacity(list.len());
+        for (i, sys) in list.iter().enumerate() {
+            by_id.insert(sys.id, i);
+            by_name.insert(sys.name.to_lowercase(), i);
+        }
+
+        Systems {
+            list,
+            by_id,
+            by_name,
+        }
+    }
+
+    pub fn systems(&self) -> impl Iterator<Item = &System> {
+        self.list.iter()
+    }
+
+    pub fn into_list(self) -> Vec<System> {
+        self.list
+    }
+
+    pub fn by_id(&self, id: u64) -> Option<&System> {
+        self.by_id.get(&id).map(|&i| &self.list[i])
+    }
+
+    /// Find system by name, ignoring case.
+    pub fn by_name(&self, name: &str) -> Option<&System> {
+        self.by_name
+            .get(&name.to_lowercase())
+            .map(|&i| &self.list[i])
+    }
+
+    /// Coordinates from `"x,y,z"` or system name.
+    pub fn locate(&self, target: &str) -> Result<Coords, Fail> {
+        if let Some(coords) = Coords::parse(target) {
+            return Ok(coords);
+        }
+        self.by_name(target)
+            .map(|sys| sys.coords)
+            .err_msg(format!("unknown system: {}", target))
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+}
+
+/// Record of systems dump.
+///
+/// EDSM dumps don't have power and arrival star, which richer dumps such as Spansh have.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct System {
-    id: u64,
-    coords: Coords,
+    pub id: u64,
+    pub name: String,
+    pub coords: Coords,
+    #[serde(default)]
+    pub allegiance: Option<String>,
+    #[serde(default)]
+    pub government: Option<String>,
+    #[serde(default)]
+    pub population: Option<u64>,
+    /// Controlling power, if the dump has it.
+    #[serde(default, alias = "controllingPower")]
+    pub power: Option<String>,
+    #[serde(default)]
+    pub security: Option<String>,
+    /// Arrival star, if the dump has it.
+    #[serde(default)]
+    pub primary_star: Option<PrimaryStar>,
+}
+
+/// Arrival star of a system.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrimaryStar {
+    /// Such as `"K (Yellow-Orange) Star"`.
+    #[serde(rename = "type")]
+    pub star_type: String,
+    #[serde(default)]
+    pub is_scoopable: Option<bool>,
+}
+
+impl PrimaryStar {
+    /// Spectral class such as `"K"`, or the star type if it isn't a main class.
+    pub fn class(&self) -> &str {
+        match self.star_type.find(" (") {
+            Some(i) => &self.star_type[..i],
+            None => &self.star_type,
+        }
+    }
+
+    /// Fuel can be scooped from K, G, B, F, O, A and M stars.
+    pub fn is_scoopable(&self) -> bool {
+        match self.is_scoopable {
+            Some(scoopable) => scoopable,
+            None => matches!(self.class(), "K" | "G" | "B" | "F" | "O" | "A" | "M"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -184,11 +962,42 @@ pub struct System {
 pub struct Station {
     #[serde(default)]
     pub coords: Coords,
+    /// Economy or type changed in a load tracking changes.
+    #[serde(skip)]
+    pub changed: bool,
     pub distance_to_arrival: Option<f64>,
+    /// Last modified time of the dump this record was read from.
+    #[serde(skip)]
+    pub dump_date: Option<DateTime<FixedOffset>>,
     pub economy: Option<Economy>,
+    #[serde(default)]
+    pub government: Option<String>,
+    /// Whether the station has services, if the dump has it.
+    #[serde(default)]
+    pub have_market: Option<bool>,
+    #[serde(default)]
+    pub have_outfitting: Option<bool>,
+    #[serde(default)]
+    pub have_shipyard: Option<bool>,
+    /// Landing pad counts, if the dump has it.
+    #[serde(default)]
+    pub landing_pads: Option<LandingPads>,
     pub market_id: Option<u64>,
     pub name: String,
+    #[serde(default)]
+    pub power: Option<String>,
+    /// Joined from systems dump.
+    #[serde(skip)]
+    pub primary_star: Option<PrimaryStar>,
+    /// Median days between updates including days since the last update, if tracked.
+    #[serde(skip)]
+    pub refresh_interval: Option<i64>,
     pub second_economy: Option<Economy>,
+    #[serde(default)]
+    pub security: Option<String>,
+    /// Station state such as `Damaged` or `UnderRepairs`, if the dump has it.
+    #[serde(default)]
+    pub state: Option<String>,
     #[serde(rename = "type")]
     pub st_type: StationType,
     pub system_id: u64,
@@ -200,6 +1009,55 @@ impl Station {
     pub fn update_time(&self) -> &UpdateTime {
         &self.update_time
     }
+
+    /// Security level of the system, or `"Anarchy"` if the station is controlled by anarchy faction.
+    pub fn security_level(&self) -> Option<&str> {
+        if self.government.as_deref() == Some("Anarchy") {
+            return Some("Anarchy");
+        }
+        self.security.as_deref()
+    }
+
+    pub fn has_l_pad(&self) -> bool {
+        match self.landing_pads {
+            Some(pads) => pads.large > 0,
+            None => self.st_type.has_l_pad(),
+        }
+    }
+
+    pub fn has_m_pad(&self) -> bool {
+        match self.landing_pads {
+            Some(pads) => pads.large > 0 || pads.medium > 0,
+            None => self.st_type.has_m_pad(),
+        }
+    }
+
+    /// Damaged or under repair, so services can't be updated normally.
+    pub fn is_damaged(&self) -> bool {
+        matches!(
+            self.state.as_deref(),
+            Some("Damaged") | Some("UnderRepairs") | Some("Under Repairs")
+        )
+    }
+
+    /// Median refresh interval is `days` or more.
+    pub fn is_neglected(&self, days: i64) -> bool {
+        self.refresh_interval.is_some_and(|i| i >= days)
+    }
+
+    pub fn is_dangerous(&self) -> bool {
+        matches!(self.security_level(), Some("Anarchy") | Some("Low"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LandingPads {
+    #[serde(default)]
+    pub large: u32,
+    #[serde(default)]
+    pub medium: u32,
+    #[serde(default)]
+    pub small: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -216,6 +1074,20 @@ pub struct UpdateTime {
 }
 
 impl UpdateTime {
+    pub(crate) fn new(
+        information: DateTime<Utc>,
+        market: Option<DateTime<Utc>>,
+        shipyard: Option<DateTime<Utc>>,
+        outfitting: Option<DateTime<Utc>>,
+    ) -> UpdateTime {
+        UpdateTime {
+            information,
+            market,
+            shipyard,
+            outfitting,
+        }
+    }
+
     pub fn information(&self) -> DateTime<Utc> {
         self.information
     }
@@ -233,41 +1105,126 @@ impl UpdateTime {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+/// Type of station, named as in dumps.
+///
+/// Types added to EDSM after this version are kept as `Unknown`, so that they don't break parsing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum StationType {
     // Orbital Large
-    #[serde(rename = "Ocellus Starport")]
     OcellusStarport,
-    #[serde(rename = "Orbis Starport")]
     OrbisStarport,
-    #[serde(rename = "Coriolis Starport")]
     CoriolisStarport,
-    #[serde(rename = "Asteroid base")]
     AsteroidBase,
-    #[serde(rename = "Mega ship")]
     MegaShip,
     // Orbital small
     Outpost,
     // Planetary
-    #[serde(rename = "Planetary Port")]
     PlanetaryPort,
-    #[serde(rename = "Planetary Outpost")]
     PlanetaryOutpost,
+    /// On-foot settlement.
+    OdysseySettlement,
+    // Carrier
+    FleetCarrier,
+    DrakeClassCarrier,
+    /// Type not known to this version, by its name in dumps.
+    Unknown(String),
 }
 
 impl StationType {
-    pub fn has_l_pad(self) -> bool {
+    /// Name in dumps.
+    pub fn name(&self) -> &str {
         match self {
-            StationType::Outpost => false,
+            StationType::OcellusStarport => "Ocellus Starport",
+            StationType::OrbisStarport => "Orbis Starport",
+            StationType::CoriolisStarport => "Coriolis Starport",
+            StationType::AsteroidBase => "Asteroid base",
+            StationType::MegaShip => "Mega ship",
+            StationType::Outpost => "Outpost",
+            StationType::PlanetaryPort => "Planetary Port",
+            StationType::PlanetaryOutpost => "Planetary Outpost",
+            StationType::OdysseySettlement => "Odyssey Settlement",
+            StationType::FleetCarrier => "Fleet Carrier",
+            StationType::DrakeClassCarrier => "Drake-Class Carrier",
+            StationType::Unknown(name) => name,
+        }
+    }
+
+    pub fn from_name(name: &str) -> StationType {
+        match name {
+            "Ocellus Starport" => StationType::OcellusStarport,
+            "Orbis Starport" => StationType::OrbisStarport,
+            "Coriolis Starport" => StationType::CoriolisStarport,
+            "Asteroid base" => StationType::AsteroidBase,
+            "Mega ship" => StationType::MegaShip,
+            "Outpost" => StationType::Outpost,
+            "Planetary Port" => StationType::PlanetaryPort,
+            "Planetary Outpost" => StationType::PlanetaryOutpost,
+            "Odyssey Settlement" => StationType::OdysseySettlement,
+            "Fleet Carrier" => StationType::FleetCarrier,
+            "Drake-Class Carrier" => StationType::DrakeClassCarrier,
+            _ => StationType::Unknown(name.to_owned()),
+        }
+    }
+
+    /// Like `from_name`, but `None` for names not known to this version, such as typos in config.
+    pub fn from_known_name(name: &str) -> Option<StationType> {
+        match StationType::from_name(name) {
+            StationType::Unknown(_) => None,
+            t => Some(t),
+        }
+    }
+
+    pub fn has_l_pad(&self) -> bool {
+        match self {
+            // Many settlements only have small or medium pads.
+            StationType::Outpost | StationType::OdysseySettlement => false,
             _ => true,
         }
     }
 
-    pub fn is_planetary(self) -> bool {
+    /// Settlements vary, so they have medium pads only if `LandingPads` in dumps says so.
+    pub fn has_m_pad(&self) -> bool {
+        !matches!(
+            self,
+            StationType::OdysseySettlement | StationType::Unknown(_)
+        )
+    }
+
+    pub fn is_planetary(&self) -> bool {
+        matches!(
+            self,
+            StationType::PlanetaryPort
+                | StationType::PlanetaryOutpost
+                | StationType::OdysseySettlement
+        )
+    }
+
+    pub fn is_settlement(&self) -> bool {
+        *self == StationType::OdysseySettlement
+    }
+
+    /// Player owned carriers, which move around.
+    pub fn is_carrier(&self) -> bool {
+        matches!(
+            self,
+            StationType::FleetCarrier | StationType::DrakeClassCarrier
+        )
+    }
+
+    pub fn short(&self) -> &'static str {
         match self {
-            StationType::PlanetaryPort => true,
-            StationType::PlanetaryOutpost => true,
-            _ => false,
+            StationType::OcellusStarport => "Oc",
+            StationType::OrbisStarport => "Or",
+            StationType::CoriolisStarport => "Co",
+            StationType::AsteroidBase => "As",
+            StationType::MegaShip => "MS",
+            StationType::Outpost => "Op",
+            StationType::PlanetaryPort => "PP",
+            StationType::PlanetaryOutpost => "PO",
+            StationType::OdysseySettlement => "OS",
+            StationType::FleetCarrier => "FC",
+            StationType::DrakeClassCarrier => "DC",
+            StationType::Unknown(_) => "??",
         }
     }
 }
@@ -283,11 +1240,28 @@ impl fmt::Display for StationType {
             StationType::Outpost => write!(f, "Outpost"),
             StationType::PlanetaryPort => write!(f, "PlanetaryPort"),
             StationType::PlanetaryOutpost => write!(f, "PlanetaryOutpost"),
+            StationType::OdysseySettlement => write!(f, "Settlement"),
+            StationType::FleetCarrier => write!(f, "FleetCarrier"),
+            StationType::DrakeClassCarrier => write!(f, "DrakeCarrier"),
+            StationType::Unknown(name) => write!(f, "{}", name),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+impl Serialize for StationType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for StationType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<StationType, D::Error> {
+        let name = Cow::<str>::deserialize(deserializer)?;
+        Ok(StationType::from_name(&name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum Economy {
     Agriculture,
     Colony,
@@ -304,3 +1278,35 @@ pub enum Economy {
     Terraforming,
     Tourism,
 }
+
+#[cfg(test)]
+impl Station {
+    /// Outpost updated `information` days ago, and `market` days ago if any.
+    pub(crate) fn for_test(information: i64, market: Option<i64>) -> Station {
+        use chrono::Duration;
+        use serde_json::{from_value, json};
+
+        let date = |days: i64| {
+            (Utc::now() - Duration::days(days))
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        };
+        from_value(json!({
+            "distanceToArrival": 100.0,
+            "economy": null,
+            "marketId": 1,
+            "name": "Test Station",
+            "secondEconomy": null,
+            "type": "Outpost",
+            "systemId": 1,
+            "systemName": "Test System",
+            "updateTime": {
+                "information": date(information),
+                "market": market.map(date),
+                "shipyard": null,
+                "outfitting": null,
+            },
+        }))
+        .unwrap()
+    }
+}
@@ -0,0 +1,54 @@
+//! Little-endian primitives of binary caches.
+
+use std::io::{self, Read, Write};
+
+pub fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn read_f64(r: &mut impl Read) -> io::Result<f64> {
+    Ok(f64::from_bits(read_u64(r)?))
+}
+
+pub fn read_string(r: &mut impl Read) -> io::Result<String> {
+    let len = read_u32(r)? as usize;
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn read_opt_string(r: &mut impl Read) -> io::Result<Option<String>> {
+    if read_u8(r)? != 0 {
+        Ok(Some(read_string(r)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn write_string(w: &mut impl Write, s: &str) -> io::Result<()> {
+    w.write_all(&(s.len() as u32).to_le_bytes())?;
+    w.write_all(s.as_bytes())
+}
+
+pub fn write_opt_string(w: &mut impl Write, s: &Option<String>) -> io::Result<()> {
+    if let Some(s) = s {
+        w.write_all(&[1])?;
+        write_string(w, s)
+    } else {
+        w.write_all(&[0])
+    }
+}
@@ -1,13 +1,58 @@
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, NaiveDateTime, ParseError, Utc};
 use serde::{self, Deserialize, Deserializer};
 
-const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// Naive time in UTC, which dumps have used. Fractional seconds are optional.
+const FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+/// `FORMAT` with timezone offset such as `+00:00`.
+const OFFSET_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f%:z";
+/// ISO 8601 without timezone, read as UTC.
+const ISO_NAIVE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
 
 pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
-    Utc.datetime_from_str(&s, FORMAT)
-        .map_err(serde::de::Error::custom)
+    parse(&s).map_err(serde::de::Error::custom)
+}
+
+/// Parse time in dumps, detecting format of each value.
+///
+/// Accepts `2019-10-01 12:34:56`, with offset such as `2019-10-01 12:34:56+00:00`, and ISO 8601
+/// such as `2019-10-01T12:34:56.789+09:00`, so that a format change of dumps doesn't break loading.
+pub(super) fn parse(s: &str) -> Result<DateTime<Utc>, ParseError> {
+    if !s.contains('T') {
+        return DateTime::parse_from_str(s, OFFSET_FORMAT)
+            .map(|t| t.with_timezone(&Utc))
+            .or_else(|_| NaiveDateTime::parse_from_str(s, FORMAT).map(|t| t.and_utc()));
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, ISO_NAIVE_FORMAT).map(|t| t.and_utc()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn parse_formats() {
+        let expected = Utc.with_ymd_and_hms(2019, 10, 1, 12, 34, 56).unwrap();
+        assert_eq!(parse("2019-10-01 12:34:56"), Ok(expected));
+        assert_eq!(parse("2019-10-01 12:34:56+00:00"), Ok(expected));
+        assert_eq!(parse("2019-10-01 21:34:56+09:00"), Ok(expected));
+        assert_eq!(parse("2019-10-01T12:34:56Z"), Ok(expected));
+        assert_eq!(parse("2019-10-01T21:34:56+09:00"), Ok(expected));
+        assert_eq!(parse("2019-10-01T12:34:56"), Ok(expected));
+
+        let precise = expected + Duration::milliseconds(789);
+        assert_eq!(parse("2019-10-01 12:34:56.789"), Ok(precise));
+        assert_eq!(parse("2019-10-01 12:34:56.789+00:00"), Ok(precise));
+        assert_eq!(parse("2019-10-01T12:34:56.789Z"), Ok(precise));
+
+        assert!(parse("2019/10/01 12:34:56").is_err());
+        assert!(parse("").is_err());
+    }
 }
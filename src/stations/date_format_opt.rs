@@ -1,16 +1,13 @@
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, Utc};
 use serde::{self, Deserialize, Deserializer};
 
-const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+use super::date_format::parse;
 
 pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s: Option<String> = Deserialize::deserialize(deserializer)?;
-    s.map(|s| {
-        Utc.datetime_from_str(&s, FORMAT)
-            .map_err(serde::de::Error::custom)
-    })
-    .transpose()
+    s.map(|s| parse(&s).map_err(serde::de::Error::custom))
+        .transpose()
 }
@@ -1,222 +1,79 @@
-use std::collections::BTreeMap;
-use std::fs::File;
-use std::io::{self, BufWriter, Write};
-use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::fs::metadata;
+use std::net::IpAddr;
+use std::path::Path;
 
-use chrono::{DateTime, FixedOffset};
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH, LAST_MODIFIED, USER_AGENT};
-use reqwest::Client;
-use serde_json::{from_reader, to_writer_pretty};
-use tiny_fail::{ErrorMessageExt, Fail};
+use chrono::{DateTime, FixedOffset, Utc};
+#[cfg(not(feature = "native"))]
+use tiny_fail::Fail;
 
-const TIMEOUT_SECS: u64 = 10;
-const BAR_TICK_SIZE: u64 = 32 * 1024;
+#[cfg(feature = "native")]
+mod http;
 
-pub struct Downloader {
-    get_client: Client,
-    head_client: Client,
-    etags: EtagStoreage,
-}
-
-impl Downloader {
-    pub fn new() -> Result<Downloader, Fail> {
-        let mut default_headers = HeaderMap::new();
-        default_headers.insert(
-            USER_AGENT,
-            format!(
-                "EDSM Dumps Downloader/{}",
-                option_env!("CARGO_PKG_VERSION").unwrap_or("unknown version")
-            )
-            .parse()
-            .unwrap(),
-        );
+#[cfg(feature = "native")]
+pub use http::Downloader;
 
-        let get_client = Client::builder()
-            .default_headers(default_headers.clone())
-            .connect_timeout(Some(Duration::from_secs(TIMEOUT_SECS)))
-            .gzip(true)
-            .build()?;
+/// Stub of downloader, for builds without network access such as WebAssembly.
+#[cfg(not(feature = "native"))]
+pub struct Downloader;
 
-        let head_client = Client::builder()
-            .default_headers(default_headers)
-            .connect_timeout(Some(Duration::from_secs(TIMEOUT_SECS)))
-            .gzip(false)
-            .build()?;
-
-        Ok(Downloader {
-            get_client,
-            head_client,
-            etags: EtagStoreage::new("./.cache.json"),
-        })
+#[cfg(not(feature = "native"))]
+impl Downloader {
+    pub fn new(_network: &NetworkOptions) -> Result<Downloader, Fail> {
+        Err(Fail::new(
+            "downloading is not supported in this build (enable 'native' feature)",
+        ))
     }
 
     pub fn download(
         &self,
-        file_name: &str,
-        url: &str,
+        _file_name: &str,
+        _url: &str,
     ) -> Result<Option<DateTime<FixedOffset>>, Fail> {
-        // check update and get size
-        let spin_style = ProgressStyle::default_spinner().template("{spinner} {msg}");
-
-        let prog_bar = ProgressBar::new_spinner();
-        prog_bar.set_style(spin_style.clone());
-        prog_bar.enable_steady_tick(100);
-        prog_bar.set_message("Checking update");
-
-        let mut req = self.head_client.get(url);
-
-        if let Some(etag) = self.etags.get(url)? {
-            req = req.header(IF_NONE_MATCH, etag);
-        }
-
-        let res = req.send()?.error_for_status()?;
-
-        let last_mod = res
-            .headers()
-            .get(LAST_MODIFIED)
-            .map(HeaderValue::to_str)
-            .transpose()?
-            .map(DateTime::parse_from_rfc2822)
-            .transpose()?;
-
-        if res.status().as_u16() == 304 {
-            prog_bar.finish_and_clear();
-            return Ok(last_mod);
-        }
-
-        let size = res.content_length();
-        prog_bar.finish_and_clear();
-
-        // download
-        let prog_bar = if let Some(size) = size {
-            let prog_bar = ProgressBar::new(size);
-            prog_bar.set_style(ProgressStyle::default_bar().template("{msg} [{bar:40.white/black}] {bytes}/{total_bytes}, {bytes_per_sec}, {eta_precise}"));
-            prog_bar
-        } else {
-            let prog_bar = ProgressBar::new_spinner();
-            prog_bar.set_style(spin_style);
-            prog_bar
-        };
-        prog_bar.set_draw_delta(BAR_TICK_SIZE);
-        prog_bar.set_message("Coneccting");
-
-        let req = self.get_client.get(url);
-
-        let mut res = req.send()?.error_for_status()?;
-
-        prog_bar.set_message(file_name.trim_end_matches(".json.gz"));
-        let f = File::create(file_name)?;
-        let mut w: ProgressWriter<Box<dyn Write>> = if file_name.ends_with(".gz") {
-            ProgressWriter::new(Box::new(BufWriter::new(f)), prog_bar)
-        } else {
-            ProgressWriter::new(Box::new(GzEncoder::new(f, Compression::best())), prog_bar)
-        };
-
-        res.copy_to(&mut w)?;
-        let prog_bar = w.finalize()?;
-
-        // save ETag
-        prog_bar.set_message("Saving cache info");
-        if let Some(etag) = res.headers().get(ETAG) {
-            let etag = etag.to_str().err_msg("can't parse ETag as string")?;
-            self.etags.save(url, etag)?;
-        } else {
-            self.etags.remove(url)?;
-        }
-
-        prog_bar.finish_with_message("Downloaded");
-        Ok(last_mod)
+        unreachable!("stub downloader can't be created")
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct EtagStoreage {
-    path: PathBuf,
-}
-
-impl EtagStoreage {
-    pub fn new<P: AsRef<Path>>(path: P) -> EtagStoreage {
-        EtagStoreage {
-            path: path.as_ref().to_owned(),
-        }
-    }
-
-    pub fn get(&self, url: &str) -> Result<Option<String>, Fail> {
-        if self.path.exists() {
-            let f = File::open(&self.path).err_msg(format!("can't open file: {:?}", self.path))?;
-            let mut table: BTreeMap<String, String> =
-                from_reader(f).err_msg("can't parse ETag file")?;
-
-            Ok(table.remove(url))
-        } else {
-            Ok(None)
-        }
-    }
-
-    pub fn save(&self, url: &str, etag: &str) -> Result<(), Fail> {
-        let mut table: BTreeMap<String, String> = if self.path.exists() {
-            let f = File::open(&self.path).err_msg(format!("can't open file: {:?}", self.path))?;
-            from_reader(f).err_msg("can't parse ETag file")?
-        } else {
-            BTreeMap::new()
-        };
-
-        table.insert(url.to_owned(), etag.to_owned());
-
-        let mut f =
-            File::create(&self.path).err_msg(format!("can't create file: {:?}", self.path))?;
-        to_writer_pretty(&mut f, &table).err_msg("can't encode ETag file")?;
-
-        Ok(())
+    pub fn download_from(
+        &self,
+        _file_name: &str,
+        _urls: &[String],
+    ) -> Result<Option<DateTime<FixedOffset>>, Fail> {
+        unreachable!("stub downloader can't be created")
     }
 
-    pub fn remove(&self, url: &str) -> Result<(), Fail> {
-        let mut table: BTreeMap<String, String> = if self.path.exists() {
-            let f = File::open(&self.path).err_msg(format!("can't open file: {:?}", self.path))?;
-            from_reader(f).err_msg("can't parse ETag file")?
-        } else {
-            BTreeMap::new()
-        };
-
-        table.remove(url);
-
-        let mut f =
-            File::create(&self.path).err_msg(format!("can't create file: {:?}", self.path))?;
-        to_writer_pretty(&mut f, &table).err_msg("can't encode ETag file")?;
-
-        Ok(())
+    pub fn check_all(&self, _dumps: &[(&str, &[String])]) -> Vec<Freshness> {
+        unreachable!("stub downloader can't be created")
     }
 }
 
-struct ProgressWriter<W: Write> {
-    inner: W,
-    prog: ProgressBar,
+/// Update state of a dump on server, checked without downloading.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Freshness {
+    /// Cached file is the latest. Has last modified time of the file.
+    UpToDate(Option<DateTime<FixedOffset>>),
+    /// Server has newer file, or nothing is cached yet.
+    Modified(Option<DateTime<FixedOffset>>),
+    /// All servers are unavailable, with the last error.
+    Unavailable(String),
 }
 
-impl<W: Write> ProgressWriter<W> {
-    fn new(inner: W, prog: ProgressBar) -> ProgressWriter<W> {
-        ProgressWriter { inner, prog }
-    }
-
-    fn finalize(mut self) -> Result<ProgressBar, io::Error> {
-        self.inner.flush()?;
-        self.prog.tick();
-        Ok(self.prog)
-    }
+/// Network settings for downloading.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkOptions {
+    /// Addresses used instead of DNS lookup, by host name.
+    pub hosts: HashMap<String, IpAddr>,
+    /// Connect only by IPv4 or IPv6.
+    pub ip_version: Option<IpVersion>,
 }
 
-impl<W: Write> Write for ProgressWriter<W> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let n = self.inner.write(buf)?;
-        self.prog.inc(n as u64);
-        Ok(n)
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.inner.flush()
-    }
+/// Modified time of downloaded file.
+pub(super) fn cached_time(path: &Path) -> Option<DateTime<Utc>> {
+    let modified = metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(DateTime::<Utc>::from(modified))
 }
@@ -0,0 +1,483 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{rename, File};
+use std::io::{self, BufWriter, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indicatif::{ProgressBar, ProgressStyle};
+use lazy_static::lazy_static;
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH, LAST_MODIFIED, USER_AGENT};
+use reqwest::StatusCode;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::diagnostics::{warn, Warning};
+use crate::lock::LockFile;
+use crate::paths::{cache_file, lock_cache};
+use crate::state::StateFile;
+
+use super::{cached_time, Freshness, IpVersion, NetworkOptions};
+
+const TIMEOUT_SECS: u64 = 10;
+const BAR_TICK_SIZE: u64 = 32 * 1024;
+/// Results of `Downloader::check_all` older than this are checked again before downloading.
+const CHECK_EXPIRY: Duration = Duration::from_secs(10 * 60);
+
+lazy_static! {
+    /// Results of `Downloader::check_all` by URL, taken by the following download.
+    static ref CHECKED: Mutex<HashMap<String, (Instant, Checked)>> = Mutex::new(HashMap::new());
+}
+
+/// Downloader of files into cache directory.
+///
+/// Cache directory is locked while `Downloader` lives, so that other instances don't rewrite files in use.
+pub struct Downloader {
+    get_client: Client,
+    head_client: Client,
+    etags: EtagStoreage,
+    network: NetworkOptions,
+    _lock: Arc<LockFile>,
+}
+
+impl Downloader {
+    pub fn new(network: &NetworkOptions) -> Result<Downloader, Fail> {
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(
+            USER_AGENT,
+            format!(
+                "EDSM Dumps Downloader/{}",
+                option_env!("CARGO_PKG_VERSION").unwrap_or("unknown version")
+            )
+            .parse()
+            .unwrap(),
+        );
+
+        let local_address = network.ip_version.map(IpVersion::unspecified);
+
+        let get_client = network
+            .pin_hosts(Client::builder())
+            .default_headers(default_headers.clone())
+            .connect_timeout(Duration::from_secs(TIMEOUT_SECS))
+            .local_address(local_address)
+            .gzip(true)
+            .build()?;
+
+        let head_client = network
+            .pin_hosts(Client::builder())
+            .default_headers(default_headers)
+            .connect_timeout(Duration::from_secs(TIMEOUT_SECS))
+            .local_address(local_address)
+            .gzip(false)
+            .build()?;
+
+        let lock = lock_cache()?;
+
+        Ok(Downloader {
+            get_client,
+            head_client,
+            etags: EtagStoreage::new(cache_file(".cache.json")),
+            network: network.clone(),
+            _lock: lock,
+        })
+    }
+
+    /// Download `url` to `file_name` in cache directory if updated.
+    ///
+    /// If the server is unavailable and `file_name` already exists, the existing file is used with warning.
+    pub fn download(
+        &self,
+        file_name: &str,
+        url: &str,
+    ) -> Result<Option<DateTime<FixedOffset>>, Fail> {
+        self.download_from(file_name, &[url.to_owned()])
+    }
+
+    /// Download `file_name` from first available URL in `urls`.
+    ///
+    /// If all servers are unavailable and `file_name` already exists, the existing file is used with warning.
+    pub fn download_from(
+        &self,
+        file_name: &str,
+        urls: &[String],
+    ) -> Result<Option<DateTime<FixedOffset>>, Fail> {
+        let mut last_err = Fail::new("no URL is configured");
+        for url in urls {
+            match self.try_download(file_name, url) {
+                Ok(last_mod) => return Ok(last_mod),
+                Err(DownloadError::Unavailable(e)) => last_err = e.msg(url),
+                Err(DownloadError::Fatal(e)) => return Err(e.msg(url)),
+            }
+        }
+
+        let cached_at = match cached_time(&cache_file(file_name)) {
+            Some(t) => t,
+            None => return Err(last_err),
+        };
+        let days = Utc::now().signed_duration_since(cached_at).num_days();
+        warn(Warning::ServerUnavailable(last_err.to_string(), days));
+        Ok(Some(cached_at.fixed_offset()))
+    }
+
+    /// Check updates of `dumps`, pairs of file name and URLs, concurrently without downloading.
+    ///
+    /// Each dump is checked at the first available URL. Results are reused by the following downloads.
+    pub fn check_all(&self, dumps: &[(&str, &[String])]) -> Vec<Freshness> {
+        let handles: Vec<_> = dumps
+            .iter()
+            .map(|(_, urls)| {
+                let client = self.head_client.clone();
+                let network = self.network.clone();
+                let etags = self.etags.clone();
+                let urls = urls.to_vec();
+                thread::spawn(move || check_first(&client, &network, &etags, &urls))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join().unwrap_or_else(|_| {
+                    Freshness::Unavailable("checking thread panicked".to_owned())
+                })
+            })
+            .collect()
+    }
+
+    fn try_download(
+        &self,
+        file_name: &str,
+        url: &str,
+    ) -> Result<Option<DateTime<FixedOffset>>, DownloadError> {
+        let spin_style = ProgressStyle::default_spinner().template("{spinner} {msg}");
+
+        // check update and get size, unless checked just before
+        let checked = match take_checked(url) {
+            Some(checked) => checked,
+            None => {
+                let prog_bar = ProgressBar::new_spinner();
+                prog_bar.set_style(spin_style.clone());
+                prog_bar.enable_steady_tick(100);
+                prog_bar.set_message("Checking update");
+                let checked = check(&self.head_client, &self.network, &self.etags, url);
+                prog_bar.finish_and_clear();
+                checked?
+            }
+        };
+        let last_mod = checked.last_mod;
+        if !checked.modified {
+            return Ok(last_mod);
+        }
+        let size = checked.size;
+
+        // download
+        let prog_bar = if let Some(size) = size {
+            let prog_bar = ProgressBar::new(size);
+            prog_bar.set_style(ProgressStyle::default_bar().template("{msg} [{bar:40.white/black}] {bytes}/{total_bytes}, {bytes_per_sec}, {eta_precise}"));
+            prog_bar
+        } else {
+            let prog_bar = ProgressBar::new_spinner();
+            prog_bar.set_style(spin_style);
+            prog_bar
+        };
+        prog_bar.set_draw_delta(BAR_TICK_SIZE);
+        prog_bar.set_message("Coneccting");
+
+        self.network.resolve(url)?;
+        let mut res = self
+            .get_client
+            .get(url)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .map_err(DownloadError::from_reqwest)?;
+
+        // Write to temporary file not to break existing file on failure.
+        let path = cache_file(file_name);
+        let part_file_name = cache_file(format!("{}.part", file_name));
+        prog_bar.set_message(file_name.trim_end_matches(".json.gz"));
+        let f = File::create(&part_file_name)?;
+        let mut w: ProgressWriter<Box<dyn Write>> = if file_name.ends_with(".gz") {
+            ProgressWriter::new(Box::new(BufWriter::new(f)), prog_bar)
+        } else {
+            ProgressWriter::new(Box::new(GzEncoder::new(f, Compression::best())), prog_bar)
+        };
+
+        res.copy_to(&mut w).map_err(DownloadError::from_reqwest)?;
+        let prog_bar = w.finalize()?;
+        rename(&part_file_name, &path)?;
+
+        // save ETag
+        prog_bar.set_message("Saving cache info");
+        if let Some(etag) = res.headers().get(ETAG) {
+            let etag = etag.to_str().err_msg("can't parse ETag as string")?;
+            self.etags.save(url, etag)?;
+        } else {
+            self.etags.remove(url)?;
+        }
+
+        prog_bar.finish_with_message("Downloaded");
+        Ok(last_mod)
+    }
+}
+
+/// Update state of a URL, got by conditional request.
+#[derive(Debug, Clone)]
+struct Checked {
+    modified: bool,
+    last_mod: Option<DateTime<FixedOffset>>,
+    size: Option<u64>,
+}
+
+/// Send conditional request with saved ETag, reading only headers.
+fn check(
+    client: &Client,
+    network: &NetworkOptions,
+    etags: &EtagStoreage,
+    url: &str,
+) -> Result<Checked, DownloadError> {
+    network.resolve(url)?;
+    let mut req = client.get(url);
+
+    if let Some(etag) = etags.get(url)? {
+        req = req.header(IF_NONE_MATCH, etag);
+    }
+
+    let res = req
+        .send()
+        .and_then(|res| res.error_for_status())
+        .map_err(DownloadError::from_reqwest)?;
+
+    let last_mod = res
+        .headers()
+        .get(LAST_MODIFIED)
+        .map(HeaderValue::to_str)
+        .transpose()?
+        .map(DateTime::parse_from_rfc2822)
+        .transpose()?;
+
+    Ok(Checked {
+        modified: res.status().as_u16() != 304,
+        last_mod,
+        size: res.content_length(),
+    })
+}
+
+/// Check `urls` in order until one is available, and keep the result for `try_download`.
+fn check_first(
+    client: &Client,
+    network: &NetworkOptions,
+    etags: &EtagStoreage,
+    urls: &[String],
+) -> Freshness {
+    let mut last_err = Fail::new("no URL is configured");
+    for url in urls {
+        match check(client, network, etags, url) {
+            Ok(checked) => {
+                let freshness = if checked.modified {
+                    Freshness::Modified(checked.last_mod)
+                } else {
+                    Freshness::UpToDate(checked.last_mod)
+                };
+                CHECKED
+                    .lock()
+                    .unwrap()
+                    .insert(url.clone(), (Instant::now(), checked));
+                return freshness;
+            }
+            Err(DownloadError::Unavailable(e)) | Err(DownloadError::Fatal(e)) => {
+                last_err = e.msg(url)
+            }
+        }
+    }
+    Freshness::Unavailable(last_err.to_string())
+}
+
+/// Result of `check_all` for `url` if not expired. Each result is used once.
+fn take_checked(url: &str) -> Option<Checked> {
+    let (at, checked) = CHECKED.lock().unwrap().remove(url)?;
+    if at.elapsed() < CHECK_EXPIRY {
+        Some(checked)
+    } else {
+        None
+    }
+}
+
+impl NetworkOptions {
+    /// Connect to pinned hosts by their addresses.
+    ///
+    /// URLs keep the host names, so that TLS verifies certificates for them.
+    fn pin_hosts(&self, builder: ClientBuilder) -> ClientBuilder {
+        self.hosts.iter().fold(builder, |builder, (host, &addr)| {
+            // Port is taken from URL.
+            builder.resolve(host, SocketAddr::new(addr, 0))
+        })
+    }
+
+    /// Check that host of `url` can be resolved.
+    ///
+    /// Host names are resolved here (unless pinned), to report DNS failures separately from connection failures.
+    fn resolve(&self, url: &str) -> Result<(), DownloadError> {
+        let (host, port) = match split_host(url) {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        if self.hosts.contains_key(host) {
+            return Ok(());
+        }
+
+        let addrs = (host, port).to_socket_addrs().map_err(|e| {
+            DownloadError::Unavailable(Fail::new(format!("can't resolve host {}: {}", host, e)))
+        })?;
+        if let Some(version) = self.ip_version {
+            if !addrs.map(|a| a.ip()).any(|ip| version.matches(ip)) {
+                return Err(DownloadError::Unavailable(Fail::new(format!(
+                    "host {} has no {:?} address",
+                    host, version
+                ))));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl IpVersion {
+    fn matches(self, ip: IpAddr) -> bool {
+        match self {
+            IpVersion::V4 => ip.is_ipv4(),
+            IpVersion::V6 => ip.is_ipv6(),
+        }
+    }
+
+    /// Local address to bind, which limits connections to this version.
+    fn unspecified(self) -> IpAddr {
+        match self {
+            IpVersion::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpVersion::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+}
+
+/// Host and port number in `url`. IPv6 literal hosts are not supported.
+fn split_host(url: &str) -> Option<(&str, u16)> {
+    let scheme_end = url.find("://")?;
+    let default_port = match &url[..scheme_end] {
+        "http" => 80,
+        "https" => 443,
+        _ => return None,
+    };
+
+    let start = scheme_end + 3;
+    let authority_end = url[start..].find('/').map_or(url.len(), |i| start + i);
+    let authority = &url[start..authority_end];
+    if authority.contains('@') || authority.starts_with('[') {
+        return None;
+    }
+
+    match authority.find(':') {
+        Some(i) => {
+            let port = authority[i + 1..].parse().ok()?;
+            Some((&authority[..i], port))
+        }
+        None => Some((authority, default_port)),
+    }
+}
+
+#[derive(Debug)]
+enum DownloadError {
+    /// Server is down or unreachable. Cached file can be used.
+    Unavailable(Fail),
+    Fatal(Fail),
+}
+
+impl DownloadError {
+    /// Errors of the server or the connection are `Unavailable`, so that other mirrors or cached file can be used.
+    fn from_reqwest(e: reqwest::Error) -> DownloadError {
+        let unavailable_status = e.status().is_some_and(|s| {
+            s.is_server_error()
+                || s == StatusCode::TOO_MANY_REQUESTS
+                || s == StatusCode::REQUEST_TIMEOUT
+        });
+        if e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() || unavailable_status {
+            DownloadError::Unavailable(e.into())
+        } else {
+            DownloadError::Fatal(e.into())
+        }
+    }
+}
+
+impl<E: Into<Fail>> From<E> for DownloadError {
+    fn from(e: E) -> DownloadError {
+        DownloadError::Fatal(e.into())
+    }
+}
+
+/// Format version of ETag file.
+const ETAG_VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct EtagStoreage {
+    file: StateFile,
+}
+
+impl EtagStoreage {
+    pub fn new<P: AsRef<Path>>(path: P) -> EtagStoreage {
+        EtagStoreage {
+            file: StateFile::new(path, "ETag", ETAG_VERSION).pretty(),
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Result<Option<String>, Fail> {
+        Ok(self.table()?.remove(url))
+    }
+
+    pub fn save(&self, url: &str, etag: &str) -> Result<(), Fail> {
+        let mut table = self.table()?;
+        table.insert(url.to_owned(), etag.to_owned());
+        self.file.save(&table)
+    }
+
+    pub fn remove(&self, url: &str) -> Result<(), Fail> {
+        let mut table = self.table()?;
+        table.remove(url);
+        self.file.save(&table)
+    }
+
+    fn table(&self) -> Result<BTreeMap<String, String>, Fail> {
+        Ok(self.file.load()?.unwrap_or_default())
+    }
+}
+
+struct ProgressWriter<W: Write> {
+    inner: W,
+    prog: ProgressBar,
+}
+
+impl<W: Write> ProgressWriter<W> {
+    fn new(inner: W, prog: ProgressBar) -> ProgressWriter<W> {
+        ProgressWriter { inner, prog }
+    }
+
+    fn finalize(mut self) -> Result<ProgressBar, io::Error> {
+        self.inner.flush()?;
+        self.prog.tick();
+        Ok(self.prog)
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.prog.inc(n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use serde_json::from_str;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use super::{load_systems, open_dump, stations_dump_file, LoadOptions};
+
+/// Write stations within `radius` Ly from `around` system to `output` in the same format as the stations dump.
+///
+/// Returns the number of written stations.
+pub fn extract(
+    opts: &LoadOptions,
+    around: &str,
+    radius: f64,
+    output: &Path,
+) -> Result<usize, Fail> {
+    let (stations_path, _) = stations_dump_file(opts)?;
+    let systems = load_systems(opts, false)?;
+
+    let center = systems
+        .by_name(around)
+        .err_msg(format!("unknown system: {}", around))?
+        .coords;
+
+    let mut r = open_dump(stations_path)?;
+
+    let f = File::create(output).err_msg(format!("can't create file: {:?}", output))?;
+    let mut w = BufWriter::new(GzEncoder::new(f, Compression::best()));
+
+    writeln!(w, "[")?;
+    let mut buf = String::new();
+    let mut count = 0usize;
+    loop {
+        buf.truncate(0);
+        if r.read_line(&mut buf)? == 0 {
+            break;
+        }
+
+        let s = buf.trim().trim_end_matches(',');
+        if s == "[" || s == "]" || s.is_empty() {
+            continue;
+        }
+
+        let st: SystemRef = from_str(s).map_err(|e| Fail::new(format!("{}: {}", e, s)))?;
+        let inside = systems
+            .by_id(st.system_id)
+            .map(|sys| sys.coords.dist_to(center) <= radius)
+            .unwrap_or(false);
+        if !inside {
+            continue;
+        }
+
+        if count > 0 {
+            writeln!(w, ",")?;
+        }
+        write!(w, "    {}", s)?;
+        count += 1;
+    }
+    writeln!(w, "\n]")?;
+
+    w.into_inner()
+        .map_err(|e| Fail::new(e.to_string()))?
+        .finish()?;
+
+    Ok(count)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SystemRef {
+    system_id: u64,
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::fs::{rename, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use super::binary::{read_u32, read_u64};
+use super::Station;
+
+const MAGIC: &[u8; 6] = b"NOSFPR";
+const VERSION: u32 = 1;
+
+/// Unix time of information update of each station, keyed by market ID.
+pub type Fingerprint = HashMap<u64, i64>;
+
+pub fn new<'a>(stations: impl Iterator<Item = &'a Station>) -> Fingerprint {
+    stations
+        .filter_map(|st| {
+            st.market_id
+                .map(|id| (id, st.update_time.information().timestamp()))
+        })
+        .collect()
+}
+
+/// Read fingerprint written by `write`. Returns `None` if written in another format version.
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Option<Fingerprint>, Fail> {
+    let f = File::open(&path).err_msg(format!("can't open file: {:?}", path.as_ref()))?;
+    let mut r = BufReader::new(GzDecoder::new(f));
+
+    let mut magic = [0u8; 6];
+    r.read_exact(&mut magic)
+        .err_msg("failed to read fingerprint header")?;
+    if &magic != MAGIC || read_u32(&mut r)? != VERSION {
+        return Ok(None);
+    }
+
+    let len = read_u64(&mut r)? as usize;
+    // Length of a broken file must not allocate too much.
+    let mut fingerprint = Fingerprint::with_capacity(len.min(1 << 20));
+    let mut id = 0;
+    for _ in 0..len {
+        id = read_u64(&mut r)
+            .err_msg("failed to decode fingerprint")?
+            .wrapping_add(id);
+        let time = read_u64(&mut r).err_msg("failed to decode fingerprint")?;
+        fingerprint.insert(id, time as i64);
+    }
+    Ok(Some(fingerprint))
+}
+
+/// Write fingerprint through temporary file.
+///
+/// IDs are sorted and saved as differences from the previous one, which compress well.
+pub fn write<P: AsRef<Path>>(path: P, fingerprint: &Fingerprint) -> Result<(), Fail> {
+    let path = path.as_ref();
+    let tmp = path.with_extension("tmp");
+    let f = File::create(&tmp).err_msg(format!("can't create file: {:?}", tmp))?;
+    let mut w = BufWriter::new(GzEncoder::new(f, Compression::default()));
+
+    let mut entries: Vec<(u64, i64)> = fingerprint.iter().map(|(&id, &t)| (id, t)).collect();
+    entries.sort_unstable();
+
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    w.write_all(&(entries.len() as u64).to_le_bytes())?;
+    let mut prev = 0;
+    for (id, time) in entries {
+        w.write_all(&(id - prev).to_le_bytes())?;
+        w.write_all(&(time as u64).to_le_bytes())?;
+        prev = id;
+    }
+
+    w.into_inner()
+        .map_err(|e| Fail::new(e.to_string()))?
+        .finish()?;
+    rename(&tmp, path).err_msg(format!("can't write file: {:?}", path))?;
+    Ok(())
+}
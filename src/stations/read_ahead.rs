@@ -0,0 +1,77 @@
+use std::io::{self, Read};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// Chunks decompressed ahead of the reader.
+const QUEUE_CHUNKS: usize = 8;
+
+/// Reader which reads `inner` in a background thread.
+///
+/// Gzip stream can't be decompressed in parallel, but decompression can run concurrently with parsing.
+pub struct ReadAhead {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ReadAhead {
+    pub fn spawn<R: Read + Send + 'static>(mut inner: R) -> ReadAhead {
+        let (tx, rx) = sync_channel(QUEUE_CHUNKS);
+        thread::spawn(move || loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let res = match read_full(&mut inner, &mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    chunk.truncate(n);
+                    Ok(chunk)
+                }
+                Err(e) => Err(e),
+            };
+            let failed = res.is_err();
+            // Receiver is dropped if reading is aborted.
+            if tx.send(res).is_err() || failed {
+                break;
+            }
+        });
+
+        ReadAhead {
+            rx,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len() {
+            match self.rx.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
+                }
+                // Sender is dropped at end of stream.
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let n = (&self.chunk[self.pos..]).read(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Read until `buf` is filled or end of stream.
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match r.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
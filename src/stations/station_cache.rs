@@ -0,0 +1,306 @@
+use std::fs::{rename, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use chrono::{DateTime, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{from_value, to_value, Value};
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use super::binary::{
+    read_f64, read_opt_string, read_string, read_u32, read_u64, read_u8, write_opt_string,
+    write_string,
+};
+use super::{LandingPads, Station, UpdateTime};
+use crate::coords::Coords;
+
+const MAGIC: &[u8; 6] = b"NOSSTA";
+const VERSION: u32 = 1;
+const RECORD: u8 = 1;
+const END: u8 = 0;
+
+/// Parsed stations dump and count of skipped bad records.
+pub type Parsed = (Vec<Station>, usize);
+
+/// Read parsed stations cache written with the same `key`.
+///
+/// Returns `None` if the file was written in another format version or for another dump.
+/// Fields not in dumps, such as coordinates, are left default.
+pub fn read<P: AsRef<Path>>(path: P, key: &str) -> Result<Option<Parsed>, Fail> {
+    let f = File::open(&path).err_msg(format!("can't open file: {:?}", path.as_ref()))?;
+    let mut r = BufReader::new(GzDecoder::new(f));
+
+    let mut magic = [0u8; 6];
+    r.read_exact(&mut magic)
+        .err_msg("failed to read stations cache header")?;
+    if &magic != MAGIC || read_u32(&mut r)? != VERSION || read_string(&mut r)? != key {
+        return Ok(None);
+    }
+
+    let skipped = read_u64(&mut r)? as usize;
+    let mut list = Vec::new();
+    while read_u8(&mut r).err_msg("failed to decode stations cache")? == RECORD {
+        list.push(read_station(&mut r).err_msg("failed to decode stations cache")?);
+    }
+    list.shrink_to_fit();
+
+    Ok(Some((list, skipped)))
+}
+
+/// Write parsed stations, through temporary file so that interrupted write leaves no truncated cache.
+pub fn write<P: AsRef<Path>>(
+    path: P,
+    key: &str,
+    list: &[Station],
+    skipped: usize,
+) -> Result<(), Fail> {
+    let path = path.as_ref();
+    let tmp = path.with_extension("tmp");
+    let f = File::create(&tmp).err_msg(format!("can't create file: {:?}", tmp))?;
+    let mut w = BufWriter::new(GzEncoder::new(f, Compression::fast()));
+
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    write_string(&mut w, key)?;
+    w.write_all(&(skipped as u64).to_le_bytes())?;
+    for st in list {
+        w.write_all(&[RECORD])?;
+        write_station(&mut w, st).err_msg("failed to encode stations cache")?;
+    }
+    w.write_all(&[END])?;
+
+    w.into_inner()
+        .map_err(|e| Fail::new(e.to_string()))?
+        .finish()?;
+    rename(&tmp, path).err_msg(format!("can't write file: {:?}", path))?;
+    Ok(())
+}
+
+fn read_station(r: &mut impl Read) -> io::Result<Station> {
+    Ok(Station {
+        coords: Coords::default(),
+        changed: false,
+        distance_to_arrival: read_opt(r, read_f64)?,
+        dump_date: None,
+        economy: read_opt(r, read_enum)?,
+        government: read_opt_string(r)?,
+        have_market: read_opt(r, read_bool)?,
+        have_outfitting: read_opt(r, read_bool)?,
+        have_shipyard: read_opt(r, read_bool)?,
+        landing_pads: read_opt(r, |r| {
+            Ok(LandingPads {
+                large: read_u32(r)?,
+                medium: read_u32(r)?,
+                small: read_u32(r)?,
+            })
+        })?,
+        market_id: read_opt(r, read_u64)?,
+        name: read_string(r)?,
+        power: read_opt_string(r)?,
+        primary_star: None,
+        refresh_interval: None,
+        second_economy: read_opt(r, read_enum)?,
+        security: read_opt_string(r)?,
+        state: read_opt_string(r)?,
+        st_type: read_enum(r)?,
+        system_id: read_u64(r)?,
+        system_name: read_string(r)?,
+        update_time: UpdateTime {
+            information: read_time(r)?,
+            market: read_opt(r, read_time)?,
+            shipyard: read_opt(r, read_time)?,
+            outfitting: read_opt(r, read_time)?,
+        },
+    })
+}
+
+fn write_station(w: &mut impl Write, st: &Station) -> io::Result<()> {
+    // Exhaustive, so that adding a field to `Station` fails here until it is stored or skipped.
+    // Bump VERSION when the stored fields change.
+    let Station {
+        coords: _,
+        changed: _,
+        distance_to_arrival,
+        dump_date: _,
+        economy,
+        government,
+        have_market,
+        have_outfitting,
+        have_shipyard,
+        landing_pads,
+        market_id,
+        name,
+        power,
+        primary_star: _,
+        refresh_interval: _,
+        second_economy,
+        security,
+        state,
+        st_type,
+        system_id,
+        system_name,
+        update_time,
+    } = st;
+
+    write_opt(w, distance_to_arrival, |w, v| {
+        w.write_all(&v.to_bits().to_le_bytes())
+    })?;
+    write_opt(w, economy, write_enum)?;
+    write_opt_string(w, government)?;
+    write_opt(w, have_market, write_bool)?;
+    write_opt(w, have_outfitting, write_bool)?;
+    write_opt(w, have_shipyard, write_bool)?;
+    write_opt(w, landing_pads, |w, pads| {
+        w.write_all(&pads.large.to_le_bytes())?;
+        w.write_all(&pads.medium.to_le_bytes())?;
+        w.write_all(&pads.small.to_le_bytes())
+    })?;
+    write_opt(w, market_id, |w, id| w.write_all(&id.to_le_bytes()))?;
+    write_string(w, name)?;
+    write_opt_string(w, power)?;
+    write_opt(w, second_economy, write_enum)?;
+    write_opt_string(w, security)?;
+    write_opt_string(w, state)?;
+    write_enum(w, st_type)?;
+    w.write_all(&system_id.to_le_bytes())?;
+    write_string(w, system_name)?;
+
+    let UpdateTime {
+        information,
+        market,
+        shipyard,
+        outfitting,
+    } = update_time;
+    write_time(w, information)?;
+    write_opt(w, market, write_time)?;
+    write_opt(w, shipyard, write_time)?;
+    write_opt(w, outfitting, write_time)?;
+    Ok(())
+}
+
+fn read_time(r: &mut impl Read) -> io::Result<DateTime<Utc>> {
+    Utc.timestamp_opt(read_u64(r)? as i64, 0)
+        .single()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "time out of range"))
+}
+
+fn write_time<W: Write>(w: &mut W, t: &DateTime<Utc>) -> io::Result<()> {
+    w.write_all(&(t.timestamp() as u64).to_le_bytes())
+}
+
+fn read_bool(r: &mut impl Read) -> io::Result<bool> {
+    Ok(read_u8(r)? != 0)
+}
+
+fn write_bool<W: Write>(w: &mut W, v: &bool) -> io::Result<()> {
+    w.write_all(&[*v as u8])
+}
+
+fn read_opt<R: Read, T>(
+    r: &mut R,
+    read: impl FnOnce(&mut R) -> io::Result<T>,
+) -> io::Result<Option<T>> {
+    if read_u8(r)? != 0 {
+        Ok(Some(read(r)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn write_opt<W: Write, T>(
+    w: &mut W,
+    v: &Option<T>,
+    write: impl FnOnce(&mut W, &T) -> io::Result<()>,
+) -> io::Result<()> {
+    match v {
+        Some(v) => {
+            w.write_all(&[1])?;
+            write(w, v)
+        }
+        None => w.write_all(&[0]),
+    }
+}
+
+/// Enums are kept by their names in dumps, so that adding variants doesn't break caches.
+fn read_enum<R: Read, T: DeserializeOwned>(r: &mut R) -> io::Result<T> {
+    from_value(Value::String(read_string(r)?))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_enum<W: Write, T: Serialize>(w: &mut W, v: &T) -> io::Result<()> {
+    match to_value(v) {
+        Ok(Value::String(s)) => write_string(w, &s),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "enum isn't serialized as string",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::process;
+
+    use super::*;
+    use crate::stations::{Economy, StationType};
+
+    /// Station with every stored field set, and fields not in dumps left default.
+    fn populated() -> Station {
+        Station {
+            coords: Coords::default(),
+            changed: false,
+            distance_to_arrival: Some(1234.5),
+            dump_date: None,
+            economy: Some(Economy::HighTech),
+            government: Some("Democracy".to_owned()),
+            have_market: Some(true),
+            have_outfitting: Some(false),
+            have_shipyard: None,
+            landing_pads: Some(LandingPads {
+                large: 1,
+                medium: 2,
+                small: 3,
+            }),
+            market_id: Some(3_228_000_000),
+            name: "Test Station".to_owned(),
+            power: Some("Zachary Hudson".to_owned()),
+            primary_star: None,
+            refresh_interval: None,
+            second_economy: Some(Economy::Industrial),
+            security: Some("High".to_owned()),
+            state: Some("Boom".to_owned()),
+            st_type: StationType::Unknown("Space Elevator".to_owned()),
+            system_id: 10_477_373_803,
+            system_name: "Sol".to_owned(),
+            update_time: UpdateTime {
+                information: Utc.timestamp_opt(1_600_000_000, 0).unwrap(),
+                market: Some(Utc.timestamp_opt(1_600_000_100, 0).unwrap()),
+                shipyard: Some(Utc.timestamp_opt(1_600_000_200, 0).unwrap()),
+                outfitting: Some(Utc.timestamp_opt(1_600_000_300, 0).unwrap()),
+            },
+        }
+    }
+
+    #[test]
+    fn populated_station_round_trip() {
+        let path = temp_dir().join(format!("near-old-stations-stations-{}.gz", process::id()));
+        let list = vec![populated(), Station::for_test(10, None)];
+        write(&path, "key", &list, 7).unwrap();
+
+        assert!(read(&path, "other key").unwrap().is_none());
+        let (got, skipped) = read(&path, "key").unwrap().unwrap();
+        remove_file(&path).unwrap();
+
+        assert_eq!(skipped, 7);
+        assert_eq!(got.len(), list.len());
+        assert_eq!(got[0], list[0]);
+        assert_eq!(got[1].name, list[1].name);
+        assert_eq!(got[1].update_time, list[1].update_time);
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs::{rename, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use super::binary::{
+    read_f64, read_opt_string, read_string, read_u32, read_u64, read_u8, write_opt_string,
+    write_string,
+};
+use super::{PrimaryStar, System};
+use crate::coords::Coords;
+
+const MAGIC: &[u8; 6] = b"NOSSYS";
+const VERSION: u32 = 4;
+/// Precedes each system. Count is not in the header, so that systems can be written while streaming.
+const RECORD: u8 = 1;
+const END: u8 = 0;
+
+/// Read system info cache written with the same `key`.
+///
+/// Returns `None` if the file was written in another format version or with another key.
+pub fn read<P: AsRef<Path>>(path: P, key: &str) -> Result<Option<Vec<System>>, Fail> {
+    let f = File::open(&path).err_msg(format!("can't open file: {:?}", path.as_ref()))?;
+    let mut r = BufReader::new(GzDecoder::new(f));
+
+    let mut magic = [0u8; 6];
+    r.read_exact(&mut magic)
+        .err_msg("failed to read system cache header")?;
+    if &magic != MAGIC || read_u32(&mut r)? != VERSION || read_string(&mut r)? != key {
+        return Ok(None);
+    }
+
+    let mut list = Vec::new();
+    while read_u8(&mut r).err_msg("failed to decode system cache")? == RECORD {
+        list.push(read_system(&mut r).err_msg("failed to decode system cache")?);
+    }
+    list.shrink_to_fit();
+
+    Ok(Some(list))
+}
+
+pub fn write<P: AsRef<Path>>(path: P, key: &str, list: &[System]) -> Result<(), Fail> {
+    let mut w = Writer::create(path, key)?;
+    for sys in list {
+        w.push(sys)?;
+    }
+    w.finish()
+}
+
+/// Writes system info cache one by one, so that whole dump needn't be in memory.
+///
+/// Written to temporary file and moved to the path by `finish`, so that interrupted write leaves no
+/// truncated cache.
+pub struct Writer {
+    w: BufWriter<GzEncoder<File>>,
+    tmp: PathBuf,
+    path: PathBuf,
+}
+
+impl Writer {
+    pub fn create<P: AsRef<Path>>(path: P, key: &str) -> Result<Writer, Fail> {
+        let path = path.as_ref().to_owned();
+        let tmp = path.with_extension("tmp");
+        let f = File::create(&tmp).err_msg(format!("can't create file: {:?}", tmp))?;
+        let mut w = BufWriter::new(GzEncoder::new(f, Compression::best()));
+
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        write_string(&mut w, key)?;
+        Ok(Writer { w, tmp, path })
+    }
+
+    pub fn push(&mut self, sys: &System) -> Result<(), Fail> {
+        self.w.write_all(&[RECORD])?;
+        write_system(&mut self.w, sys).err_msg("failed to encode system cache")
+    }
+
+    pub fn finish(mut self) -> Result<(), Fail> {
+        self.w.write_all(&[END])?;
+        self.w
+            .into_inner()
+            .map_err(|e| Fail::new(e.to_string()))?
+            .finish()?;
+        rename(&self.tmp, &self.path).err_msg(format!("can't write file: {:?}", self.path))?;
+        Ok(())
+    }
+}
+
+fn read_system(r: &mut impl Read) -> io::Result<System> {
+    Ok(System {
+        id: read_u64(r)?,
+        name: read_string(r)?,
+        coords: Coords::new(read_f64(r)?, read_f64(r)?, read_f64(r)?),
+        allegiance: read_opt_string(r)?,
+        government: read_opt_string(r)?,
+        population: if read_u8(r)? != 0 {
+            Some(read_u64(r)?)
+        } else {
+            None
+        },
+        power: read_opt_string(r)?,
+        security: read_opt_string(r)?,
+        primary_star: match read_opt_string(r)? {
+            Some(star_type) => Some(PrimaryStar {
+                star_type,
+                is_scoopable: match read_u8(r)? {
+                    1 => Some(false),
+                    2 => Some(true),
+                    _ => None,
+                },
+            }),
+            None => None,
+        },
+    })
+}
+
+fn write_system(w: &mut impl Write, sys: &System) -> io::Result<()> {
+    w.write_all(&sys.id.to_le_bytes())?;
+    write_string(w, &sys.name)?;
+    w.write_all(&sys.coords.x().to_bits().to_le_bytes())?;
+    w.write_all(&sys.coords.y().to_bits().to_le_bytes())?;
+    w.write_all(&sys.coords.z().to_bits().to_le_bytes())?;
+    write_opt_string(w, &sys.allegiance)?;
+    write_opt_string(w, &sys.government)?;
+    if let Some(population) = sys.population {
+        w.write_all(&[1])?;
+        w.write_all(&population.to_le_bytes())?;
+    } else {
+        w.write_all(&[0])?;
+    }
+    write_opt_string(w, &sys.power)?;
+    write_opt_string(w, &sys.security)?;
+    match sys.primary_star {
+        Some(ref star) => {
+            w.write_all(&[1])?;
+            write_string(w, &star.star_type)?;
+            let scoopable = match star.is_scoopable {
+                Some(false) => 1,
+                Some(true) => 2,
+                None => 0,
+            };
+            w.write_all(&[scoopable])?;
+        }
+        None => w.write_all(&[0])?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::{remove_file, write as write_file};
+    use std::path::PathBuf;
+    use std::process;
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        temp_dir().join(format!(
+            "near-old-stations-systems-{}-{}.gz",
+            name,
+            process::id()
+        ))
+    }
+
+    fn system(id: u64, star: Option<PrimaryStar>) -> System {
+        System {
+            id,
+            name: format!("System {}", id),
+            coords: Coords::new(-1.5, 2.25, f64::MAX),
+            allegiance: Some("Federation".to_owned()),
+            government: Some("Democracy".to_owned()),
+            population: Some(u64::MAX),
+            power: Some("Zachary Hudson".to_owned()),
+            security: Some("High".to_owned()),
+            primary_star: star,
+        }
+    }
+
+    #[test]
+    fn systems_round_trip() {
+        let star = |is_scoopable| PrimaryStar {
+            star_type: "K (Yellow-Orange) Star".to_owned(),
+            is_scoopable,
+        };
+        let bare = System {
+            id: 0,
+            name: String::new(),
+            coords: Coords::zero(),
+            allegiance: None,
+            government: None,
+            population: None,
+            power: None,
+            security: None,
+            primary_star: None,
+        };
+        let list = vec![
+            system(1, Some(star(None))),
+            system(2, Some(star(Some(true)))),
+            system(3, Some(star(Some(false)))),
+            bare,
+        ];
+
+        let path = temp_path("round-trip");
+        write(&path, "key", &list).unwrap();
+        assert_eq!(read(&path, "other key").unwrap(), None);
+        let got = read(&path, "key").unwrap();
+        remove_file(&path).unwrap();
+        assert_eq!(got, Some(list));
+    }
+
+    #[test]
+    fn other_format_is_not_read() {
+        let path = temp_path("other");
+        let mut w = BufWriter::new(GzEncoder::new(
+            File::create(&path).unwrap(),
+            Compression::fast(),
+        ));
+        w.write_all(MAGIC).unwrap();
+        w.write_all(&(VERSION - 1).to_le_bytes()).unwrap();
+        w.into_inner().unwrap().finish().unwrap();
+        assert_eq!(read(&path, "").unwrap(), None);
+
+        write_file(&path, b"").unwrap();
+        assert!(read(&path, "").is_err());
+        remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use serde_json::to_string_pretty;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::searcher::Record;
+
+/// Aggregated statistics of outdated stations, without any station, system, or commander names.
+///
+/// Stations are counted in cubes of `cell_size` Ly. Records must be searched in a region not depending
+/// on the commander, such as around Sol, because covered cells would reveal the origin.
+#[derive(Debug, Serialize)]
+pub struct Submission {
+    version: &'static str,
+    /// Date of the stations dump, without time.
+    dump_date: String,
+    cell_size: f64,
+    cells: Vec<Cell>,
+}
+
+#[derive(Debug, Serialize)]
+struct Cell {
+    /// Cube index, `floor(coordinate / cell_size)`.
+    x: i64,
+    y: i64,
+    z: i64,
+    outdated: usize,
+    max_days: i64,
+}
+
+impl Submission {
+    pub fn new(records: &[Record], cell_size: f64, dump_date: DateTime<Utc>) -> Submission {
+        assert!(cell_size > 0.0, "cell size must be positive");
+        let mut cells = BTreeMap::<(i64, i64, i64), (usize, i64)>::new();
+        for r in records {
+            let days = match r.outdated() {
+                Some(o) => o.days,
+                None => continue,
+            };
+            let c = r.station.coords;
+            let key = (
+                (c.x() / cell_size).floor() as i64,
+                (c.y() / cell_size).floor() as i64,
+                (c.z() / cell_size).floor() as i64,
+            );
+            let cell = cells.entry(key).or_insert((0, 0));
+            cell.0 += 1;
+            cell.1 = cell.1.max(days);
+        }
+
+        Submission {
+            version: env!("CARGO_PKG_VERSION"),
+            dump_date: dump_date.format("%F").to_string(),
+            cell_size,
+            cells: cells
+                .into_iter()
+                .map(|((x, y, z), (outdated, max_days))| Cell {
+                    x,
+                    y,
+                    z,
+                    outdated,
+                    max_days,
+                })
+                .collect(),
+        }
+    }
+
+    /// Exactly the body to be sent.
+    pub fn to_json(&self) -> Result<String, Fail> {
+        to_string_pretty(self).err_msg("can't encode submission")
+    }
+
+    pub fn cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn send(&self, url: &str) -> Result<(), Fail> {
+        Client::new()
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(self.to_json()?)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .err_msg(format!("failed to submit to {}", url))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::coords::Coords;
+    use crate::searcher::{new_record, Coverage};
+    use crate::stations::Station;
+    use crate::visited::Visited;
+
+    #[test]
+    fn cells_are_counted_by_floor() {
+        let mut near = Station::for_test(50, None);
+        near.coords = Coords::new(-0.5, 10.0, 99.0);
+        let mut far = Station::for_test(80, None);
+        far.coords = Coords::new(-99.0, 50.0, 0.0);
+        let fresh = Station::for_test(0, None);
+        let records: Vec<_> = [&near, &far, &fresh]
+            .iter()
+            .map(|st| {
+                let mut r = new_record(
+                    st,
+                    Coords::zero(),
+                    &Visited::new(),
+                    Coverage::default(),
+                    Utc::now(),
+                );
+                r.information_days.check(|d| d >= 30);
+                r
+            })
+            .collect();
+
+        let s = Submission::new(
+            &records,
+            100.0,
+            Utc.timestamp_opt(1_600_000_000, 0).unwrap(),
+        );
+        assert_eq!(s.cells(), 1);
+        assert_eq!((s.cells[0].x, s.cells[0].y, s.cells[0].z), (-1, 0, 0));
+        assert_eq!(s.cells[0].outdated, 2);
+        assert_eq!(s.cells[0].max_days, 80);
+        assert_eq!(s.dump_date, "2020-09-13");
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_cell_size() {
+        Submission::new(&[], 0.0, Utc::now());
+    }
+}
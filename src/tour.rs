@@ -0,0 +1,126 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::coords::Coords;
+use crate::searcher::Record;
+use crate::travel::TravelCost;
+
+/// Order `points` to visit starting from `origin`, not returning.
+///
+/// Built by nearest neighbor, then improved by 2-opt, both by travel time of `travel`.
+/// Returns indices of `points` in visit order.
+pub fn plan_tour(origin: Coords, points: &[Coords], travel: &dyn TravelCost) -> Vec<usize> {
+    let cost = |a: Coords, b: Coords| travel.cost(a.dist_to(b), 0.0);
+
+    let mut order = Vec::with_capacity(points.len());
+    let mut rest: Vec<usize> = (0..points.len()).collect();
+    let mut pos = origin;
+    while !rest.is_empty() {
+        let (k, _) = rest
+            .iter()
+            .enumerate()
+            .min_by(|(_, &l), (_, &r)| {
+                cost(pos, points[l])
+                    .partial_cmp(&cost(pos, points[r]))
+                    .unwrap()
+            })
+            .unwrap();
+        let next = rest.swap_remove(k);
+        pos = points[next];
+        order.push(next);
+    }
+
+    // 2-opt: reverse route positions i..=j while it shortens the route. Position 0 is origin.
+    let at = |order: &[usize], k: usize| -> Coords {
+        if k == 0 {
+            origin
+        } else {
+            points[order[k - 1]]
+        }
+    };
+    let n = order.len();
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 1..=n {
+            for j in (i + 1)..=n {
+                let (a, b, c) = (at(&order, i - 1), at(&order, i), at(&order, j));
+                let (mut before, mut after) = (cost(a, b), cost(a, c));
+                if j < n {
+                    let d = at(&order, j + 1);
+                    before += cost(c, d);
+                    after += cost(b, d);
+                }
+                if after + 1e-9 < before {
+                    order[i - 1..j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+
+    order
+}
+
+/// Total distance of visiting `points` in `order` from `origin`.
+pub fn tour_length(origin: Coords, points: &[Coords], order: &[usize]) -> f64 {
+    let mut pos = origin;
+    let mut total = 0.0;
+    for &i in order {
+        total += pos.dist_to(points[i]);
+        pos = points[i];
+    }
+    total
+}
+
+/// Distance from `origin` through each stop, visiting `points` in `order`.
+pub fn cumulative_lengths(origin: Coords, points: &[Coords], order: &[usize]) -> Vec<f64> {
+    let mut pos = origin;
+    let mut total = 0.0;
+    order
+        .iter()
+        .map(|&i| {
+            total += pos.dist_to(points[i]);
+            pos = points[i];
+            total
+        })
+        .collect()
+}
+
+/// Split route into at most `days` consecutive parts with roughly equal time.
+///
+/// `legs[i]` is the time to reach i-th stop from the previous one. Returns ranges of stops.
+pub fn split_days(legs: &[f64], days: usize) -> Vec<Range<usize>> {
+    let total: f64 = legs.iter().sum();
+    let mut parts = Vec::with_capacity(days);
+    let mut start = 0;
+    let mut acc = 0.0;
+    for (i, &t) in legs.iter().enumerate() {
+        let goal = total * (parts.len() + 1) as f64 / days as f64;
+        // Start next day here if it ends the day closer to the goal.
+        if parts.len() + 1 < days && i > start && acc + t - goal > goal - acc {
+            parts.push(start..i);
+            start = i;
+        }
+        acc += t;
+    }
+    if start < legs.len() {
+        parts.push(start..legs.len());
+    }
+    parts
+}
+
+/// Write route as `system<TAB>station` lines, the same format as selection export.
+pub fn export_route<P: AsRef<Path>>(path: P, records: &[&Record]) -> Result<(), Fail> {
+    let path = path.as_ref();
+    let f = File::create(path).err_msg(format!("can't create file: {:?}", path))?;
+    let mut w = BufWriter::new(f);
+    for r in records {
+        writeln!(w, "{}\t{}", r.station.system_name, r.station.name)?;
+    }
+    Ok(())
+}
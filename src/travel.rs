@@ -0,0 +1,211 @@
+/// Ly added to distance per Ls of arrival distance by default, which only breaks near ties.
+pub const DEFAULT_ARRIVAL_WEIGHT: f64 = 0.000_000_1;
+pub const DEFAULT_JUMP_RANGE: f64 = 30.0;
+pub const DEFAULT_SECS_PER_JUMP: f64 = 60.0;
+pub const DEFAULT_SECS_PER_STATION: f64 = 180.0;
+
+/// Range multiplier of a jump supercharged at a neutron star.
+const NEUTRON_BOOST: f64 = 4.0;
+/// Seconds to approach a neutron star and charge the FSD there, added to boosted jumps.
+const NEUTRON_SECS_PER_BOOST: f64 = 45.0;
+/// Supercruise seconds per square root of Ls, which fits times from 100 Ls to 100,000 Ls roughly.
+const SUPERCRUISE_SECS_PER_SQRT_LS: f64 = 2.0;
+/// Lower bound of costs, which keeps scores of stations at the origin finite.
+pub const MIN_SECS: f64 = 1.0;
+
+/// Estimated seconds to reach a station.
+///
+/// Implementations must return `MIN_SECS` or more, and NaN only for NaN input.
+pub trait TravelCost {
+    /// Seconds to travel `dist` Ly, then reach a station `arrival` Ls from the arrival star.
+    fn cost(&self, dist: f64, arrival: f64) -> f64;
+}
+
+/// Time proportional to straight-line distance, ignoring jumps and docking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StraightLine {
+    pub secs_per_ly: f64,
+    pub secs_per_ls: f64,
+}
+
+impl StraightLine {
+    /// `arrival_weight` is Ly added to distance per Ls of arrival distance.
+    pub fn new(jump_range: f64, secs_per_jump: f64, arrival_weight: f64) -> StraightLine {
+        let secs_per_ly = secs_per_jump / jump_range;
+        StraightLine {
+            secs_per_ly,
+            secs_per_ls: secs_per_ly * arrival_weight,
+        }
+    }
+}
+
+impl Default for StraightLine {
+    fn default() -> StraightLine {
+        StraightLine::new(
+            DEFAULT_JUMP_RANGE,
+            DEFAULT_SECS_PER_JUMP,
+            DEFAULT_ARRIVAL_WEIGHT,
+        )
+    }
+}
+
+impl TravelCost for StraightLine {
+    fn cost(&self, dist: f64, arrival: f64) -> f64 {
+        floor(dist * self.secs_per_ly + arrival * self.secs_per_ls)
+    }
+}
+
+/// Time of hyperspace jumps needed, plus fixed time to supercruise to, dock at and leave a station.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Jumps {
+    /// Jump range in Ly.
+    pub jump_range: f64,
+    /// Seconds per hyperspace jump, including charging and fuel scooping.
+    pub secs_per_jump: f64,
+    /// Seconds to supercruise to, dock at and leave a station.
+    pub secs_per_station: f64,
+    /// Seconds added per Ls of arrival distance, like `StraightLine`.
+    pub secs_per_ls: f64,
+}
+
+impl Jumps {
+    pub fn new(jump_range: f64) -> Jumps {
+        Jumps {
+            jump_range,
+            secs_per_jump: DEFAULT_SECS_PER_JUMP,
+            secs_per_station: DEFAULT_SECS_PER_STATION,
+            secs_per_ls: DEFAULT_SECS_PER_JUMP / jump_range * DEFAULT_ARRIVAL_WEIGHT,
+        }
+    }
+
+    fn jumps_secs(&self, dist: f64, range: f64) -> f64 {
+        (dist / range).ceil() * self.secs_per_jump
+    }
+}
+
+impl TravelCost for Jumps {
+    fn cost(&self, dist: f64, arrival: f64) -> f64 {
+        floor(
+            self.jumps_secs(dist, self.jump_range)
+                + self.secs_per_station
+                + arrival * self.secs_per_ls,
+        )
+    }
+}
+
+/// Like `Jumps`, but long trips use jumps supercharged at neutron stars when it is faster.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Neutron(pub Jumps);
+
+impl TravelCost for Neutron {
+    fn cost(&self, dist: f64, arrival: f64) -> f64 {
+        let Neutron(jumps) = self;
+        let plain = jumps.jumps_secs(dist, jumps.jump_range);
+        let boosted = (dist / (jumps.jump_range * NEUTRON_BOOST)).ceil()
+            * (jumps.secs_per_jump + NEUTRON_SECS_PER_BOOST);
+        floor(plain.min(boosted) + jumps.secs_per_station + arrival * jumps.secs_per_ls)
+    }
+}
+
+/// Like `Jumps`, but also counts supercruise from the arrival star by arrival distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Supercruise(pub Jumps);
+
+impl TravelCost for Supercruise {
+    fn cost(&self, dist: f64, arrival: f64) -> f64 {
+        let Supercruise(jumps) = self;
+        jumps.cost(dist, arrival) + arrival.max(0.0).sqrt() * SUPERCRUISE_SECS_PER_SQRT_LS
+    }
+}
+
+/// Raises `secs` to `MIN_SECS`, keeping NaN.
+fn floor(secs: f64) -> f64 {
+    if secs < MIN_SECS {
+        MIN_SECS
+    } else {
+        secs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jumps() -> Jumps {
+        Jumps {
+            jump_range: 10.0,
+            secs_per_jump: 60.0,
+            secs_per_station: 100.0,
+            secs_per_ls: 0.0,
+        }
+    }
+
+    fn assert_floor(travel: &dyn TravelCost) {
+        assert!(travel.cost(0.0, 0.0) >= MIN_SECS);
+        assert!(travel.cost(-5.0, -5.0) >= MIN_SECS);
+        assert!(travel.cost(f64::NAN, 0.0).is_nan());
+    }
+
+    #[test]
+    fn straight_line() {
+        let travel = StraightLine::new(10.0, 60.0, 0.5);
+        assert_eq!(travel.cost(20.0, 0.0), 120.0);
+        assert_eq!(travel.cost(20.0, 10.0), 150.0);
+        assert_eq!(travel.cost(0.0, 0.0), MIN_SECS);
+        assert_floor(&travel);
+    }
+
+    #[test]
+    fn jumps_are_counted_up() {
+        let travel = jumps();
+        assert_eq!(travel.cost(0.0, 0.0), 100.0);
+        assert_eq!(travel.cost(10.0, 1000.0), 160.0);
+        assert_eq!(travel.cost(10.5, 0.0), 220.0);
+        let zero = Jumps {
+            secs_per_station: 0.0,
+            ..jumps()
+        };
+        assert_eq!(zero.cost(0.0, 0.0), MIN_SECS);
+        assert_floor(&travel);
+        assert_floor(&zero);
+    }
+
+    #[test]
+    fn jumps_add_arrival_weight() {
+        let travel = Jumps {
+            secs_per_ls: 0.5,
+            ..jumps()
+        };
+        assert_eq!(travel.cost(10.0, 10.0), 165.0);
+        assert_eq!(Neutron(travel).cost(10.0, 10.0), 165.0);
+        assert_eq!(
+            Supercruise(travel).cost(10.0, 100.0),
+            160.0 + 50.0 + 10.0 * SUPERCRUISE_SECS_PER_SQRT_LS
+        );
+    }
+
+    #[test]
+    fn neutron_boosts_only_long_trips() {
+        let travel = Neutron(jumps());
+        // 1 plain jump is faster than 1 boosted jump of 105 secs.
+        assert_eq!(travel.cost(5.0, 0.0), jumps().cost(5.0, 0.0));
+        // 4 boosted jumps of 105 secs instead of 16 plain jumps.
+        assert_eq!(travel.cost(160.0, 0.0), 4.0 * 105.0 + 100.0);
+        assert_floor(&Neutron(Jumps {
+            secs_per_station: 0.0,
+            ..jumps()
+        }));
+    }
+
+    #[test]
+    fn supercruise_adds_arrival_time() {
+        let travel = Supercruise(jumps());
+        assert_eq!(travel.cost(10.0, 0.0), 160.0);
+        assert_eq!(
+            travel.cost(10.0, 10_000.0),
+            160.0 + 100.0 * SUPERCRUISE_SECS_PER_SQRT_LS
+        );
+        assert_eq!(travel.cost(10.0, -1.0), 160.0);
+        assert_floor(&travel);
+    }
+}
@@ -0,0 +1,72 @@
+use serde::de::{Deserializer, Error as DeError};
+use serde::Deserialize;
+
+/// Unit of distance in config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// Light years, between systems.
+    Ly,
+    /// Light seconds, in a system.
+    Ls,
+}
+
+impl Unit {
+    fn name(self) -> &'static str {
+        match self {
+            Unit::Ly => "Ly",
+            Unit::Ls => "Ls",
+        }
+    }
+}
+
+/// Parse distance like `"600"`, `"600 Ly"` or `"1.2 kLy"` in `unit`.
+///
+/// Distance in the other unit is an error, because mixing them up is a common mistake.
+pub fn parse_distance(s: &str, unit: Unit) -> Result<f64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E')
+        .unwrap_or(s.len());
+    let (num, suffix) = s.split_at(split);
+    let value: f64 = num
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid distance: {:?}", s))?;
+
+    let (scale, suffix) = match suffix.strip_prefix('k') {
+        Some(rest) => (1000.0, rest),
+        None => (1.0, suffix),
+    };
+    if !suffix.is_empty() && !suffix.eq_ignore_ascii_case(unit.name()) {
+        return Err(format!("distance {:?} must be in {}", s, unit.name()));
+    }
+    if !value.is_finite() || value < 0.0 {
+        return Err(format!("invalid distance: {:?}", s));
+    }
+
+    Ok(value * scale)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDistance {
+    Number(f64),
+    Text(String),
+}
+
+fn deserialize_distance<'de, D: Deserializer<'de>>(d: D, unit: Unit) -> Result<f64, D::Error> {
+    match RawDistance::deserialize(d)? {
+        RawDistance::Number(v) => Ok(v),
+        RawDistance::Text(s) => parse_distance(&s, unit).map_err(D::Error::custom),
+    }
+}
+
+/// Deserialize number or string in light years.
+pub fn deserialize_ly<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    deserialize_distance(d, Unit::Ly)
+}
+
+/// Deserialize number or string in light seconds.
+pub fn deserialize_ls<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    deserialize_distance(d, Unit::Ls)
+}
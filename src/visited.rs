@@ -0,0 +1,167 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{rename, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::de::{Deserializer, Error as DeError};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use serde_json::to_writer_pretty;
+use tiny_fail::{ErrorMessageExt, Fail};
+
+use crate::state::StateFile;
+
+/// Format version of visited file.
+const VERSION: u32 = 1;
+
+fn state_file<P: AsRef<Path>>(path: P) -> StateFile {
+    StateFile::new(path, "visited", VERSION).pretty()
+}
+
+/// Docked stations with last visit time, keyed by market ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Visited {
+    visited: HashMap<u64, DateTime<Utc>>,
+}
+
+impl Visited {
+    pub fn new() -> Visited {
+        Visited {
+            visited: HashMap::new(),
+        }
+    }
+
+    /// Load visits saved by `save`. Returns empty `Visited` if `path` doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Visited, Fail> {
+        Ok(state_file(path).load()?.unwrap_or_default())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Fail> {
+        state_file(path).save(self)
+    }
+
+    /// Write visits as plain JSON for other tools, without format version nor backup.
+    ///
+    /// The file is replaced atomically, and can be read by `load`.
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> Result<(), Fail> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        let f = File::create(&tmp).err_msg(format!("can't create file: {:?}", tmp))?;
+        let mut w = BufWriter::new(f);
+        to_writer_pretty(&mut w, self).err_msg("can't encode visited stations")?;
+        w.flush()?;
+        w.get_ref()
+            .sync_all()
+            .err_msg(format!("can't write file: {:?}", tmp))?;
+        rename(&tmp, path).err_msg(format!("can't write file: {:?}", path))?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, id: u64, time: DateTime<Utc>) {
+        let last = self.visited.entry(id).or_insert(time);
+        if *last < time {
+            *last = time;
+        }
+    }
+
+    /// Add all visits in `other`, keeping later time for each station.
+    pub fn merge(&mut self, other: &Visited) {
+        for (&id, &time) in &other.visited {
+            self.add(id, time);
+        }
+    }
+
+    pub fn is_visited(&self, id: u64) -> bool {
+        self.visited.contains_key(&id)
+    }
+
+    pub fn last_visit(&self, id: u64) -> Option<DateTime<Utc>> {
+        self.visited.get(&id).cloned()
+    }
+
+    /// Stations last visited at or after `time`, in order of visit.
+    pub fn since(&self, time: DateTime<Utc>) -> Vec<(u64, DateTime<Utc>)> {
+        let mut list: Vec<(u64, DateTime<Utc>)> = self
+            .visited
+            .iter()
+            .filter(|(_, &t)| t >= time)
+            .map(|(&id, &t)| (id, t))
+            .collect();
+        list.sort_by_key(|&(_, t)| t);
+        list
+    }
+
+    /// Visits which are not in `other` or newer than in `other`.
+    pub fn newer_than(&self, other: &Visited) -> Vec<(u64, DateTime<Utc>)> {
+        let mut list: Vec<(u64, DateTime<Utc>)> = self
+            .visited
+            .iter()
+            .filter(|(id, t)| other.last_visit(**id).is_none_or(|o| o < **t))
+            .map(|(&id, &t)| (id, t))
+            .collect();
+        list.sort_by_key(|&(_, t)| t);
+        list
+    }
+
+    pub fn len(&self) -> usize {
+        self.visited.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visited.is_empty()
+    }
+}
+
+// Saved as `{"<market ID>": "<RFC 3339 time>"}`, ordered by market ID.
+impl Serialize for Visited {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let map: BTreeMap<u64, String> = self
+            .visited
+            .iter()
+            .map(|(&id, t)| (id, t.to_rfc3339()))
+            .collect();
+        map.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Visited {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Visited, D::Error> {
+        let map = BTreeMap::<u64, String>::deserialize(deserializer)?;
+        let mut visited = Visited::new();
+        for (id, t) in map {
+            let t = DateTime::parse_from_rfc3339(&t).map_err(D::Error::custom)?;
+            visited.add(id, t.with_timezone(&Utc));
+        }
+        Ok(visited)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file};
+    use std::process;
+
+    use chrono::TimeZone;
+    use serde_json::{from_str, json, Value};
+
+    use super::*;
+
+    #[test]
+    fn export_is_plain_json() {
+        let mut visited = Visited::new();
+        visited.add(128_000_000, Utc.timestamp_opt(1_600_000_000, 0).unwrap());
+        let path = temp_dir().join(format!("near-old-stations-visited-{}.json", process::id()));
+        visited.export(&path).unwrap();
+
+        let text = read_to_string(&path).unwrap();
+        assert_eq!(
+            from_str::<Value>(&text).unwrap(),
+            json!({"128000000": "2020-09-13T12:26:40+00:00"})
+        );
+        assert_eq!(Visited::load(&path).unwrap(), visited);
+        remove_file(&path).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+    }
+}
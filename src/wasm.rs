@@ -0,0 +1,84 @@
+//! JavaScript API of the search core. Built with `wasm` feature and without `native` feature.
+//!
+//! See `web/README.md` for usage.
+
+use std::collections::HashMap;
+use std::io::{BufReader, Cursor};
+
+use chrono::{TimeZone, Utc};
+use serde_json::to_string;
+use tiny_fail::Fail;
+use wasm_bindgen::prelude::*;
+
+use crate::batch::BatchResult;
+use crate::filter::{Days, Filter, Filters};
+use crate::journal::parse_journal;
+use crate::searcher::Searcher;
+use crate::stations::parse_stations;
+
+/// Stations loaded from dumps given by user.
+#[wasm_bindgen]
+pub struct WebSearcher {
+    searcher: Searcher<Filters>,
+}
+
+#[wasm_bindgen]
+impl WebSearcher {
+    /// Load stations and systems dumps, optionally gzip compressed.
+    ///
+    /// Stations within `max_dist` Ly and outdated `days` or more in any category are searched.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        stations_dump: Vec<u8>,
+        systems_dump: Vec<u8>,
+        max_dist: f64,
+        days: i64,
+    ) -> Result<WebSearcher, JsValue> {
+        let stations = parse_stations(
+            BufReader::new(Cursor::new(stations_dump)),
+            BufReader::new(Cursor::new(systems_dump)),
+            true,
+        )
+        .map_err(to_js)?;
+
+        let mut filters = Filters::new();
+        filters.add(Filter::Dist(max_dist));
+        for &d in &[
+            Days::Information(days),
+            Days::Market(days),
+            Days::Shipyard(days),
+            Days::Outfitting(days),
+        ] {
+            filters.add(Filter::Days(d, HashMap::new()));
+        }
+        filters.add(Filter::Outdated);
+
+        Ok(WebSearcher {
+            searcher: Searcher::new(stations, filters),
+        })
+    }
+
+    /// Search around the last location in `journal`, and return results as JSON.
+    ///
+    /// `now` is milliseconds since UNIX epoch, such as `Date.now()`.
+    /// Result has the same format as an element of `--origins-from --json` output.
+    pub fn search(&self, journal: &str, now: f64, limit: usize) -> Result<String, JsValue> {
+        let (location, visited) = parse_journal(journal.as_bytes()).map_err(to_js)?;
+        let location =
+            location.ok_or_else(|| JsValue::from_str("no location event in the journal"))?;
+        let now = Utc
+            .timestamp_millis_opt(now as i64)
+            .single()
+            .ok_or_else(|| JsValue::from_str("'now' is out of range"))?;
+
+        let records = self
+            .searcher
+            .search_at_time(location.star_pos, &visited, now);
+        let result = BatchResult::new(&location.star_system, &records, limit);
+        to_string(&result).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+fn to_js(e: Fail) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}